@@ -0,0 +1,43 @@
+#!/bin/bash
+
+# Records the pages in tests/fixtures/timetable from timetable.unsw.edu.au:
+# the year page, the Kensington page of each subject area given (ACCT, COMP
+# and MATH by default) and every course page those link to. Scripts, styles
+# and comments are stripped to keep the pages small. Run ./update-goldens.sh
+# afterwards and review the diff of tests/fixtures and tests/golden.
+#
+# Usage: ./record-fixtures.sh [year] [subject code...]
+
+set -euo pipefail
+
+year="${1:-2025}"
+shift || true
+subjects=("$@")
+if [ "${#subjects[@]}" = 0 ]; then
+    subjects=(ACCT COMP MATH)
+fi
+
+base_url="https://timetable.unsw.edu.au/$year"
+dir="$(dirname "$0")/tests/fixtures/timetable/$year"
+mkdir -p "$dir"
+
+# Fetches $base_url/$1 into $dir/$2, without scripts, styles and comments.
+record() {
+    echo "recording $base_url/$1"
+    # Only replaces the fixture once the whole page has been fetched.
+    local page
+    page="$(curl --fail --silent --show-error "$base_url/$1")"
+    perl -0pe 's/<script\b.*?<\/script>//gis; s/<style\b.*?<\/style>//gis; s/<!--.*?-->//gs' \
+        <<<"$page" >"$dir/$2"
+    # Stay well below the request rate the scraper itself uses.
+    sleep 1
+}
+
+record "" index.html
+for subject in "${subjects[@]}"; do
+    record "${subject}KENS.html" "${subject}KENS.html"
+    for course in $(grep -oE "href=\"${subject}[0-9]{4}\.html\"" "$dir/${subject}KENS.html" |
+        sed -E 's/href="(.*)"/\1/' | sort -u); do
+        record "$course" "$course"
+    done
+done
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS Occurrences CASCADE;
\ No newline at end of file
@@ -0,0 +1,13 @@
+CREATE TABLE Occurrences (
+    "id"                              VARCHAR(520) PRIMARY KEY,
+    "time_id"                         VARCHAR(500) NOT NULL,
+    "class_id"                        VARCHAR(255) NOT NULL,
+    "week"                            INTEGER NOT NULL,
+    "date"                            DATE NOT NULL,
+    "starts_at"                       TIMESTAMPTZ NOT NULL,
+    "ends_at"                         TIMESTAMPTZ NOT NULL,
+    "location"                        VARCHAR(255) NOT NULL,
+    FOREIGN KEY ("time_id") REFERENCES Times("id") ON DELETE CASCADE
+);
+
+CREATE INDEX occurrences_location_date ON Occurrences("location", "date");
\ No newline at end of file
@@ -0,0 +1,78 @@
+-- SQLite version of the Courses, Classes, Times and Occurrences tables in sql/,
+-- plus the subject areas courses are listed under, for `export sqlite`.
+-- Postgres arrays (Courses.modes) are stored as JSON arrays, as is terms.
+-- Dates and timestamps are ISO 8601 text, e.g. 2025-03-12T09:00:00+11:00.
+
+CREATE TABLE SubjectAreas (
+    "subject_code"                    TEXT PRIMARY KEY,
+    "subject_name"                    TEXT NOT NULL,
+    "school"                          TEXT NOT NULL,
+    "url"                             TEXT NOT NULL
+);
+
+CREATE TABLE Courses (
+    "course_id"                       TEXT PRIMARY KEY,
+    "course_code"                     TEXT,
+    "course_name"                     TEXT NOT NULL,
+    "uoc"                             INTEGER NOT NULL,
+    "faculty"                         TEXT,
+    "school"                          TEXT,
+    "campus"                          TEXT,
+    "career"                          TEXT,
+    "terms"                           TEXT,
+    "modes"                           TEXT,
+    -- NULL if the course's subject area wasn't scraped.
+    "subject_code"                    TEXT,
+    FOREIGN KEY ("subject_code") REFERENCES SubjectAreas("subject_code") ON DELETE CASCADE
+);
+
+CREATE TABLE Classes (
+    "class_id"                        TEXT PRIMARY KEY,
+    "career"                          TEXT,
+    "course_id"                       TEXT NOT NULL,
+    "section"                         TEXT NOT NULL,
+    "term"                            TEXT NOT NULL,
+    "year"                            TEXT NOT NULL,
+    "activity"                        TEXT NOT NULL,
+    "status"                          TEXT CHECK ("status" IN ('Open', 'Closed', 'Full', 'On Hold')),
+    "course_enrolment"                TEXT NOT NULL,
+    "offering_period"                 TEXT NOT NULL,
+    "meeting_dates"                   TEXT NOT NULL,
+    "census_date"                     TEXT NOT NULL,
+    "consent"                         TEXT NOT NULL,
+    "mode"                            TEXT NOT NULL,
+    "class_notes"                     TEXT,
+    FOREIGN KEY ("course_id") REFERENCES Courses("course_id") ON DELETE CASCADE
+);
+
+CREATE TABLE Times (
+    "id"                              TEXT PRIMARY KEY,
+    "class_id"                        TEXT NOT NULL,
+    "day"                             TEXT NOT NULL,
+    "instructor"                      TEXT,
+    "location"                        TEXT NOT NULL,
+    "time"                            TEXT NOT NULL,
+    "weeks"                           TEXT NOT NULL,
+    "career"                          TEXT,
+    FOREIGN KEY ("class_id") REFERENCES Classes("class_id") ON DELETE CASCADE
+);
+
+-- Only filled in with `--occurrences`.
+CREATE TABLE Occurrences (
+    "id"                              TEXT PRIMARY KEY,
+    "time_id"                         TEXT NOT NULL,
+    "class_id"                        TEXT NOT NULL,
+    "week"                            INTEGER NOT NULL,
+    "date"                            TEXT NOT NULL,
+    "starts_at"                       TEXT NOT NULL,
+    "ends_at"                         TEXT NOT NULL,
+    "location"                        TEXT NOT NULL,
+    FOREIGN KEY ("time_id") REFERENCES Times("id") ON DELETE CASCADE
+);
+
+CREATE INDEX courses_course_code ON Courses("course_code");
+CREATE INDEX courses_subject_code ON Courses("subject_code");
+CREATE INDEX classes_course_id ON Classes("course_id");
+CREATE INDEX classes_term ON Classes("term");
+CREATE INDEX times_class_id ON Times("class_id");
+CREATE INDEX occurrences_location_date ON Occurrences("location", "date");
@@ -0,0 +1,293 @@
+use scraper::{ElementRef, Html};
+
+use crate::{
+    ScrapingContext, Year,
+    parse_error::{Expectation, ParseError, ParseStage},
+    selector_profile::SelectorProfile,
+    text_manipulators::{extract_text, get_html_link_to_page, subject_code},
+};
+
+/// The outcome of checking one structural invariant of one page.
+#[derive(Debug)]
+pub struct CanaryCheck {
+    pub url: String,
+    pub description: String,
+    pub result: Result<(), ParseError>,
+}
+
+impl CanaryCheck {
+    fn new(url: &str, description: &str, result: Result<(), ParseError>) -> Self {
+        Self {
+            url: url.to_string(),
+            description: description.to_string(),
+            result,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Fetches a small set of known pages and checks that they still have the
+/// structure the scrapers rely on, so that we find out about timetable website
+/// redesigns before a scheduled scrape breaks.
+///
+/// Checks the year's school area page, the subject area page of each of the
+/// given courses, and the course pages themselves.
+pub async fn run_canary(
+    year: Year,
+    course_codes: &[String],
+    ctx: &ScrapingContext,
+) -> anyhow::Result<Vec<CanaryCheck>> {
+    let mut checks = Vec::new();
+    let profile = &ctx.selector_profile;
+
+    let school_area_url = ctx.scraping_config.get_timetable_api_url_for_year(year);
+    let school_area_html = ctx.fetch_url_body(&school_area_url).await?;
+    let subject_area_links = {
+        let document = Html::parse_document(&school_area_html);
+        check_school_area_page(&document, &school_area_url, profile, &mut checks)
+    };
+
+    for course_code in course_codes {
+        let course_code = course_code.trim().to_uppercase();
+        let subject_code = subject_code(&course_code).unwrap_or(&course_code);
+        match subject_area_links
+            .iter()
+            .find(|(code, _href)| code == subject_code)
+        {
+            Some((_code, href)) => {
+                let url = get_html_link_to_page(year, href, ctx);
+                let html = ctx.fetch_url_body(&url).await?;
+                let document = Html::parse_document(&html);
+                check_subject_area_page(&document, &url, &course_code, profile, &mut checks);
+            }
+            None => checks.push(CanaryCheck::new(
+                &school_area_url,
+                &format!("lists subject area {}", subject_code),
+                Err(ParseError::new(
+                    ParseStage::SchoolArea,
+                    &school_area_url,
+                    Expectation::Structure(format!("a row for subject area {}", subject_code)),
+                    "",
+                )),
+            )),
+        }
+
+        let url = get_html_link_to_page(year, &format!("{}.html", course_code), ctx);
+        let html = ctx.fetch_url_body(&url).await?;
+        let document = Html::parse_document(&html);
+        check_course_page(&document, &url, profile, &mut checks);
+    }
+
+    Ok(checks)
+}
+
+/// Returns the (subject code, href) of each subject area listed.
+fn check_school_area_page(
+    document: &Html,
+    url: &str,
+    profile: &SelectorProfile,
+    checks: &mut Vec<CanaryCheck>,
+) -> Vec<(String, String)> {
+    let selectors = &profile.school_area;
+    let stage = ParseStage::SchoolArea;
+
+    let rows = document.select(&selectors.row).collect::<Vec<_>>();
+    checks.push(CanaryCheck::new(
+        url,
+        "has subject area rows",
+        require_any(&rows, || {
+            ParseError::missing_selector(
+                stage,
+                url,
+                selectors.row.as_str(),
+                document.root_element(),
+            )
+        }),
+    ));
+
+    let mut links = Vec::new();
+    let mut row_result = Ok(());
+    for row in &rows {
+        let code = row.select(&selectors.code).next().map(extract_text);
+        let href = row
+            .select(&selectors.link)
+            .next()
+            .and_then(|link| link.value().attr("href"));
+        let has_school = row.select(&selectors.school).next().is_some();
+        match (code, href, has_school) {
+            (Some(code), Some(href), true) => links.push((code.trim().to_string(), href.into())),
+            _ => {
+                row_result = Err(ParseError::in_element(
+                    stage,
+                    url,
+                    Expectation::Structure(
+                        "a subject code, link and school in every row".to_string(),
+                    ),
+                    *row,
+                ));
+                break;
+            }
+        }
+    }
+    checks.push(CanaryCheck::new(
+        url,
+        "every row has a subject code, link and school",
+        row_result,
+    ));
+
+    links
+}
+
+fn check_subject_area_page(
+    document: &Html,
+    url: &str,
+    course_code: &str,
+    profile: &SelectorProfile,
+    checks: &mut Vec<CanaryCheck>,
+) {
+    let selectors = &profile.subject_area;
+    let stage = ParseStage::SubjectArea;
+
+    let careers = document
+        .select(&selectors.career)
+        .filter(|career| !extract_text(*career).trim().is_empty())
+        .collect::<Vec<_>>();
+    checks.push(CanaryCheck::new(
+        url,
+        "has career headings",
+        require_any(&careers, || {
+            ParseError::missing_selector(
+                stage,
+                url,
+                selectors.career.as_str(),
+                document.root_element(),
+            )
+        }),
+    ));
+
+    let course_row = document.select(&selectors.row).find(|row| {
+        row.select(&selectors.code)
+            .next()
+            .is_some_and(|code| extract_text(code).trim() == course_code)
+    });
+    let course_row_result = match course_row {
+        Some(row) => match row.select(&selectors.uoc).next() {
+            Some(uoc) if extract_text(uoc).trim().parse::<i32>().is_ok() => Ok(()),
+            _ => Err(ParseError::in_element(
+                stage,
+                url,
+                Expectation::Structure(format!(
+                    "a whole number of UOC in '{}'",
+                    selectors.uoc.as_str()
+                )),
+                row,
+            )),
+        },
+        None => Err(ParseError::in_element(
+            stage,
+            url,
+            Expectation::Structure(format!("a row for {}", course_code)),
+            document.root_element(),
+        )),
+    };
+    checks.push(CanaryCheck::new(
+        url,
+        &format!("lists {} with its UOC", course_code),
+        course_row_result,
+    ));
+}
+
+fn check_course_page(
+    document: &Html,
+    url: &str,
+    profile: &SelectorProfile,
+    checks: &mut Vec<CanaryCheck>,
+) {
+    let selectors = &profile.course;
+    let labels = &profile.labels;
+    let stage = ParseStage::Course;
+
+    let labels_of = |info_box: ElementRef| {
+        info_box
+            .select(&selectors.label)
+            .map(|label| extract_text(label).trim().replace("\u{a0}", ""))
+            .collect::<Vec<_>>()
+    };
+    let info_boxes = document.select(&selectors.info_box).collect::<Vec<_>>();
+    checks.push(CanaryCheck::new(
+        url,
+        "has info boxes",
+        require_any(&info_boxes, || {
+            ParseError::missing_selector(
+                stage,
+                url,
+                selectors.info_box.as_str(),
+                document.root_element(),
+            )
+        }),
+    ));
+
+    let mut expect_info_box = |description: &str, first_label: &str, expected_labels: &[&str]| {
+        let info_box = info_boxes.iter().find(|info_box| {
+            labels_of(**info_box).first().map(String::as_str) == Some(first_label)
+        });
+        let result = match info_box {
+            Some(info_box) => {
+                let labels = labels_of(*info_box);
+                match expected_labels
+                    .iter()
+                    .find(|expected| !labels.iter().any(|label| label == *expected))
+                {
+                    Some(missing) => Err(ParseError::in_element(
+                        stage,
+                        url,
+                        Expectation::Label(missing.to_string()),
+                        *info_box,
+                    )),
+                    None => Ok(()),
+                }
+            }
+            None => Err(ParseError::in_element(
+                stage,
+                url,
+                Expectation::Label(first_label.to_string()),
+                document.root_element(),
+            )),
+        };
+        checks.push(CanaryCheck::new(url, description, result));
+    };
+    expect_info_box(
+        "has a course info box with the expected labels",
+        &labels.faculty,
+        &[
+            &labels.faculty,
+            &labels.school,
+            &labels.campus,
+            &labels.career,
+        ],
+    );
+    expect_info_box(
+        "has a class info box with the expected labels",
+        &labels.class_nbr,
+        &[
+            &labels.class_nbr,
+            &labels.activity,
+            &labels.section,
+            &labels.teaching_period,
+            &labels.status,
+            &labels.enrols_capacity,
+            &labels.offering_period,
+        ],
+    );
+}
+
+fn require_any<T>(items: &[T], error: impl FnOnce() -> ParseError) -> Result<(), ParseError> {
+    if items.is_empty() {
+        Err(error())
+    } else {
+        Ok(())
+    }
+}
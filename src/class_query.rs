@@ -0,0 +1,348 @@
+use std::io::Write;
+
+use chrono::{NaiveTime, Weekday};
+use serde::Serialize;
+
+use crate::{
+    Class, Course, Time, occurrences::parse_time_range, rows::ClassRow, utils::write_aligned,
+};
+
+/// Filters on classes, e.g. for "COMP courses in T2 with open in-person
+/// tutorials on Fridays". A class matches if it matches every filter given.
+/// Text filters ignore case, and `-` matches a space, so `in-person` matches
+/// `In Person`.
+#[derive(Debug, Clone, Default)]
+pub struct ClassQuery {
+    /// Course codes or prefixes of them, e.g. `COMP1511` or `COMP`. Any of
+    /// them may match.
+    pub courses: Vec<String>,
+    pub term: Option<String>,
+    /// e.g. `Tutorial`.
+    pub activity: Option<String>,
+    /// e.g. `Open`.
+    pub status: Option<String>,
+    /// e.g. `In Person`.
+    pub mode: Option<String>,
+    /// The campus of the course, e.g. `Sydney`.
+    pub campus: Option<String>,
+    /// Only classes that meet on this day.
+    pub day: Option<Weekday>,
+    /// Only classes that meet at or after this time of day.
+    pub after: Option<NaiveTime>,
+    /// Only classes that meet until this time of day at the latest.
+    pub before: Option<NaiveTime>,
+    /// Only classes with at least this many free seats. Classes whose
+    /// enrolment isn't formatted like `400/450` don't match.
+    pub min_free_seats: Option<i32>,
+}
+
+/// A class that matched a [`ClassQuery`].
+#[derive(Debug, Serialize)]
+pub struct ClassMatch<'a> {
+    pub course_code: &'a str,
+    pub course_name: &'a str,
+    pub campus: Option<&'a str>,
+    pub class_id: &'a str,
+    pub term: &'a str,
+    pub activity: &'a str,
+    pub section: &'a str,
+    pub status: &'a str,
+    pub mode: &'a str,
+    pub course_enrolment: &'a str,
+    pub free_seats: Option<i32>,
+    /// The times within the query's day and time window, or all times of the
+    /// class if it has neither.
+    pub times: Vec<MatchedTime<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MatchedTime<'a> {
+    pub day: &'a str,
+    pub time: &'a str,
+    pub location: &'a str,
+    pub weeks: &'a str,
+    pub instructor: Option<&'a str>,
+}
+
+impl ClassQuery {
+    /// The classes of `courses` matching the query, in the order of `courses`.
+    pub fn run<'a>(&self, courses: &'a [Course]) -> Vec<ClassMatch<'a>> {
+        courses
+            .iter()
+            .filter(|course| self.matches_course(course))
+            .flat_map(|course| {
+                course
+                    .classes
+                    .iter()
+                    .filter_map(move |class| self.match_class(course, class))
+            })
+            .collect()
+    }
+
+    fn matches_course(&self, course: &Course) -> bool {
+        (self.courses.is_empty()
+            || self.courses.iter().any(|prefix| {
+                course
+                    .course_code
+                    .to_uppercase()
+                    .starts_with(&prefix.trim().to_uppercase())
+            }))
+            && text_matches(&self.campus, course.campus.as_deref().unwrap_or_default())
+    }
+
+    fn match_class<'a>(&self, course: &'a Course, class: &'a Class) -> Option<ClassMatch<'a>> {
+        let free_seats = ClassRow::new(class)
+            .enrolment()
+            .map(|(enrolled, capacity)| capacity - enrolled);
+        let matches = text_matches(&self.term, &class.term)
+            && text_matches(&self.activity, &class.activity)
+            && text_matches(&self.status, &class.status)
+            && text_matches(&self.mode, &class.mode)
+            && self
+                .min_free_seats
+                .is_none_or(|min_free_seats| free_seats.is_some_and(|free| free >= min_free_seats));
+        if !matches {
+            return None;
+        }
+        let times = class
+            .times
+            .iter()
+            .flatten()
+            .filter(|time| self.matches_time(time))
+            .map(|time| MatchedTime {
+                day: &time.day,
+                time: &time.time,
+                location: &time.location,
+                weeks: &time.weeks,
+                instructor: time.instructor.as_deref(),
+            })
+            .collect::<Vec<_>>();
+        if self.filters_times() && times.is_empty() {
+            return None;
+        }
+        Some(ClassMatch {
+            course_code: &course.course_code,
+            course_name: &course.course_name,
+            campus: course.campus.as_deref(),
+            class_id: &class.class_id,
+            term: &class.term,
+            activity: &class.activity,
+            section: &class.section,
+            status: &class.status,
+            mode: &class.mode,
+            course_enrolment: &class.course_enrolment,
+            free_seats,
+            times,
+        })
+    }
+
+    fn filters_times(&self) -> bool {
+        self.day.is_some() || self.after.is_some() || self.before.is_some()
+    }
+
+    /// Times whose day or time range can't be parsed (e.g. `TBA`) only match
+    /// if there is no day or time window to check.
+    fn matches_time(&self, time: &Time) -> bool {
+        if let Some(day) = self.day
+            && time.day.parse::<Weekday>().ok() != Some(day)
+        {
+            return false;
+        }
+        if self.after.is_none() && self.before.is_none() {
+            return true;
+        }
+        let Ok((start, end)) = parse_time_range(&time.time) else {
+            return false;
+        };
+        self.after.is_none_or(|after| start >= after)
+            && self.before.is_none_or(|before| end <= before)
+    }
+}
+
+fn normalise(text: &str) -> String {
+    text.trim().to_lowercase().replace('-', " ")
+}
+
+fn text_matches(filter: &Option<String>, value: &str) -> bool {
+    filter
+        .as_ref()
+        .is_none_or(|filter| normalise(filter) == normalise(value))
+}
+
+const COLUMNS: [&str; 9] = [
+    "course",
+    "term",
+    "activity",
+    "section",
+    "status",
+    "mode",
+    "enrolment",
+    "free",
+    "times",
+];
+
+/// One line per class, with its times joined, for the table and CSV output.
+fn summary_row(class_match: &ClassMatch) -> [String; 9] {
+    let times = class_match
+        .times
+        .iter()
+        .map(|time| format!("{} {} {}", time.day, time.time, time.location))
+        .collect::<Vec<_>>()
+        .join("; ");
+    [
+        class_match.course_code.to_string(),
+        class_match.term.to_string(),
+        class_match.activity.to_string(),
+        class_match.section.to_string(),
+        class_match.status.to_string(),
+        class_match.mode.to_string(),
+        class_match.course_enrolment.to_string(),
+        class_match
+            .free_seats
+            .map(|free_seats| free_seats.to_string())
+            .unwrap_or_default(),
+        times,
+    ]
+}
+
+/// Writes `matches` as a table with aligned columns, for reading in a terminal.
+pub fn write_class_table(writer: &mut impl Write, matches: &[ClassMatch]) -> anyhow::Result<()> {
+    let header = COLUMNS.map(|column| column.to_uppercase());
+    let rows = std::iter::once(header)
+        .chain(matches.iter().map(summary_row))
+        .collect::<Vec<_>>();
+    write_aligned(writer, &rows, "")?;
+    Ok(())
+}
+
+/// Writes `matches` as CSV, one row per class.
+pub fn write_class_csv(writer: impl Write, matches: &[ClassMatch]) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(COLUMNS)?;
+    for class_match in matches {
+        writer.write_record(summary_row(class_match))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{self, course, time};
+
+    fn class(
+        course_code: &str,
+        section: &str,
+        activity: &str,
+        course_enrolment: &str,
+        times: Vec<Time>,
+    ) -> Class {
+        Class {
+            activity: activity.to_string(),
+            course_enrolment: course_enrolment.to_string(),
+            ..test_fixtures::class(course_code, section, "T2", times)
+        }
+    }
+
+    fn courses() -> Vec<Course> {
+        vec![
+            course(
+                "COMP1511",
+                vec![
+                    class(
+                        "COMP1511",
+                        "A",
+                        "Lecture",
+                        "400/450",
+                        vec![time("Fri", "09:00 - 11:00")],
+                    ),
+                    class(
+                        "COMP1511",
+                        "F09A",
+                        "Tutorial",
+                        "20/24",
+                        vec![time("Mon", "09:00 - 11:00"), time("Fri", "09:00 - 11:00")],
+                    ),
+                    class(
+                        "COMP1511",
+                        "F15A",
+                        "Tutorial",
+                        "24/24",
+                        vec![time("Fri", "15:00 - 18:00")],
+                    ),
+                    class("COMP1511", "T", "Tutorial", "TBA", vec![time("TBA", "TBA")]),
+                ],
+            ),
+            course(
+                "MATH1131",
+                vec![class(
+                    "MATH1131",
+                    "F10A",
+                    "Tutorial",
+                    "1/24",
+                    vec![time("Fri", "10:00 - 11:00")],
+                )],
+            ),
+        ]
+    }
+
+    fn sections(matches: &[ClassMatch]) -> Vec<String> {
+        matches
+            .iter()
+            .map(|class_match| format!("{} {}", class_match.course_code, class_match.section))
+            .collect()
+    }
+
+    #[test]
+    fn test_query_open_in_person_friday_tutorials() {
+        let courses = courses();
+        let query = ClassQuery {
+            courses: vec!["comp".to_string()],
+            term: Some("t2".to_string()),
+            activity: Some("tutorial".to_string()),
+            status: Some("open".to_string()),
+            mode: Some("in-person".to_string()),
+            campus: Some("Sydney".to_string()),
+            day: Some(Weekday::Fri),
+            ..ClassQuery::default()
+        };
+        let matches = query.run(&courses);
+        // The TBA tutorial can't be placed on a day.
+        assert_eq!(sections(&matches), ["COMP1511 F09A", "COMP1511 F15A"]);
+        // Only the Friday time of F09A is listed.
+        assert_eq!(matches[0].times.len(), 1);
+        assert_eq!(matches[0].free_seats, Some(4));
+    }
+
+    #[test]
+    fn test_query_time_window_and_free_seats() {
+        let courses = courses();
+        let query = ClassQuery {
+            after: Some(NaiveTime::from_hms_opt(9, 0, 0).unwrap()),
+            before: Some(NaiveTime::from_hms_opt(12, 0, 0).unwrap()),
+            min_free_seats: Some(1),
+            ..ClassQuery::default()
+        };
+        assert_eq!(
+            sections(&query.run(&courses)),
+            ["COMP1511 A", "COMP1511 F09A", "MATH1131 F10A"]
+        );
+    }
+
+    #[test]
+    fn test_write_class_table() {
+        let courses = courses();
+        let query = ClassQuery {
+            courses: vec!["MATH1131".to_string()],
+            ..ClassQuery::default()
+        };
+        let mut table = Vec::new();
+        write_class_table(&mut table, &query.run(&courses)).unwrap();
+        assert_eq!(
+            String::from_utf8(table).unwrap(),
+            "COURSE    TERM  ACTIVITY  SECTION  STATUS  MODE       ENROLMENT  FREE  TIMES\n\
+             MATH1131  T2    Tutorial  F10A     Open    In Person  1/24       23    Fri 10:00 - 11:00 Quadrangle G040 (K-E15-G040)\n"
+        );
+    }
+}
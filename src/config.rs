@@ -8,10 +8,16 @@ use crate::Year;
 #[derive(Debug, Deserialize)]
 pub struct ScrapingEnv {
     timetable_api_url: String,
+    /// Optional JSON file overriding fields of the default selector profile.
+    selector_profile_path: Option<String>,
 }
 
+/// The live timetable website.
+pub const DEFAULT_TIMETABLE_API_URL: &str = "https://timetable.unsw.edu.au/year/";
+
 pub struct ScrapingConfig {
     timetable_api_url: String,
+    selector_profile_path: Option<String>,
 }
 
 impl ScrapingConfig {
@@ -19,9 +25,35 @@ impl ScrapingConfig {
         let scraping_env = ScrapingEnv::load_from_env()?;
         Ok(Self {
             timetable_api_url: scraping_env.timetable_api_url,
+            selector_profile_path: scraping_env.selector_profile_path,
         })
     }
 
+    /// A config for a timetable mirror other than the one in the env vars, e.g.
+    /// a local mock. `timetable_api_url` should contain `year`, which is
+    /// replaced with the year being scraped.
+    pub fn from_timetable_api_url(timetable_api_url: &str) -> Self {
+        Self {
+            timetable_api_url: timetable_api_url.to_string(),
+            selector_profile_path: None,
+        }
+    }
+
+    /// Uses the JSON file at `selector_profile_path` to override fields of the
+    /// default selector profile.
+    pub fn with_selector_profile_path(mut self, selector_profile_path: Option<String>) -> Self {
+        self.selector_profile_path = selector_profile_path;
+        self
+    }
+
+    pub fn selector_profile_path(&self) -> Option<&str> {
+        self.selector_profile_path.as_deref()
+    }
+
+    pub fn timetable_api_url(&self) -> &str {
+        &self.timetable_api_url
+    }
+
     pub fn get_timetable_api_url_for_year(&self, year: Year) -> String {
         self.timetable_api_url.replace("year", &year.to_string())
     }
@@ -38,12 +70,23 @@ const YEAR: &str = "YEAR";
 const COURSE_CODE: &str = "CODE";
 
 impl TimetableUrlRegex {
-    pub fn new() -> anyhow::Result<Self> {
-        let year_only_regex = Regex::new(&format!(
-            r"^https?://timetable\.unsw\.edu\.au/(?P<{YEAR}>\d{{4}})"
-        ))?;
+    /// `timetable_api_url` is the same `year` template as in `ScrapingConfig`,
+    /// e.g. `https://timetable.unsw.edu.au/year/`.
+    pub fn new(timetable_api_url: &str) -> anyhow::Result<Self> {
+        let (prefix, _) = timetable_api_url
+            .split_once("year")
+            .ok_or_else(|| anyhow::anyhow!("timetable api url must contain 'year'"))?;
+        let prefix = regex::escape(prefix);
+        // Accept both http and https, since UNSW links to both.
+        let prefix = match prefix.split_once("://") {
+            Some((scheme, rest)) if scheme == "http" || scheme == "https" => {
+                format!("https?://{rest}")
+            }
+            _ => prefix,
+        };
+        let year_only_regex = Regex::new(&format!(r"^{prefix}(?P<{YEAR}>\d{{4}})"))?;
         let course_url_regex = Regex::new(&format!(
-            r"^https?://timetable\.unsw\.edu\.au/(?P<{YEAR}>\d{{4}})/(?P<{COURSE_CODE}>[A-Z]{{4}}\d{{4}})\.html$"
+            r"^{prefix}(?P<{YEAR}>\d{{4}})/(?P<{COURSE_CODE}>[A-Z]{{4}}\d{{4}})\.html$"
         ))?;
         Ok(Self {
             year_only_regex,
@@ -100,7 +143,8 @@ mod tests {
 
     #[test]
     fn test_timetable_url_regex() {
-        let timetable_url_regex = TimetableUrlRegex::new().unwrap();
+        let timetable_url_regex =
+            TimetableUrlRegex::new("https://timetable.unsw.edu.au/year/").unwrap();
         assert_eq!(
             2024,
             timetable_url_regex
@@ -119,6 +163,19 @@ mod tests {
                 .extract_course_code("https://timetable.unsw.edu.au/2024/COMP1511.html")
                 .unwrap()
         );
+
+        let mirror_url_regex = TimetableUrlRegex::new("http://127.0.0.1:8080/year/").unwrap();
+        assert_eq!(
+            "COMP1511",
+            mirror_url_regex
+                .extract_course_code("http://127.0.0.1:8080/2024/COMP1511.html")
+                .unwrap()
+        );
+        assert!(
+            mirror_url_regex
+                .extract_year("https://timetable.unsw.edu.au/2024")
+                .is_err()
+        );
     }
 }
 
@@ -1,12 +1,22 @@
 use derive_new::new;
 use rayon::prelude::*;
-use scraper::Selector;
-use serde::Serialize;
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::Span;
 
-use crate::{ScrapingContext, text_manipulators::extract_text};
+use crate::{
+    ScrapingContext, Year,
+    parse_error::{Expectation, ParseError, ParseStage},
+    scrape_year::fetch_url_body,
+    selector_profile::SelectorProfile,
+    text_manipulators::{extract_text, get_html_link_to_page},
+};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Course {
     pub course_id: String,
     pub course_code: String,
@@ -23,7 +33,7 @@ pub struct Course {
     pub classes: Vec<Class>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Class {
     pub course_id: String,
     pub career: String,
@@ -43,7 +53,7 @@ pub struct Class {
     pub class_notes: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Time {
     pub career: String,
     pub day: String,
@@ -53,7 +63,18 @@ pub struct Time {
     pub instructor: Option<String>,
 }
 
-#[derive(Debug, new)]
+impl Time {
+    /// A unique id for this time of `class`, used as the primary key of the
+    /// times table.
+    pub fn id(&self, class: &Class) -> String {
+        format!(
+            "{}{}{}{}{}",
+            &class.class_id, &self.day, &self.location, &self.time, &self.weeks
+        )
+    }
+}
+
+#[derive(Debug, new, Serialize)]
 pub struct PartialCourse {
     pub course_code: String,
     pub course_name: String,
@@ -62,158 +83,305 @@ pub struct PartialCourse {
     pub url: String,
 }
 
+/// What the course page parser saw while completing a course, for debugging
+/// courses that parse wrongly.
+#[derive(Debug, Default, Serialize)]
+pub struct CourseExplanation {
+    /// Whether any course info box was for the career of the course being
+    /// completed. If not, the course has no faculty, school, campus or classes.
+    pub found_career_info_box: bool,
+    /// Careers of the info boxes that were skipped because they didn't match the
+    /// career of the course being completed.
+    pub skipped_info_boxes: Vec<SkippedInfoBox>,
+    /// One entry per "Class Nbr" info box that was parsed, in page order.
+    pub classes: Vec<ClassExplanation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedInfoBox {
+    /// Position of the info box among all `td.formBody td.formBody` cells on the page.
+    pub info_box_index: usize,
+    /// The career the info box was for, or `None` if this is a class info box
+    /// that belongs to a skipped career.
+    pub career: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ClassExplanation {
+    /// The label/data pairs `parse_class_info` saw, in page order.
+    pub label_data_pairs: Vec<(String, String)>,
+    /// The cells that were passed to `parse_meeting_info`.
+    pub meeting_info: Vec<String>,
+}
+
 impl PartialCourse {
+    /// A partial course for a single course page, for when the subject area page
+    /// wasn't scraped. The course name and UOC are only listed on subject area
+    /// pages, so they are left blank.
+    pub fn for_course_code(
+        course_code: &str,
+        career: &str,
+        year: Year,
+        ctx: &ScrapingContext,
+    ) -> Self {
+        let course_code = course_code.trim().to_uppercase();
+        let url = get_html_link_to_page(year, &format!("{course_code}.html"), ctx);
+        Self::new(course_code, String::new(), career.to_string(), 0, url)
+    }
+
     pub async fn complete(self, ctx: &ScrapingContext) -> anyhow::Result<Course> {
-        let html = ctx.request_client.fetch_url_body(&self.url, ctx).await?;
+        let (course, _explanation) = self
+            .complete_impl(ctx, false, &CancellationToken::new())
+            .await?;
+        Ok(course)
+    }
+
+    /// Like `complete`, but also records what the parser saw.
+    pub async fn complete_with_explanation(
+        self,
+        ctx: &ScrapingContext,
+    ) -> anyhow::Result<(Course, CourseExplanation)> {
+        let (course, explanation) = self
+            .complete_impl(ctx, true, &CancellationToken::new())
+            .await?;
+        Ok((course, explanation.unwrap_or_default()))
+    }
+
+    #[tracing::instrument(
+        name = "course",
+        skip_all,
+        fields(course_code = self.course_code, career = self.career, url = self.url)
+    )]
+    /// Only the fetch is cancellable: once the course page has been fetched, it
+    /// is always parsed.
+    pub(crate) async fn complete_impl(
+        self,
+        ctx: &ScrapingContext,
+        explain: bool,
+        cancel_token: &CancellationToken,
+    ) -> anyhow::Result<(Course, Option<CourseExplanation>)> {
+        let html = fetch_url_body(&self.url, ctx, cancel_token).await?;
         let course_code = self.course_code.clone();
+        let profile = Arc::clone(&ctx.selector_profile);
 
-        let cpu_bound = move || -> anyhow::Result<Course> {
-            let document = scraper::Html::parse_document(&html);
-
-            // NOTE: We can't return the error message from `Selector::parse`
-            // because it is not Send and, therefore, not sendable across threads.
-            let error_msg = format!("failed to parse {}", self.url);
-
-            // Selectors
-            let form_bodies = Selector::parse("td.formBody td.formBody")
-                .map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-            let term_selector = Selector::parse("table table:nth-of-type(3)")
-                .map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-            let table_selector =
-                Selector::parse("table table").map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-            let label_selector =
-                Selector::parse("td.label").map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-            let data_selector =
-                Selector::parse("td.data").map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-            let information_body = document.select(&form_bodies);
-
-            let career = self.career;
-            let mut faculty = None;
-            let mut school = None;
-            let mut campus = None;
-
-            let mut skip_this_info_box = false;
-            let mut terms: Vec<String> = vec![];
-            let mut class_activity_information: Vec<Vec<String>> = vec![];
-            for info_box in information_body {
-                if let Some(label_info) = info_box.select(&label_selector).next() {
-                    // Check if it is a form body with course information
-                    if extract_text(label_info).trim() == "Faculty" {
-                        let labels: Vec<_> = info_box
-                            .select(&label_selector)
-                            .map(|el| extract_text(el).trim().replace("\u{a0}", ""))
-                            .collect();
-
-                        let data: Vec<_> = info_box
-                            .select(&data_selector)
-                            .map(|el| extract_text(el).trim().replace("\u{a0}", ""))
-                            .collect();
-                        for (label, data) in labels.iter().zip(data.into_iter()) {
-                            match label.trim().to_lowercase().as_str() {
-                                "faculty" => faculty = Some(data),
-                                "school" => school = Some(data),
-                                "campus" => campus = Some(data),
-                                "career" => {
-                                    if career != data {
-                                        skip_this_info_box = true;
-                                        break;
-                                    } else {
-                                        skip_this_info_box = false;
-                                    }
+        let span = Span::current();
+        let cpu_bound = move || span.in_scope(|| self.parse_html(&html, &profile, explain));
+        let course_and_explanation = tokio::task::spawn_blocking(cpu_bound).await?;
+        log::info!("Finished scraping course {}", course_code);
+        course_and_explanation
+    }
+
+    /// Completes this course from the HTML of its course page.
+    pub fn complete_from_html(
+        self,
+        html: &str,
+        profile: &SelectorProfile,
+    ) -> anyhow::Result<Course> {
+        let (course, _explanation) = self.parse_html(html, profile, false)?;
+        Ok(course)
+    }
+
+    fn parse_html(
+        self,
+        html: &str,
+        profile: &SelectorProfile,
+        explain: bool,
+    ) -> anyhow::Result<(Course, Option<CourseExplanation>)> {
+        let mut explanation = explain.then(CourseExplanation::default);
+        let document = scraper::Html::parse_document(html);
+
+        let selectors = &profile.course;
+        let labels = &profile.labels;
+        let information_body = document.select(&selectors.info_box);
+
+        let career = self.career;
+        let mut faculty = None;
+        let mut school = None;
+        let mut campus = None;
+
+        let mut skip_this_info_box = false;
+        let mut found_course_info_box = false;
+        let mut terms: Vec<String> = vec![];
+        let mut class_activity_information: Vec<Vec<String>> = vec![];
+        for (info_box_index, info_box) in information_body.enumerate() {
+            if let Some(label_info) = info_box.select(&selectors.label).next() {
+                // Check if it is a form body with course information
+                let first_label = extract_text(label_info);
+                if first_label.trim() == labels.faculty {
+                    found_course_info_box = true;
+                    // Only kept if the info box is for this career.
+                    let mut box_faculty = None;
+                    let mut box_school = None;
+                    let mut box_campus = None;
+                    let info_labels: Vec<_> = info_box
+                        .select(&selectors.label)
+                        .map(|el| extract_text(el).trim().replace("\u{a0}", ""))
+                        .collect();
+
+                    let data: Vec<_> = info_box
+                        .select(&selectors.data)
+                        .map(|el| extract_text(el).trim().replace("\u{a0}", ""))
+                        .collect();
+                    for (label, data) in info_labels.iter().zip(data) {
+                        let label = label.trim();
+                        if label.eq_ignore_ascii_case(&labels.faculty) {
+                            box_faculty = Some(data);
+                        } else if label.eq_ignore_ascii_case(&labels.school) {
+                            box_school = Some(data);
+                        } else if label.eq_ignore_ascii_case(&labels.campus) {
+                            box_campus = Some(data);
+                        } else if label.eq_ignore_ascii_case(&labels.career) {
+                            if career != data {
+                                skip_this_info_box = true;
+                                if let Some(explanation) = &mut explanation {
+                                    explanation.skipped_info_boxes.push(SkippedInfoBox {
+                                        info_box_index,
+                                        career: Some(data),
+                                    });
                                 }
-                                _ => {}
+                                break;
+                            } else {
+                                skip_this_info_box = false;
                             }
                         }
-                        if skip_this_info_box {
-                            continue;
-                        }
-                        if let Some(terms_info_table) = info_box.select(&term_selector).next() {
-                            for terms_table in terms_info_table.select(&table_selector) {
-                                let curr_terms_row = terms_table
-                                    .text()
-                                    .map(|e| e.trim().to_string())
-                                    .filter(|s| !s.is_empty())
-                                    .collect::<Vec<_>>();
-                                if !curr_terms_row.is_empty() {
-                                    terms.extend(curr_terms_row);
-                                }
+                    }
+                    if skip_this_info_box {
+                        continue;
+                    }
+                    faculty = box_faculty;
+                    school = box_school;
+                    campus = box_campus;
+                    if let Some(explanation) = &mut explanation {
+                        explanation.found_career_info_box = true;
+                    }
+                    if let Some(terms_info_table) = info_box.select(&selectors.terms_table).next() {
+                        for terms_table in terms_info_table.select(&selectors.terms_row) {
+                            let curr_terms_row = terms_table
+                                .text()
+                                .map(|e| e.trim().to_string())
+                                .filter(|s| !s.is_empty())
+                                .collect::<Vec<_>>();
+                            if !curr_terms_row.is_empty() {
+                                terms.extend(curr_terms_row);
                             }
                         }
-                    } else if extract_text(label_info).trim() == "Class Nbr" && !skip_this_info_box
-                    {
-                        // Extract class.
-                        let info_map = info_box
-                            .select(
-                                &Selector::parse("td.label, td.data")
-                                    .map_err(|_| anyhow::anyhow!(error_msg.clone()))?,
-                            )
-                            .map(|cell| {
-                                cell.text()
-                                    .collect::<String>()
-                                    .trim()
-                                    .replace("\u{a0}", "")
-                                    .to_string()
-                            })
-                            .collect::<Vec<_>>();
-                        if !info_map.is_empty() {
-                            class_activity_information.push(info_map);
-                        }
                     }
+                } else if first_label.trim() == labels.class_nbr && !skip_this_info_box {
+                    // Extract class.
+                    let info_map = info_box
+                        .select(&selectors.class_cell)
+                        .map(|cell| {
+                            cell.text()
+                                .collect::<String>()
+                                .trim()
+                                .replace("\u{a0}", "")
+                                .to_string()
+                        })
+                        .collect::<Vec<_>>();
+                    if !info_map.is_empty() {
+                        class_activity_information.push(info_map);
+                    }
+                } else if first_label.trim() == labels.class_nbr
+                    && let Some(explanation) = &mut explanation
+                {
+                    explanation.skipped_info_boxes.push(SkippedInfoBox {
+                        info_box_index,
+                        career: None,
+                    });
                 }
             }
+        }
+
+        if !found_course_info_box {
+            // Not fatal, the course will just be missing its details, but
+            // most likely means the page layout changed.
+            ParseError::new(
+                ParseStage::Course,
+                &self.url,
+                Expectation::Label(labels.faculty.clone()),
+                &document
+                    .select(&selectors.info_box)
+                    .next()
+                    .unwrap_or(document.root_element())
+                    .html(),
+            )
+            .warn();
+        }
 
-            let course_id = format!("{}{}", &self.course_code, career);
-            let course_code = self.course_code;
-            let course_name = self.course_name;
-            let uoc = self.uoc;
+        let course_id = format!("{}{}", &self.course_code, career);
+        let course_code = self.course_code;
+        let course_name = self.course_name;
+        let uoc = self.uoc;
 
-            let classes: Vec<Class> = class_activity_information
+        let (classes, class_explanations): (Vec<Class>, Vec<Option<ClassExplanation>>) =
+            class_activity_information
                 .into_par_iter()
-                .map(|class_data| parse_class_info(class_data, course_id.as_str(), career.as_ref()))
-                .collect::<anyhow::Result<_>>()?;
-
-            let unique_modes: HashSet<&String> = classes.iter().map(|class| &class.mode).collect();
-            let mut modes: Vec<String> = unique_modes.iter().map(|mode| mode.to_string()).collect();
-            // Guarantee unique order by sorting, which Hashset doesn't.
-            modes.sort();
-
-            Ok(Course {
-                course_id,
-                course_code,
-                course_name,
-                uoc,
-                faculty,
-                school,
-                campus,
-                career: Some(career),
-                modes,
-                terms,
-                classes,
-            })
+                .map(|class_data| {
+                    let mut class_explanation = explain.then(ClassExplanation::default);
+                    let class = parse_class_info(
+                        class_data,
+                        course_id.as_str(),
+                        &self.url,
+                        career.as_ref(),
+                        profile,
+                        class_explanation.as_mut(),
+                    )?;
+                    Ok((class, class_explanation))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .into_iter()
+                .unzip();
+        if let Some(explanation) = &mut explanation {
+            explanation.classes = class_explanations.into_iter().flatten().collect();
+        }
+
+        let unique_modes: HashSet<&String> = classes.iter().map(|class| &class.mode).collect();
+        let mut modes: Vec<String> = unique_modes.iter().map(|mode| mode.to_string()).collect();
+        // Guarantee unique order by sorting, which Hashset doesn't.
+        modes.sort();
+
+        let course = Course {
+            course_id,
+            course_code,
+            course_name,
+            uoc,
+            faculty,
+            school,
+            campus,
+            career: Some(career),
+            modes,
+            terms,
+            classes,
         };
-        let course = tokio::task::spawn_blocking(cpu_bound).await?;
-        log::info!("Finished scraping course {}", course_code);
-        course
+        Ok((course, explanation))
     }
 }
 
 fn parse_class_info(
     class_data: Vec<String>,
     course_id: &str,
+    url: &str,
     career: &str,
+    profile: &SelectorProfile,
+    mut explanation: Option<&mut ClassExplanation>,
 ) -> anyhow::Result<Class> {
+    let labels = &profile.labels;
     let mut map: HashMap<&str, &str> = HashMap::new();
     let mut i = 0;
     let mut times_parsed = Vec::<Time>::new();
 
     while i < class_data.len() {
         let key = &class_data[i];
-        if key == "Meeting Information" {
+        if *key == labels.meeting_information {
             let mut j = i + 1;
-            while j < class_data.len() && class_data[j] != "Class Notes" {
+            while j < class_data.len() && class_data[j] != labels.class_notes {
                 j += 1;
             }
-            times_parsed = parse_meeting_info(&class_data[i + 1..j], career);
+            let meeting_info = &class_data[i + 1..j];
+            if let Some(explanation) = &mut explanation {
+                explanation.meeting_info = meeting_info.to_vec();
+            }
+            times_parsed = parse_meeting_info(meeting_info, career, &profile.days);
             i = j + 1;
             continue;
         }
@@ -223,27 +391,43 @@ fn parse_class_info(
         } else {
             ""
         };
+        if let Some(explanation) = &mut explanation {
+            explanation
+                .label_data_pairs
+                .push((key.to_string(), value.to_string()));
+        }
         map.insert(key, value);
         i += 2;
     }
-    let offering_period_str = map.get("Offering Period").unwrap_or(&"").to_string();
+    let offering_period_str = map
+        .get(labels.offering_period.as_str())
+        .unwrap_or(&"")
+        .to_string();
     let mut split_offering_period_str = offering_period_str.split(" - ");
 
     let error_msg = format!("failed to parse a class for course {}", course_id);
+    let invalid_offering_period = || {
+        ParseError::new(
+            ParseStage::Class,
+            url,
+            Expectation::Structure(format!(
+                "an '{}' label with a DD/MM/YYYY start date",
+                labels.offering_period
+            )),
+            &class_data.join(" | "),
+        )
+    };
     let date = split_offering_period_str
         .next()
-        .ok_or_else(|| anyhow::anyhow!(error_msg.clone()))?;
-    let year = date
-        .split("/")
-        .nth(2)
-        .ok_or_else(|| anyhow::anyhow!(error_msg.clone()))?;
+        .ok_or_else(invalid_offering_period)?;
+    let year = date.split("/").nth(2).ok_or_else(invalid_offering_period)?;
     Ok(Class {
         course_id: course_id.to_string(),
         class_id: format!(
             "{}-{}-{}-{}",
             course_id,
-            map.get("Class Nbr").unwrap_or(&""),
-            map.get("Teaching Period")
+            map.get(labels.class_nbr.as_str()).unwrap_or(&""),
+            map.get(labels.teaching_period.as_str())
                 .unwrap_or(&"")
                 .split(" - ")
                 .next()
@@ -253,9 +437,9 @@ fn parse_class_info(
                 )))?,
             year,
         ),
-        section: map.get("Section").unwrap_or(&"").to_string(),
+        section: map.get(labels.section.as_str()).unwrap_or(&"").to_string(),
         term: map
-            .get("Teaching Period")
+            .get(labels.teaching_period.as_str())
             .unwrap_or(&"")
             .split(" - ")
             .next()
@@ -267,18 +451,30 @@ fn parse_class_info(
             })?
             .to_string(),
         year: year.to_string(),
-        activity: map.get("Activity").unwrap_or(&"").to_string(),
-        status: map.get("Status").unwrap_or(&"").to_string(),
+        activity: map.get(labels.activity.as_str()).unwrap_or(&"").to_string(),
+        status: map.get(labels.status.as_str()).unwrap_or(&"").to_string(),
         course_enrolment: map
-            .get("Enrols/Capacity")
+            .get(labels.enrols_capacity.as_str())
             .unwrap_or(&"")
             .replace("*", "")
             .to_string(),
-        offering_period: map.get("Offering Period").unwrap_or(&"").to_string(),
-        meeting_dates: map.get("Meeting Dates").unwrap_or(&"").to_string(),
-        census_date: map.get("Census Date").unwrap_or(&"").to_string(),
-        mode: map.get("Mode of Delivery").unwrap_or(&"").to_string(),
-        consent: map.get("Consent").unwrap_or(&"").to_string(),
+        offering_period: map
+            .get(labels.offering_period.as_str())
+            .unwrap_or(&"")
+            .to_string(),
+        meeting_dates: map
+            .get(labels.meeting_dates.as_str())
+            .unwrap_or(&"")
+            .to_string(),
+        census_date: map
+            .get(labels.census_date.as_str())
+            .unwrap_or(&"")
+            .to_string(),
+        mode: map
+            .get(labels.mode_of_delivery.as_str())
+            .unwrap_or(&"")
+            .to_string(),
+        consent: map.get(labels.consent.as_str()).unwrap_or(&"").to_string(),
         career: career.to_string(),
         times: if times_parsed.is_empty() {
             None
@@ -286,19 +482,18 @@ fn parse_class_info(
             Some(times_parsed)
         },
         class_notes: map
-            .get("Class Notes")
+            .get(labels.class_notes.as_str())
             .filter(|s| !s.is_empty())
             .map(|s| s.to_string()),
     })
 }
 
-fn parse_meeting_info(vec: &[String], career: &str) -> Vec<Time> {
-    let days = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+fn parse_meeting_info(vec: &[String], career: &str, days: &[String]) -> Vec<Time> {
     let mut meetings = Vec::new();
     let mut iter: Box<dyn Iterator<Item = &String>> = Box::new(vec.iter());
 
     while let Some(day) = iter.next() {
-        if days.contains(&day.as_str()) {
+        if days.contains(day) {
             let mut timeslot = get_blank_time_struct();
             timeslot.day = day.clone();
 
@@ -315,7 +510,7 @@ fn parse_meeting_info(vec: &[String], career: &str) -> Vec<Time> {
 
             // Optional instructor parsing
             if let Some(instructor) = iter.next() {
-                if !days.contains(&instructor.as_str()) {
+                if !days.contains(instructor) {
                     timeslot.instructor = Some(instructor.clone());
                 } else {
                     iter = Box::new(std::iter::once(instructor).chain(iter));
@@ -339,3 +534,78 @@ fn get_blank_time_struct() -> Time {
         instructor: None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_class_info_explanation() {
+        let class_data = [
+            "Class Nbr",
+            "1234",
+            "Teaching Period",
+            "T1 - Term 1",
+            "Offering Period",
+            "17/02/2025 - 18/05/2025",
+            "Meeting Information",
+            "Mon",
+            "10:00 - 12:00",
+            "Quad G041",
+            "1-5,7-10",
+            "Class Notes",
+        ]
+        .map(String::from)
+        .to_vec();
+        let mut explanation = ClassExplanation::default();
+        let class = parse_class_info(
+            class_data,
+            "COMP1511Undergraduate",
+            "https://timetable.unsw.edu.au/2025/COMP1511.html",
+            "Undergraduate",
+            &SelectorProfile::load(None).unwrap(),
+            Some(&mut explanation),
+        )
+        .unwrap();
+
+        assert_eq!(class.class_id, "COMP1511Undergraduate-1234-T1-2025");
+        assert_eq!(class.times.as_ref().map(Vec::len), Some(1));
+        assert_eq!(
+            explanation.meeting_info,
+            ["Mon", "10:00 - 12:00", "Quad G041", "1-5,7-10"]
+        );
+        assert_eq!(
+            explanation.label_data_pairs[0],
+            ("Class Nbr".to_string(), "1234".to_string())
+        );
+        assert_eq!(explanation.label_data_pairs.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_html_ignores_info_boxes_of_other_careers() {
+        let partial_course = PartialCourse::new(
+            "COMP9021".to_string(),
+            "Principles of Programming".to_string(),
+            "Undergraduate".to_string(),
+            6,
+            "https://timetable.unsw.edu.au/2025/COMP9021.html".to_string(),
+        );
+        let (course, explanation) = partial_course
+            .parse_html(
+                include_str!("../tests/fixtures/timetable/2025/COMP9021.html"),
+                &SelectorProfile::load(None).unwrap(),
+                true,
+            )
+            .unwrap();
+        let explanation = explanation.unwrap();
+
+        assert!(!explanation.found_career_info_box);
+        assert_eq!(
+            explanation.skipped_info_boxes[0].career.as_deref(),
+            Some("Postgraduate")
+        );
+        assert_eq!(course.faculty, None);
+        assert_eq!(course.campus, None);
+        assert!(course.classes.is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use std::{collections::HashMap, io, path::PathBuf};
+
+use anyhow::Context;
+use futures::future::BoxFuture;
+use reqwest::StatusCode;
+
+use crate::{RequestClient, ScrapingContext};
+
+/// Where the scrapers get timetable pages from.
+///
+/// `ScrapingContext` holds one of these, so the scrapers can be pointed at
+/// something other than the live website, e.g. a caching proxy or test double.
+pub trait Fetcher: Send + Sync {
+    /// Returns the status and body of a GET request to `url`.
+    ///
+    /// A missing page should be a 404 rather than an error, since that is how
+    /// UNSW signals that a year has no data.
+    fn fetch<'a>(
+        &'a self,
+        url: &'a str,
+        ctx: &'a ScrapingContext,
+    ) -> BoxFuture<'a, anyhow::Result<(StatusCode, String)>>;
+}
+
+/// Fetches pages from the live website, rate-limited and with retries.
+impl Fetcher for RequestClient {
+    fn fetch<'a>(
+        &'a self,
+        url: &'a str,
+        ctx: &'a ScrapingContext,
+    ) -> BoxFuture<'a, anyhow::Result<(StatusCode, String)>> {
+        Box::pin(self.fetch_url_response_and_body(url, ctx))
+    }
+}
+
+/// Serves pages saved to disk, laid out as `<root>/<year>/<page>`, with the
+/// year's school area page saved as `<root>/<year>/index.html`.
+pub struct SnapshotFetcher {
+    root: PathBuf,
+}
+
+impl SnapshotFetcher {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The file `url` is saved to.
+    pub fn path_for(&self, url: &str, ctx: &ScrapingContext) -> anyhow::Result<PathBuf> {
+        let year = ctx.timetable_url_regex.extract_year(url)?;
+        let page = url
+            .rsplit('/')
+            .next()
+            .filter(|page| !page.is_empty() && *page != year.to_string())
+            .unwrap_or("index.html");
+        Ok(self.root.join(year.to_string()).join(page))
+    }
+}
+
+impl Fetcher for SnapshotFetcher {
+    fn fetch<'a>(
+        &'a self,
+        url: &'a str,
+        ctx: &'a ScrapingContext,
+    ) -> BoxFuture<'a, anyhow::Result<(StatusCode, String)>> {
+        Box::pin(async move {
+            let path = self.path_for(url, ctx)?;
+            match tokio::fs::read_to_string(&path).await {
+                Ok(body) => Ok((StatusCode::OK, body)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    Ok((StatusCode::NOT_FOUND, String::new()))
+                }
+                Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+            }
+        })
+    }
+}
+
+/// Serves pages from memory, keyed by URL.
+#[derive(Default)]
+pub struct InMemoryFetcher {
+    pages: HashMap<String, String>,
+}
+
+impl InMemoryFetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, url: impl Into<String>, body: impl Into<String>) {
+        self.pages.insert(url.into(), body.into());
+    }
+}
+
+impl Fetcher for InMemoryFetcher {
+    fn fetch<'a>(
+        &'a self,
+        url: &'a str,
+        _ctx: &'a ScrapingContext,
+    ) -> BoxFuture<'a, anyhow::Result<(StatusCode, String)>> {
+        let response = match self.pages.get(url) {
+            Some(body) => (StatusCode::OK, body.clone()),
+            None => (StatusCode::NOT_FOUND, String::new()),
+        };
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ScrapeFilter, ScrapingConfig};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_fetchers_treat_missing_pages_as_not_found() {
+        let config = ScrapingConfig::from_timetable_api_url("https://timetable.unsw.edu.au/year/");
+        let mut fetcher = InMemoryFetcher::new();
+        fetcher.insert("https://timetable.unsw.edu.au/2025/", "<html></html>");
+        let ctx = ScrapingContext::with_fetcher(config, ScrapeFilter::default(), Arc::new(fetcher))
+            .unwrap();
+        assert_eq!(
+            ctx.fetch_url_status("https://timetable.unsw.edu.au/2025/")
+                .await
+                .unwrap(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            ctx.fetch_url_status("https://timetable.unsw.edu.au/2026/")
+                .await
+                .unwrap(),
+            StatusCode::NOT_FOUND
+        );
+
+        let snapshot = SnapshotFetcher::new("/snapshots");
+        for (url, path) in [
+            (
+                "https://timetable.unsw.edu.au/2025/",
+                "/snapshots/2025/index.html",
+            ),
+            (
+                "https://timetable.unsw.edu.au/2025",
+                "/snapshots/2025/index.html",
+            ),
+            (
+                "https://timetable.unsw.edu.au/2025/COMPKENS.html",
+                "/snapshots/2025/COMPKENS.html",
+            ),
+        ] {
+            assert_eq!(snapshot.path_for(url, &ctx).unwrap(), PathBuf::from(path));
+        }
+        let (status, body) = snapshot
+            .fetch("https://timetable.unsw.edu.au/2025/", &ctx)
+            .await
+            .unwrap();
+        assert_eq!((status, body.as_str()), (StatusCode::NOT_FOUND, ""));
+    }
+}
@@ -1,4 +1,4 @@
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs::File;
@@ -7,6 +7,8 @@ use std::vec;
 
 use crate::UploadingConfig;
 use crate::config::LoadFromEnv;
+use crate::metrics::{self, UploadOutcome};
+use crate::rows::{ClassRow, CourseRow, OccurrenceRow, TimeRow};
 
 #[derive(Serialize, Deserialize)]
 struct Metadata {
@@ -40,17 +42,25 @@ fn read_sql_file(file_path: &str) -> std::io::Result<String> {
     file.read_to_string(&mut contents)?;
     Ok(contents)
 }
-pub struct ReadFromFile;
+pub struct ReadFromFile {
+    /// Also upload `occurrences.json`.
+    pub occurrences: bool,
+}
 pub struct ReadFromMemory {
     pub courses_vec: Vec<Value>,
     pub classes_vec: Vec<Value>,
     pub times_vec: Vec<Value>,
+    pub occurrences_vec: Option<Vec<Value>>,
 }
 
 pub trait HasuragresData {
     fn get_courses(&self) -> Vec<Value>;
     fn get_classes(&self) -> Vec<Value>;
     fn get_times(&self) -> Vec<Value>;
+    /// The optional occurrences table, which is only uploaded if present.
+    fn get_occurrences(&self) -> Option<Vec<Value>> {
+        None
+    }
 }
 impl HasuragresData for ReadFromFile {
     fn get_courses(&self) -> Vec<Value> {
@@ -62,6 +72,11 @@ impl HasuragresData for ReadFromFile {
     fn get_times(&self) -> Vec<Value> {
         read_json_file("times.json").expect("Could not read times.json file!")
     }
+    fn get_occurrences(&self) -> Option<Vec<Value>> {
+        self.occurrences.then(|| {
+            read_json_file("occurrences.json").expect("Could not read occurrences.json file!")
+        })
+    }
 }
 
 impl HasuragresData for ReadFromMemory {
@@ -74,28 +89,33 @@ impl HasuragresData for ReadFromMemory {
     fn get_times(&self) -> Vec<Value> {
         self.times_vec.clone()
     }
+    fn get_occurrences(&self) -> Option<Vec<Value>> {
+        self.occurrences_vec.clone()
+    }
 }
 
-pub async fn send_batch_data(hdata: &impl HasuragresData) -> anyhow::Result<()> {
+pub async fn send_batch_data(hdata: &impl HasuragresData) -> anyhow::Result<UploadOutcome> {
     let uploading_config = UploadingConfig::load_from_env()?;
+    send_batch_data_with_config(hdata, &uploading_config).await
+}
+
+/// Like `send_batch_data`, but uploads to the Hasuragres instance in
+/// `uploading_config` instead of the one configured in the environment.
+///
+/// Hasuragres being unreachable, rejecting the insert or answering with an
+/// unexpected response is logged and counted in the `batch_inserts_total`
+/// metric, and returned as the `UploadOutcome` rather than as an error.
+pub async fn send_batch_data_with_config(
+    hdata: &impl HasuragresData,
+    uploading_config: &UploadingConfig,
+) -> anyhow::Result<UploadOutcome> {
     let client = Client::new();
     log::info!("Starting to insert into Hasuragres!");
-    let requests = vec![
+    let mut requests = vec![
         BatchInsertRequest {
             metadata: Metadata {
                 table_name: "courses".to_string(),
-                columns: vec![
-                    "course_id".to_string(),
-                    "course_code".to_string(),
-                    "course_name".to_string(),
-                    "uoc".to_string(),
-                    "faculty".to_string(),
-                    "school".to_string(),
-                    "campus".to_string(),
-                    "career".to_string(),
-                    "terms".to_string(),
-                    "modes".to_string(),
-                ],
+                columns: CourseRow::COLUMNS.iter().map(|c| c.to_string()).collect(),
                 sql_up: read_sql_file("sql/Courses/up.sql")?,
                 sql_down: read_sql_file("sql/Courses/down.sql")?,
                 write_mode: Some("overwrite".to_string()),
@@ -108,23 +128,7 @@ pub async fn send_batch_data(hdata: &impl HasuragresData) -> anyhow::Result<()>
         BatchInsertRequest {
             metadata: Metadata {
                 table_name: "classes".to_string(),
-                columns: vec![
-                    "class_id".to_string(),
-                    "career".to_string(),
-                    "course_id".to_string(),
-                    "section".to_string(),
-                    "term".to_string(),
-                    "activity".to_string(),
-                    "year".to_string(),
-                    "status".to_string(),
-                    "course_enrolment".to_string(),
-                    "offering_period".to_string(),
-                    "meeting_dates".to_string(),
-                    "census_date".to_string(),
-                    "consent".to_string(),
-                    "mode".to_string(),
-                    "class_notes".to_string(),
-                ],
+                columns: ClassRow::COLUMNS.iter().map(|c| c.to_string()).collect(),
                 sql_up: read_sql_file("sql/Classes/up.sql")?,
                 sql_down: read_sql_file("sql/Classes/down.sql")?,
                 write_mode: Some("overwrite".to_string()),
@@ -137,16 +141,7 @@ pub async fn send_batch_data(hdata: &impl HasuragresData) -> anyhow::Result<()>
         BatchInsertRequest {
             metadata: Metadata {
                 table_name: "times".to_string(),
-                columns: vec![
-                    "id".to_string(),
-                    "class_id".to_string(),
-                    "career".to_string(),
-                    "day".to_string(),
-                    "instructor".to_string(),
-                    "location".to_string(),
-                    "time".to_string(),
-                    "weeks".to_string(),
-                ],
+                columns: TimeRow::COLUMNS.iter().map(|c| c.to_string()).collect(),
                 sql_up: read_sql_file("sql/Times/up.sql")?,
                 sql_down: read_sql_file("sql/Times/down.sql")?,
                 write_mode: Some("overwrite".to_string()),
@@ -157,40 +152,88 @@ pub async fn send_batch_data(hdata: &impl HasuragresData) -> anyhow::Result<()>
             payload: hdata.get_times(),
         },
     ];
+    if let Some(occurrences) = hdata.get_occurrences() {
+        requests.push(BatchInsertRequest {
+            metadata: Metadata {
+                table_name: "occurrences".to_string(),
+                columns: OccurrenceRow::COLUMNS
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect(),
+                sql_up: read_sql_file("sql/Occurrences/up.sql")?,
+                sql_down: read_sql_file("sql/Occurrences/down.sql")?,
+                write_mode: Some("overwrite".to_string()),
+                sql_before: None,
+                sql_after: None,
+                dryrun: Some(true),
+            },
+            payload: occurrences,
+        });
+    }
+
+    let outcome = upload(&client, &requests, uploading_config).await;
+    metrics::record_upload(outcome);
+    Ok(outcome)
+}
 
-    let response = client
+/// Posts `requests` to Hasuragres, logging how it went.
+async fn upload(
+    client: &Client,
+    requests: &[BatchInsertRequest],
+    uploading_config: &UploadingConfig,
+) -> UploadOutcome {
+    let response = match client
         .post(format!("{}/batch_insert", uploading_config.hasuragres_url))
         .header("X-API-Key", uploading_config.hasuragres_api_key.clone())
-        .json(&requests)
+        .json(requests)
         .send()
-        .await;
-
-    match response {
-        Ok(res) => {
-            if res.status().as_u16() == 400 {
-                let error_body: Result<Value, reqwest::Error> = res.json().await;
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            log::error!("Failed to insert batch data: {:?}", e);
+            return UploadOutcome::Unreachable;
+        }
+    };
+    let status = response.status();
+    let text = match response.text().await {
+        Ok(text) => text,
+        Err(e) => {
+            log::error!("Failed to read Hasuragres response body: {:?}", e);
+            return UploadOutcome::Unreachable;
+        }
+    };
 
-                match error_body {
-                    Ok(json) => {
-                        log::error!("Error occurred: {:?}", json);
-                        if let Some(error_message) = json.get("error") {
-                            log::error!("Error message: {}", error_message);
-                        }
-                    }
-                    Err(err) => {
-                        log::error!("Failed to parse error body: {:?}", err);
-                    }
-                }
-            } else {
-                let text = res.text().await?;
-                let data: Result<Value, serde_json::Error> = serde_json::from_str(&text);
-                match data {
-                    Ok(_) => log::info!("Successfully inserted into Hasuragres"),
-                    Err(err) => log::error!("Failed to parse response body: {:?}", err),
+    if status == StatusCode::BAD_REQUEST {
+        // Hasuragres explains what went wrong (e.g. which table failed to
+        // insert) in the error body.
+        match serde_json::from_str::<Value>(&text) {
+            Ok(json) => {
+                log::error!("Error occurred: {:?}", json);
+                if let Some(error_message) = json.get("error") {
+                    log::error!("Error message: {}", error_message);
                 }
             }
+            Err(err) => log::error!("Failed to parse error body: {:?}", err),
+        }
+        UploadOutcome::Rejected
+    } else if !status.is_success() {
+        log::error!(
+            "Hasuragres returned '{}' for the batch insert: {}",
+            status,
+            text
+        );
+        UploadOutcome::HttpError
+    } else {
+        match serde_json::from_str::<Value>(&text) {
+            Ok(_) => {
+                log::info!("Successfully inserted into Hasuragres");
+                UploadOutcome::Ok
+            }
+            Err(err) => {
+                log::error!("Failed to parse response body: {:?}", err);
+                UploadOutcome::InvalidResponse
+            }
         }
-        Err(e) => log::error!("Failed to insert batch data: {:?}", e),
     }
-    Ok(())
 }
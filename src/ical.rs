@@ -0,0 +1,209 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+use crate::{
+    Class, Course,
+    occurrences::{TIMEZONE, occurrences},
+};
+
+/// Lines longer than this many bytes are folded onto continuation lines.
+const MAX_LINE_LEN: usize = 75;
+
+/// The daylight saving rules of `TIMEZONE` since 2008, for calendar apps
+/// that don't know the timezone by name.
+const VTIMEZONE: &[&str] = &[
+    "BEGIN:VTIMEZONE",
+    "TZID:Australia/Sydney",
+    "BEGIN:STANDARD",
+    "DTSTART:19700405T030000",
+    "RRULE:FREQ=YEARLY;BYMONTH=4;BYDAY=1SU",
+    "TZOFFSETFROM:+1100",
+    "TZOFFSETTO:+1000",
+    "TZNAME:AEST",
+    "END:STANDARD",
+    "BEGIN:DAYLIGHT",
+    "DTSTART:19701004T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=1SU",
+    "TZOFFSETFROM:+1000",
+    "TZOFFSETTO:+1100",
+    "TZNAME:AEDT",
+    "END:DAYLIGHT",
+    "END:VTIMEZONE",
+];
+
+/// An iCalendar (`.ics`) calendar with an event for every dated meeting of
+/// `classes`, in the Australia/Sydney timezone. Times whose day, time range
+/// or weeks can't be parsed are logged and left out.
+///
+/// `dtstamp` is when the calendar was generated.
+pub fn ical_calendar(classes: &[(&Course, &Class)], dtstamp: DateTime<Utc>) -> String {
+    let mut calendar = Calendar::default();
+    calendar.line("BEGIN", "VCALENDAR");
+    calendar.line("VERSION", "2.0");
+    calendar.line("PRODID", "-//DevSoc UNSW//spooderman//EN");
+    calendar.line("CALSCALE", "GREGORIAN");
+    for line in VTIMEZONE {
+        calendar.raw(line);
+    }
+    let dtstamp = dtstamp.format("%Y%m%dT%H%M%SZ").to_string();
+    for (course, class) in classes {
+        for (i, time) in class.times.iter().flatten().enumerate() {
+            let occurrences = match occurrences(class, time) {
+                Ok(occurrences) => occurrences,
+                Err(e) => {
+                    log::warn!(
+                        "Leaving {} {} on {} at {} out of the calendar: {:#}",
+                        class.class_id,
+                        class.activity,
+                        time.day,
+                        time.time,
+                        e
+                    );
+                    continue;
+                }
+            };
+            for occurrence in occurrences {
+                calendar.line("BEGIN", "VEVENT");
+                calendar.line(
+                    "UID",
+                    &escape(&format!(
+                        "{}-{}-{}@spooderman",
+                        class.class_id,
+                        i,
+                        occurrence.start.format("%Y%m%d")
+                    )),
+                );
+                calendar.line("DTSTAMP", &dtstamp);
+                calendar.line(
+                    &format!("DTSTART;TZID={}", TIMEZONE),
+                    &local_time(&occurrence.start),
+                );
+                calendar.line(
+                    &format!("DTEND;TZID={}", TIMEZONE),
+                    &local_time(&occurrence.end),
+                );
+                calendar.line(
+                    "SUMMARY",
+                    &escape(&format!(
+                        "{} {} {}",
+                        course.course_code, class.activity, class.section
+                    )),
+                );
+                calendar.line("LOCATION", &escape(&time.location));
+                let mut description = format!(
+                    "{}\n{} {}, week {}",
+                    course.course_name, class.activity, class.section, occurrence.week
+                );
+                if let Some(instructor) = &time.instructor {
+                    write!(description, "\nInstructor: {}", instructor).unwrap();
+                }
+                calendar.line("DESCRIPTION", &escape(&description));
+                calendar.line("END", "VEVENT");
+            }
+        }
+    }
+    calendar.line("END", "VCALENDAR");
+    calendar.0
+}
+
+#[derive(Default)]
+struct Calendar(String);
+
+impl Calendar {
+    fn line(&mut self, name: &str, value: &str) {
+        self.raw(&format!("{}:{}", name, value));
+    }
+
+    /// Writes a content line, folding it onto continuation lines (which start
+    /// with a space) so that no line is longer than `MAX_LINE_LEN` bytes.
+    fn raw(&mut self, line: &str) {
+        let mut line_len = 0;
+        for c in line.chars() {
+            if line_len + c.len_utf8() > MAX_LINE_LEN {
+                self.0.push_str("\r\n ");
+                line_len = 1;
+            }
+            self.0.push(c);
+            line_len += c.len_utf8();
+        }
+        self.0.push_str("\r\n");
+    }
+}
+
+fn local_time(time: &DateTime<Tz>) -> String {
+    time.format("%Y%m%dT%H%M%S").to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Time,
+        test_fixtures::{self, class, time},
+    };
+
+    fn course() -> Course {
+        let times = vec![
+            Time {
+                location: "Clancy Auditorium, with a long name that needs folding (K-E19-G05)"
+                    .to_string(),
+                weeks: "1".to_string(),
+                instructor: Some("Dr A Smith".to_string()),
+                ..time("Mon", "09:00 - 11:00")
+            },
+            Time {
+                location: "Online".to_string(),
+                weeks: "1".to_string(),
+                ..time("Mon", "TBA")
+            },
+        ];
+        let class = Class {
+            class_id: "COMP1511Undergraduate-1001-T1-2025".to_string(),
+            ..class("COMP1511", "A", "T1", times)
+        };
+        Course {
+            course_name: "Programming Fundamentals".to_string(),
+            ..test_fixtures::course("COMP1511", vec![class])
+        }
+    }
+
+    #[test]
+    fn test_ical_calendar() {
+        let course = course();
+        let dtstamp = DateTime::from_timestamp(1735689600, 0).unwrap();
+        let calendar = ical_calendar(&[(&course, &course.classes[0])], dtstamp);
+        let event = calendar
+            .split("\r\n")
+            .skip_while(|line| *line != "BEGIN:VEVENT")
+            .collect::<Vec<_>>();
+        assert_eq!(
+            event,
+            [
+                "BEGIN:VEVENT",
+                "UID:COMP1511Undergraduate-1001-T1-2025-0-20250217@spooderman",
+                "DTSTAMP:20250101T000000Z",
+                "DTSTART;TZID=Australia/Sydney:20250217T090000",
+                "DTEND;TZID=Australia/Sydney:20250217T110000",
+                "SUMMARY:COMP1511 Lecture A",
+                "LOCATION:Clancy Auditorium\\, with a long name that needs folding (K-E19-G05",
+                " )",
+                "DESCRIPTION:Programming Fundamentals\\nLecture A\\, week 1\\nInstructor: Dr A ",
+                " Smith",
+                "END:VEVENT",
+                "END:VCALENDAR",
+                "",
+            ]
+        );
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(calendar.contains("\r\nTZID:Australia/Sydney\r\n"));
+    }
+}
@@ -0,0 +1,546 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+};
+
+use chrono::{NaiveTime, Weekday};
+use serde::Serialize;
+
+use crate::{
+    Course,
+    occurrences::{WeeklySlot, parse_time_range},
+    utils::write_aligned,
+};
+
+/// Titles left out when comparing names, so that e.g. `Dr J Wu` and
+/// `Prof J Wu` are the same instructor.
+const TITLES: [&str; 11] = [
+    "a/prof",
+    "assoc",
+    "associate",
+    "prof",
+    "professor",
+    "dr",
+    "mr",
+    "mrs",
+    "ms",
+    "miss",
+    "mx",
+];
+
+/// Finds the classes of instructors. Instructor names are written
+/// differently across the timetable, so names are matched loosely: case,
+/// punctuation, titles and word order are ignored, initials match full
+/// names, and long words may be off by one letter.
+#[derive(Debug, Clone, Default)]
+pub struct InstructorQuery {
+    /// Part of a name, e.g. `renzella` or `J Renzella`. Every instructor if
+    /// not given.
+    pub name: Option<String>,
+    pub term: Option<String>,
+}
+
+/// The classes of an instructor, by term.
+#[derive(Debug, Serialize)]
+pub struct InstructorTimetable<'a> {
+    /// The most common way the name is written.
+    pub name: &'a str,
+    /// Every way the name is written, including `name`.
+    pub spellings: Vec<&'a str>,
+    pub terms: Vec<InstructorTerm<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstructorTerm<'a> {
+    pub term: &'a str,
+    /// Sorted by day and start time.
+    pub meetings: Vec<InstructorMeeting<'a>>,
+}
+
+/// A time of a class the instructor is teaching.
+#[derive(Debug, Serialize)]
+pub struct InstructorMeeting<'a> {
+    pub course_code: &'a str,
+    pub class_id: &'a str,
+    pub activity: &'a str,
+    pub section: &'a str,
+    pub day: &'a str,
+    pub time: &'a str,
+    pub location: &'a str,
+    pub weeks: &'a str,
+    /// The other classes of the instructor meeting at an overlapping time in
+    /// the same week. Classes at the same time in the same location are
+    /// taught together (e.g. cross-listed courses), so they don't count.
+    pub double_booked_with: Vec<&'a str>,
+    #[serde(skip)]
+    slot: Option<WeeklySlot>,
+}
+
+impl InstructorTimetable<'_> {
+    /// The number of meetings that overlap another class.
+    pub fn double_booked(&self) -> usize {
+        self.terms
+            .iter()
+            .flat_map(|term| &term.meetings)
+            .filter(|meeting| !meeting.double_booked_with.is_empty())
+            .count()
+    }
+}
+
+/// The words of a name that identify the person, e.g. `["jake", "renzella"]`
+/// for both `Dr Jake Renzella` and `Renzella, Jake`.
+fn name_words(name: &str) -> Vec<String> {
+    let name = name.to_lowercase();
+    let name = match name.split_once(',') {
+        Some((surname, given)) if !given.contains(',') => format!("{} {}", given, surname),
+        _ => name,
+    };
+    name.split_whitespace()
+        .filter(|word| !TITLES.contains(&word.trim_end_matches('.')))
+        .flat_map(|word| word.split(|c: char| !c.is_alphanumeric() && c != '\''))
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The number of single letter insertions, deletions and substitutions to
+/// turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Whether `a` and `b` are the same word, allowing a typo in long words.
+fn words_match(a: &str, b: &str) -> bool {
+    a == b || a.chars().count() >= 5 && b.chars().count() >= 5 && edit_distance(a, b) <= 1
+}
+
+/// Who a name refers to: the surname and the first initial, if any.
+#[derive(Debug, PartialEq, Eq)]
+struct Person {
+    surname: String,
+    initial: Option<char>,
+}
+
+impl Person {
+    fn new(words: &[String]) -> Option<Self> {
+        let (surname, given) = words.split_last()?;
+        Some(Self {
+            surname: surname.clone(),
+            initial: given.first().and_then(|word| word.chars().next()),
+        })
+    }
+
+    fn is(&self, other: &Person) -> bool {
+        self.initial == other.initial && words_match(&self.surname, &other.surname)
+    }
+}
+
+/// Whether every word of `query` starts a word of `name`, or matches it with
+/// a typo.
+fn name_matches(query: &[String], name: &[String]) -> bool {
+    query.iter().all(|query| {
+        name.iter()
+            .any(|word| word.starts_with(query.as_str()) || words_match(query, word))
+    })
+}
+
+#[derive(Default)]
+struct Teaching<'a> {
+    spellings: BTreeMap<&'a str, usize>,
+    terms: BTreeMap<&'a str, Vec<InstructorMeeting<'a>>>,
+}
+
+impl InstructorQuery {
+    /// The timetables of the matching instructors, sorted by name.
+    pub fn run<'a>(&self, courses: &'a [Course]) -> Vec<InstructorTimetable<'a>> {
+        let query = self.name.as_deref().map(name_words);
+        let mut people = Vec::<(Person, Teaching)>::new();
+        for course in courses {
+            for class in &course.classes {
+                if let Some(term) = &self.term
+                    && !class.term.eq_ignore_ascii_case(term)
+                {
+                    continue;
+                }
+                for time in class.times.iter().flatten() {
+                    let Some(instructor) = time.instructor.as_deref() else {
+                        continue;
+                    };
+                    let words = name_words(instructor);
+                    if words.is_empty() || words == ["tba"] {
+                        continue;
+                    }
+                    let Some(person) = Person::new(&words) else {
+                        continue;
+                    };
+                    let teaching = match people.iter().position(|(other, _)| other.is(&person)) {
+                        Some(i) => &mut people[i].1,
+                        None => {
+                            people.push((person, Teaching::default()));
+                            &mut people.last_mut().expect("just pushed").1
+                        }
+                    };
+                    *teaching.spellings.entry(instructor).or_default() += 1;
+                    teaching
+                        .terms
+                        .entry(&class.term)
+                        .or_default()
+                        .push(InstructorMeeting {
+                            course_code: &course.course_code,
+                            class_id: &class.class_id,
+                            activity: &class.activity,
+                            section: &class.section,
+                            day: &time.day,
+                            time: &time.time,
+                            location: &time.location,
+                            weeks: &time.weeks,
+                            double_booked_with: Vec::new(),
+                            slot: WeeklySlot::parse(time).ok(),
+                        });
+                }
+            }
+        }
+
+        let mut timetables = people
+            .into_iter()
+            .map(|(_, teaching)| teaching)
+            .filter(|teaching| {
+                query.as_ref().is_none_or(|query| {
+                    teaching
+                        .spellings
+                        .keys()
+                        .any(|spelling| name_matches(query, &name_words(spelling)))
+                })
+            })
+            .map(|teaching| {
+                let name = teaching
+                    .spellings
+                    .iter()
+                    .max_by_key(|(spelling, count)| (**count, spelling.len()))
+                    .map(|(spelling, _)| *spelling)
+                    .expect("every instructor has a spelling");
+                let terms = teaching
+                    .terms
+                    .into_iter()
+                    .map(|(term, mut meetings)| {
+                        meetings.sort_by_key(|meeting| {
+                            (
+                                day_index(meeting.day),
+                                start_time(meeting.time),
+                                meeting.course_code,
+                                meeting.class_id,
+                            )
+                        });
+                        flag_double_bookings(&mut meetings);
+                        InstructorTerm { term, meetings }
+                    })
+                    .collect();
+                InstructorTimetable {
+                    name,
+                    spellings: teaching.spellings.into_keys().collect(),
+                    terms,
+                }
+            })
+            .collect::<Vec<_>>();
+        timetables.sort_by_key(|timetable| timetable.name.to_lowercase());
+        timetables
+    }
+}
+
+/// Unknown days, e.g. `TBA`, go last.
+fn day_index(day: &str) -> u32 {
+    day.parse::<Weekday>()
+        .map_or(7, |day| day.num_days_from_monday())
+}
+
+/// Unknown times, e.g. `TBA`, go last.
+fn start_time(time: &str) -> (bool, Option<NaiveTime>) {
+    let start = parse_time_range(time).ok().map(|(start, _)| start);
+    (start.is_none(), start)
+}
+
+/// Times whose day, time range or weeks can't be parsed (e.g. `TBA`) never
+/// overlap.
+fn flag_double_bookings(meetings: &mut [InstructorMeeting]) {
+    let mut double_booked_with = vec![BTreeSet::new(); meetings.len()];
+    for (i, first) in meetings.iter().enumerate() {
+        for (j, second) in meetings.iter().enumerate().skip(i + 1) {
+            let taught_together = first.day == second.day
+                && first.time == second.time
+                && first.location == second.location;
+            if first.class_id == second.class_id || taught_together {
+                continue;
+            }
+            if let (Some(first_slot), Some(second_slot)) = (&first.slot, &second.slot)
+                && first_slot.clashes(second_slot)
+            {
+                double_booked_with[i].insert(second.class_id);
+                double_booked_with[j].insert(first.class_id);
+            }
+        }
+    }
+    for (meeting, double_booked_with) in meetings.iter_mut().zip(double_booked_with) {
+        meeting.double_booked_with = double_booked_with.into_iter().collect();
+    }
+}
+
+const COLUMNS: [&str; 11] = [
+    "instructor",
+    "term",
+    "course",
+    "activity",
+    "section",
+    "class_id",
+    "day",
+    "time",
+    "location",
+    "weeks",
+    "double_booked_with",
+];
+
+/// Writes each instructor's classes as a table with aligned columns, for
+/// reading in a terminal, marking double-booked times with `!`.
+pub fn write_instructor_table(
+    writer: &mut impl Write,
+    timetables: &[InstructorTimetable],
+) -> anyhow::Result<()> {
+    for (i, timetable) in timetables.iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        let mut notes = Vec::new();
+        let other_spellings = timetable
+            .spellings
+            .iter()
+            .filter(|spelling| **spelling != timetable.name)
+            .copied()
+            .collect::<Vec<_>>();
+        if !other_spellings.is_empty() {
+            notes.push(format!("also written as {}", other_spellings.join(", ")));
+        }
+        let double_booked = timetable.double_booked();
+        if double_booked > 0 {
+            notes.push(format!("double-booked times: {}", double_booked));
+        }
+        if notes.is_empty() {
+            writeln!(writer, "{}", timetable.name)?;
+        } else {
+            writeln!(writer, "{} ({})", timetable.name, notes.join("; "))?;
+        }
+        let rows = timetable
+            .terms
+            .iter()
+            .flat_map(|term| {
+                term.meetings.iter().map(|meeting| {
+                    let double_booked_with = if meeting.double_booked_with.is_empty() {
+                        String::new()
+                    } else {
+                        format!(
+                            "! double-booked with {}",
+                            meeting.double_booked_with.join(", ")
+                        )
+                    };
+                    [
+                        term.term.to_string(),
+                        meeting.day.to_string(),
+                        meeting.time.to_string(),
+                        format!(
+                            "{} {} {}",
+                            meeting.course_code, meeting.activity, meeting.section
+                        ),
+                        meeting.location.to_string(),
+                        format!("weeks {}", meeting.weeks),
+                        double_booked_with,
+                    ]
+                })
+            })
+            .collect::<Vec<_>>();
+        write_aligned(writer, &rows, "  ")?;
+    }
+    Ok(())
+}
+
+/// Writes `timetables` as CSV, one row per instructor and class time.
+pub fn write_instructor_csv(
+    writer: impl Write,
+    timetables: &[InstructorTimetable],
+) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(COLUMNS)?;
+    for timetable in timetables {
+        for term in &timetable.terms {
+            for meeting in &term.meetings {
+                writer.write_record([
+                    timetable.name,
+                    term.term,
+                    meeting.course_code,
+                    meeting.activity,
+                    meeting.section,
+                    meeting.class_id,
+                    meeting.day,
+                    meeting.time,
+                    meeting.location,
+                    meeting.weeks,
+                    &meeting.double_booked_with.join(";"),
+                ])?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Class, Time,
+        test_fixtures::{self, course},
+    };
+
+    fn time(day: &str, time: &str, location: &str, instructor: &str) -> Time {
+        Time {
+            location: location.to_string(),
+            instructor: Some(instructor.to_string()),
+            ..test_fixtures::time(day, time)
+        }
+    }
+
+    fn class(course_code: &str, section: &str, activity: &str, times: Vec<Time>) -> Class {
+        Class {
+            activity: activity.to_string(),
+            ..test_fixtures::class(course_code, section, "T1", times)
+        }
+    }
+
+    const AINSWORTH: &str = "Ainsworth G03 (K-J17-G03)";
+    const QUADRANGLE: &str = "Quadrangle G040 (K-E15-G040)";
+
+    fn courses() -> Vec<Course> {
+        vec![
+            course(
+                "COMP1511",
+                vec![
+                    class(
+                        "COMP1511",
+                        "A",
+                        "Lecture",
+                        vec![time("Mon", "14:00 - 16:00", AINSWORTH, "Dr Jake Renzella")],
+                    ),
+                    class(
+                        "COMP1511",
+                        "T13A",
+                        "Tutorial",
+                        vec![time("Wed", "13:00 - 15:00", QUADRANGLE, "Renzella, J.")],
+                    ),
+                ],
+            ),
+            course(
+                "COMP6991",
+                vec![
+                    class(
+                        "COMP6991",
+                        "A",
+                        "Lecture",
+                        vec![time("Wed", "14:00 - 16:00", AINSWORTH, "A/Prof J Renzela")],
+                    ),
+                    class(
+                        "COMP6991",
+                        "B",
+                        "Lecture",
+                        vec![time("Mon", "14:00 - 16:00", AINSWORTH, "Dr Jake Renzella")],
+                    ),
+                    class(
+                        "COMP6991",
+                        "T1",
+                        "Tutorial",
+                        vec![time("TBA", "TBA", "Online", "Ms Ruby Renzella")],
+                    ),
+                ],
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_name_words() {
+        assert_eq!(name_words("Renzella, Jake"), ["jake", "renzella"]);
+        assert_eq!(
+            name_words("A/Prof J. O'Brien-Smith"),
+            ["j", "o'brien", "smith"]
+        );
+        assert!(name_words("Dr").is_empty());
+    }
+
+    #[test]
+    fn test_instructor_timetables() {
+        let courses = courses();
+        let timetables = InstructorQuery {
+            name: Some("j renzella".to_string()),
+            ..InstructorQuery::default()
+        }
+        .run(&courses);
+        // Ruby Renzella is someone else.
+        assert_eq!(timetables.len(), 1);
+        let timetable = &timetables[0];
+        assert_eq!(timetable.name, "Dr Jake Renzella");
+        assert_eq!(
+            timetable.spellings,
+            ["A/Prof J Renzela", "Dr Jake Renzella", "Renzella, J."]
+        );
+        let meetings = &timetable.terms[0].meetings;
+        assert_eq!(
+            meetings
+                .iter()
+                .map(|meeting| (meeting.course_code, meeting.day, meeting.time))
+                .collect::<Vec<_>>(),
+            [
+                ("COMP1511", "Mon", "14:00 - 16:00"),
+                ("COMP6991", "Mon", "14:00 - 16:00"),
+                ("COMP1511", "Wed", "13:00 - 15:00"),
+                ("COMP6991", "Wed", "14:00 - 16:00"),
+            ]
+        );
+        // The Monday lectures are in the same room, so are taught together.
+        assert!(meetings[0].double_booked_with.is_empty());
+        assert_eq!(
+            meetings[2].double_booked_with,
+            ["COMP6991Undergraduate-A-T1-2025"]
+        );
+        assert_eq!(timetable.double_booked(), 2);
+
+        let everyone = InstructorQuery::default().run(&courses);
+        assert_eq!(
+            everyone
+                .iter()
+                .map(|timetable| timetable.name)
+                .collect::<Vec<_>>(),
+            ["Dr Jake Renzella", "Ms Ruby Renzella"]
+        );
+    }
+
+    #[test]
+    fn test_write_instructor_table() {
+        let courses = courses();
+        let timetables = InstructorQuery {
+            name: Some("ruby".to_string()),
+            ..InstructorQuery::default()
+        }
+        .run(&courses);
+        let mut table = Vec::new();
+        write_instructor_table(&mut table, &timetables).unwrap();
+        assert_eq!(
+            String::from_utf8(table).unwrap(),
+            "Ms Ruby Renzella\n  T1  TBA  TBA  COMP6991 Tutorial T1  Online  weeks 1-10\n"
+        );
+    }
+}
@@ -1,23 +1,94 @@
+mod canary;
+mod class_query;
 mod config;
 mod course_scraper;
+mod fetcher;
 mod hasuragres_b_insert;
+mod ical;
+mod instructors;
+mod metrics;
+mod occurrences;
+mod parse_error;
+mod planner;
 mod ratelimit;
 mod requests;
+mod rooms;
+mod rows;
 mod school_area_scraper;
+mod scrape_filter;
+mod scrape_stats;
+mod scrape_summary;
+mod scrape_year;
 mod scraping_context;
+mod selector_profile;
+mod serve;
+mod sqlite_export;
+mod static_api;
 mod subject_area_scraper;
+mod tabular_export;
+#[cfg(test)]
+mod test_fixtures;
 mod text_manipulators;
 mod url_invalid_error;
 mod utils;
+mod year_to_scrape;
 
-pub use config::{ScrapingEnv, UploadingConfig};
-pub use course_scraper::{Class, Course, PartialCourse, Time};
-pub use hasuragres_b_insert::{ReadFromFile, ReadFromMemory, send_batch_data};
-pub use requests::{Request, RequestClient};
-pub use school_area_scraper::SchoolArea;
-pub use scraping_context::ScrapingContext;
+pub use canary::{CanaryCheck, run_canary};
+pub use class_query::{ClassMatch, ClassQuery, MatchedTime, write_class_csv, write_class_table};
+pub use config::{DEFAULT_TIMETABLE_API_URL, ScrapingConfig, ScrapingEnv, UploadingConfig};
+pub use course_scraper::{
+    Class, ClassExplanation, Course, CourseExplanation, PartialCourse, SkippedInfoBox, Time,
+};
+pub use fetcher::{Fetcher, InMemoryFetcher, SnapshotFetcher};
+pub use hasuragres_b_insert::{
+    HasuragresData, ReadFromFile, ReadFromMemory, send_batch_data, send_batch_data_with_config,
+};
+pub use ical::ical_calendar;
+pub use instructors::{
+    InstructorMeeting, InstructorQuery, InstructorTerm, InstructorTimetable, write_instructor_csv,
+    write_instructor_table,
+};
+pub use metrics::{UploadOutcome, gather_metrics, serve_metrics};
+pub use occurrences::{Occurrence, TIMEZONE, occurrences};
+pub use parse_error::{Expectation, ParseError, ParseStage};
+pub use planner::{
+    Plan, PlanPreferences, PlanRequest, PlanScore, PlannedClass, plan_timetables, write_plan_table,
+};
+pub use ratelimit::RateLimitPolicy;
+pub use requests::{HttpSettings, Request, RequestClient};
+pub use rooms::{
+    DayUtilisation, Room, RoomHeatmap, RoomIndex, RoomUtilisation, write_heatmap_csv,
+    write_room_table,
+};
+pub use rows::{
+    ClassRow, CourseRow, OccurrenceRow, TimeRow, class_rows, course_rows, occurrence_rows,
+    time_rows,
+};
+pub use school_area_scraper::{
+    PartialSchoolAreaPage, SchoolArea, SchoolAreaPage, parse_partial_pages,
+};
+pub use scrape_filter::ScrapeFilter;
+pub use scrape_stats::{RequestStats, ScrapeStats};
+pub use scrape_summary::{CourseTotals, ScrapeSummary};
+pub use scrape_year::{
+    ScrapeCancelled, ScrapeEvent, ScrapeProgress, SkippedPage, scrape_all_courses, scrape_year,
+};
+pub use scraping_context::{ScrapingContext, ScrapingContextBuilder};
+pub use selector_profile::{
+    CourseSelectors, Labels, ProfileSelector, SchoolAreaSelectors, SelectorProfile,
+    SubjectAreaSelectors,
+};
+pub use serve::serve_timetable;
+pub use sqlite_export::export_sqlite;
+pub use static_api::{StaticApiFile, StaticApiManifest, export_static_api};
+pub use subject_area_scraper::{SubjectArea, parse_partial_courses};
+pub use tabular_export::{export_csv, export_parquet};
+pub use text_manipulators::{course_code_of, subject_code};
 pub use url_invalid_error::UrlInvalidError;
-pub use utils::{log_execution_time, log_execution_time_async, sort_by_key_ref};
+pub use utils::{
+    StageTiming, log_execution_time, log_execution_time_async, sort_by_key_ref, stage_timings,
+};
+pub use year_to_scrape::YearToScrape;
 
 // NOTE: i32 because that is what DateTime<Utc> uses for years.
 pub type Year = i32;
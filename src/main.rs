@@ -1,121 +1,368 @@
+use anyhow::Context;
 use argh::FromArgs;
-use chrono::Datelike;
+use chrono::{NaiveTime, Utc, Weekday};
 use enum_dispatch::enum_dispatch;
-use parse_display::FromStr;
-use serde::Serialize;
-use serde_json::{json, to_writer_pretty};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::to_writer_pretty;
 use spooderman::{
-    Class, Course, SchoolArea, ScrapingContext, Time, Year, log_execution_time,
-    log_execution_time_async, send_batch_data, sort_by_key_ref,
+    Class, Course, CourseExplanation, PartialCourse, PartialSchoolAreaPage, RequestStats,
+    ScrapeCancelled, ScrapeEvent, ScrapeFilter, ScrapeProgress, ScrapeSummary, ScrapingContext,
+    SkippedPage, Year, YearToScrape, class_rows, course_code_of, course_rows, export_csv,
+    export_parquet, export_sqlite, export_static_api, ical_calendar, log_execution_time,
+    log_execution_time_async, occurrence_rows, run_canary, scrape_year, send_batch_data,
+    serve_metrics, serve_timetable, sort_by_key_ref, stage_timings, time_rows,
 };
-use spooderman::{ReadFromFile, ReadFromMemory};
+use spooderman::{
+    ClassQuery, InstructorQuery, PlanPreferences, PlanRequest, ReadFromFile, ReadFromMemory,
+    RoomIndex, plan_timetables, write_class_csv, write_class_table, write_heatmap_csv,
+    write_instructor_csv, write_instructor_table, write_plan_table, write_room_table,
+};
+use std::collections::BTreeSet;
+use std::fmt;
 use std::fs::File;
+use std::io::BufReader;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::net::SocketAddr;
 use std::path::Path;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use std::vec;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 
-use log::LevelFilter;
+use parse_display::{Display, FromStr};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::filter::LevelFilter;
 
-async fn run_all_school_offered_courses_scraper_job(
-    year: Year,
-    ctx: &Arc<ScrapingContext>,
-) -> anyhow::Result<SchoolArea> {
-    let url_to_scrape = ctx.scraping_config.get_timetable_api_url_for_year(year);
-    SchoolArea::scrape(url_to_scrape, ctx).await
+fn convert_courses_to_json(courses: &[Course]) -> Vec<serde_json::Value> {
+    rows_to_json(course_rows(courses))
 }
 
-fn convert_courses_to_json(courses: &[Course]) -> Vec<serde_json::Value> {
-    let mut json_courses = Vec::new();
-    for course in courses.iter() {
-        json_courses.push(json!({
-            "course_id": course.course_id,
-            "course_code": course.course_code,
-            "course_name": course.course_name,
-            "uoc": course.uoc,
-            "faculty": course.faculty,
-            "school": course.school,
-            "campus": course.campus,
-            "career": course.career,
-            "terms": json![course.terms],
-            "modes": course.modes,
-        }));
-    }
+fn convert_classes_times_to_json(courses: &[Course]) -> Vec<serde_json::Value> {
+    rows_to_json(time_rows(courses))
+}
 
-    json_courses
+fn convert_classes_to_json(courses: &[Course]) -> Vec<serde_json::Value> {
+    rows_to_json(class_rows(courses))
 }
 
-fn generate_time_id(class: &Class, time: &Time) -> String {
-    format!(
-        "{}{}{}{}{}",
-        &class.class_id, &time.day, &time.location, &time.time, &time.weeks
-    )
+fn convert_occurrences_to_json(courses: &[Course]) -> Vec<serde_json::Value> {
+    rows_to_json(occurrence_rows(courses).into_iter())
 }
 
-fn convert_classes_times_to_json(courses: &[Course]) -> Vec<serde_json::Value> {
-    let mut times_json = Vec::<serde_json::Value>::new();
-    for course in courses.iter() {
-        for class in course.classes.iter() {
-            if let Some(times) = &class.times {
-                for time in times.iter() {
-                    times_json.push(json!({
-                        "id": generate_time_id(class, time),
-                        "class_id": class.class_id,
-                        "day": time.day,
-                        "career": time.career,
-                        "instructor": time.instructor,
-                        "location": time.location,
-                        "time": time.time,
-                        "weeks": time.weeks,
-                    }));
-                }
-            }
+fn rows_to_json(rows: impl Iterator<Item = impl Serialize>) -> Vec<serde_json::Value> {
+    rows.map(|row| serde_json::to_value(row).expect("rows serialise to JSON"))
+        .collect()
+}
+
+/// Where the report of an interrupted scrape is written.
+const INCOMPLETE_SCRAPE_REPORT_PATH: &str = "incomplete_scrape.json";
+
+/// Returns a token that is cancelled on the first SIGINT or SIGTERM. A second
+/// signal exits immediately, for when the graceful shutdown takes too long.
+///
+/// Abort the returned task to stop handling signals this way once the scrape
+/// is done.
+fn cancel_on_shutdown_signal() -> (CancellationToken, JoinHandle<()>) {
+    let cancel_token = CancellationToken::new();
+    let token = cancel_token.clone();
+    let signal_task = tokio::spawn(async move {
+        shutdown_signal().await;
+        log::warn!(
+            "Interrupted, finishing in-flight pages and writing what was scraped so far. Interrupt again to exit immediately."
+        );
+        token.cancel();
+        shutdown_signal().await;
+        log::error!("Interrupted again, exiting without writing anything!");
+        std::process::exit(130);
+    });
+    (cancel_token, signal_task)
+}
+
+/// Stops `cancel_on_shutdown_signal` from cancelling the scrape, and exits on
+/// the next signal instead, for once the scrape is done and its output is
+/// being written or uploaded. Tokio keeps its signal handler installed, so
+/// without this a signal would be ignored.
+fn exit_on_shutdown_signal(signal_task: JoinHandle<()>) {
+    signal_task.abort();
+    tokio::spawn(async {
+        shutdown_signal().await;
+        log::error!("Interrupted, exiting before all output was written!");
+        std::process::exit(130);
+    });
+}
+
+/// Removes the report of an earlier interrupted scrape, so that it isn't left
+/// next to complete output.
+fn remove_incomplete_scrape_report() -> anyhow::Result<()> {
+    if Path::new(INCOMPLETE_SCRAPE_REPORT_PATH).is_file() {
+        std::fs::remove_file(INCOMPLETE_SCRAPE_REPORT_PATH)?;
+    }
+    Ok(())
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = sigterm.recv() => {}
         }
     }
-    times_json
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
 }
 
-fn convert_classes_to_json(courses: &[Course]) -> Vec<serde_json::Value> {
-    let mut json_classes = Vec::new();
-    for course in courses.iter() {
-        for class in course.classes.iter() {
-            json_classes.push(json!({
-                "course_id": class.course_id,
-                "class_id": class.class_id,
-                "section": class.section,
-                "term": class.term,
-                "career": class.career,
-                "year": class.year,
-                "activity": class.activity,
-                "status": class.status,
-                "course_enrolment": class.course_enrolment,
-                "offering_period": class.offering_period,
-                "meeting_dates": class.meeting_dates,
-                "census_date": class.census_date,
-                "consent": class.consent,
-                "mode": class.mode,
-                "class_notes": class.class_notes,
-            }));
-        }
-    }
-    json_classes
+/// Where the summary of a scrape is written.
+const SCRAPE_SUMMARY_PATH: &str = "scrape_summary.json";
+
+/// How often progress is logged while scraping.
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+fn log_progress(
+    progress: &ScrapeProgress,
+    request_stats: &RequestStats,
+    requests_per_sec: f64,
+    elapsed: Duration,
+) {
+    let request_rate_limit = match request_stats.request_rate {
+        Some(request_rate) => format!(" (limit {} req/s)", request_rate),
+        None => String::new(),
+    };
+    let eta = match progress.eta(elapsed) {
+        Some(eta) => humantime::format_duration(Duration::from_secs(eta.as_secs())).to_string(),
+        None => "unknown".to_string(),
+    };
+    log::info!(
+        "Progress: {}/{} subject areas, {} courses done, {} queued, {} retried requests, {:.0} req/s{}, ETA {}",
+        progress.subject_areas_scraped,
+        progress.subject_areas_found,
+        progress.courses_scraped,
+        progress.courses_queued(),
+        request_stats.retries,
+        requests_per_sec,
+        request_rate_limit,
+        eta
+    );
+}
+
+/// How a scrape went, for the reports written once the data has been written.
+struct ScrapeOutcome {
+    year: Option<Year>,
+    progress: ScrapeProgress,
+    request_stats: RequestStats,
+    /// Empty unless cancelled.
+    skipped: Vec<SkippedPage>,
+    cancelled: bool,
+}
+
+impl ScrapeOutcome {
+    fn incomplete_scrape_report(&self, data: &Data) -> Option<IncompleteScrapeReport> {
+        self.cancelled.then(|| IncompleteScrapeReport {
+            year: self.year,
+            progress: self.progress,
+            courses_written: data.all_courses.len(),
+            skipped: self.skipped.clone(),
+        })
+    }
+
+    /// Writes the summary of the scrape and logs it as text.
+    fn write_summary(&self, data: &Data, elapsed: Duration) -> anyhow::Result<()> {
+        let summary = ScrapeSummary::new(
+            self.year,
+            !self.cancelled,
+            elapsed,
+            self.progress,
+            &data.all_courses,
+            self.request_stats,
+            stage_timings(),
+        );
+        let file = File::create(SCRAPE_SUMMARY_PATH)?;
+        to_writer_pretty(file, &summary)?;
+        log::info!("Wrote {}:\n{}", SCRAPE_SUMMARY_PATH, summary);
+        Ok(())
+    }
 }
 
+/// What an interrupted scrape didn't get to.
 #[derive(Debug, Serialize)]
+struct IncompleteScrapeReport {
+    /// `None` if the scrape was interrupted before the year was resolved.
+    year: Option<Year>,
+    progress: ScrapeProgress,
+    courses_written: usize,
+    /// The pages that weren't fetched. Nothing under a skipped school or
+    /// subject area page was scraped either.
+    skipped: Vec<SkippedPage>,
+}
+
+impl IncompleteScrapeReport {
+    fn write(&self) -> anyhow::Result<()> {
+        let file = File::create(INCOMPLETE_SCRAPE_REPORT_PATH)?;
+        to_writer_pretty(file, self)?;
+        log::warn!(
+            "Scrape was interrupted: wrote {} courses, skipped {} pages. See {} for what is missing.",
+            self.courses_written,
+            self.skipped.len(),
+            INCOMPLETE_SCRAPE_REPORT_PATH
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 struct Data {
+    /// Missing from files written before the year was recorded.
+    #[serde(default)]
+    year: Option<Year>,
     all_courses: Vec<Course>,
+    /// Missing from files written before subject areas were recorded.
+    #[serde(default)]
+    subject_areas: Vec<PartialSchoolAreaPage>,
 }
 
 impl Data {
-    async fn scrape(year_to_scrape: &YearToScrape) -> anyhow::Result<Data> {
-        let ctx = Arc::new(ScrapingContext::new()?);
+    /// Scrapes until done or `cancel_token` is cancelled, logging progress
+    /// along the way. If cancelled, the courses scraped so far are returned.
+    async fn scrape(
+        year_to_scrape: &YearToScrape,
+        scrape_filter: ScrapeFilter,
+        cancel_token: CancellationToken,
+    ) -> anyhow::Result<(Data, ScrapeOutcome)> {
+        if !scrape_filter.is_empty() {
+            log::info!("Only scraping data matching {:?}", scrape_filter);
+        }
+        let ctx = Arc::new(ScrapingContext::with_scrape_filter(scrape_filter)?);
         // TODO: Batch the 2024 and 2025 years out since both too big to insert into hasura
-        let year = year_to_scrape.into_year(&ctx).await?;
-        log::info!("Starting scrape for year: {year}");
-        let school_area = run_all_school_offered_courses_scraper_job(year, &ctx).await?;
+        let mut outcome = ScrapeOutcome {
+            year: None,
+            progress: ScrapeProgress::default(),
+            request_stats: RequestStats::default(),
+            skipped: Vec::new(),
+            cancelled: false,
+        };
+        let mut all_courses = Vec::new();
+        let mut subject_areas = Vec::new();
+        let mut events =
+            std::pin::pin!(scrape_year(*year_to_scrape, Arc::clone(&ctx), cancel_token));
 
-        let mut all_courses = school_area.get_all_courses().collect::<Vec<_>>();
+        let start = Instant::now();
+        let mut progress_log_interval =
+            tokio::time::interval_at(start + PROGRESS_LOG_INTERVAL, PROGRESS_LOG_INTERVAL);
+        let mut last_logged_requests = 0;
+        loop {
+            let event = tokio::select! {
+                event = events.next() => event,
+                _ = progress_log_interval.tick() => {
+                    let request_stats = ctx.stats.snapshot();
+                    let requests_per_sec = (request_stats.requests - last_logged_requests) as f64
+                        / PROGRESS_LOG_INTERVAL.as_secs_f64();
+                    last_logged_requests = request_stats.requests;
+                    log_progress(&outcome.progress, &request_stats, requests_per_sec, start.elapsed());
+                    continue;
+                }
+            };
+            let Some(event) = event else {
+                break;
+            };
+            match event {
+                Ok(ScrapeEvent::YearResolved(year)) => outcome.year = Some(year),
+                Ok(ScrapeEvent::Progress(progress)) => outcome.progress = progress,
+                Ok(ScrapeEvent::SubjectArea(subject_area)) => subject_areas.push(subject_area),
+                Ok(ScrapeEvent::Course(course)) => all_courses.push(*course),
+                Ok(ScrapeEvent::Skipped(page)) => outcome.skipped.push(page),
+                Err(e) if e.is::<ScrapeCancelled>() => outcome.cancelled = true,
+                Err(e) => return Err(e),
+            }
+        }
+        outcome.request_stats = ctx.stats.snapshot();
         sort_by_key_ref(&mut all_courses, |course| &course.course_id);
-        Ok(Data { all_courses })
+        sort_by_key_ref(&mut subject_areas, |subject_area| {
+            &subject_area.subject_code
+        });
+        Ok((
+            Data {
+                year: outcome.year,
+                all_courses,
+                subject_areas,
+            },
+            outcome,
+        ))
+    }
+
+    /// Scrapes `year_to_scrape`, or reads the file `from` written by `scrape
+    /// --to-file`, for commands that work on scraped data. Exactly one of them
+    /// must be given. An interrupted scrape is an error.
+    async fn scrape_or_read(
+        year_to_scrape: Option<&YearToScrape>,
+        from: Option<&str>,
+        scrape_filter: ScrapeFilter,
+    ) -> anyhow::Result<Data> {
+        match (year_to_scrape, from) {
+            (Some(year_to_scrape), None) => {
+                let start = Instant::now();
+                let (cancel_token, signal_task) = cancel_on_shutdown_signal();
+                let scraped = Data::scrape(year_to_scrape, scrape_filter, cancel_token).await;
+                // The command handles signals itself from here on, e.g. `serve`
+                // shuts down gracefully.
+                signal_task.abort();
+                let (data, outcome) = scraped?;
+                outcome.write_summary(&data, start.elapsed())?;
+                if let Some(report) = outcome.incomplete_scrape_report(&data) {
+                    report.write()?;
+                    log::warn!("Not using an incomplete scrape");
+                    return Err(ScrapeCancelled.into());
+                }
+                Ok(data)
+            }
+            (None, Some(json_file_path)) => Data::read_from_single_json(json_file_path),
+            _ => Err(anyhow::anyhow!(
+                "needs exactly one of --year (to scrape) or --from (a file written by scrape --to-file)"
+            )),
+        }
+    }
+
+    /// The year the data was scraped for, falling back to the year of the
+    /// classes for files written before the year was recorded.
+    fn year(&self) -> anyhow::Result<Year> {
+        if let Some(year) = self.year {
+            return Ok(year);
+        }
+        let years = self
+            .all_courses
+            .iter()
+            .flat_map(|course| &course.classes)
+            .map(|class| class.year.as_str())
+            .collect::<BTreeSet<_>>();
+        match years.into_iter().collect::<Vec<_>>()[..] {
+            [year] => year
+                .parse()
+                .with_context(|| format!("unexpected class year '{}'", year)),
+            [] => Err(anyhow::anyhow!(
+                "no year recorded, and no classes to tell it from"
+            )),
+            ref years => Err(anyhow::anyhow!(
+                "no year recorded, and the classes are from several years: {}",
+                years.join(", ")
+            )),
+        }
+    }
+
+    fn read_from_single_json(json_file_path: &str) -> anyhow::Result<Data> {
+        log::info!("Reading scraped data from {}!", json_file_path);
+        let file = File::open(json_file_path)
+            .with_context(|| format!("failed to open {}", json_file_path))?;
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("failed to parse {}", json_file_path))
     }
 
     async fn write_to_single_json(&self, json_file_path: &str) -> anyhow::Result<()> {
@@ -125,14 +372,16 @@ impl Data {
         Ok(())
     }
 
-    async fn write_to_files(&self) -> anyhow::Result<()> {
-        let (json_classes, json_courses, json_times) =
+    async fn write_to_files(&self, with_occurrences: bool) -> anyhow::Result<()> {
+        let (json_classes, json_courses, json_times, json_occurrences) =
             log_execution_time("serialising scraped data to JSON", || {
                 log::info!("Writing scraped data to disk!");
                 let json_classes = convert_classes_to_json(&self.all_courses);
                 let json_courses = convert_courses_to_json(&self.all_courses);
                 let json_times = convert_classes_times_to_json(&self.all_courses);
-                (json_classes, json_courses, json_times)
+                let json_occurrences =
+                    with_occurrences.then(|| convert_occurrences_to_json(&self.all_courses));
+                (json_classes, json_courses, json_times, json_occurrences)
             });
 
         log_execution_time("writing JSON scraped data to files", || {
@@ -142,25 +391,54 @@ impl Data {
             to_writer_pretty(file_classes, &json_classes)?;
             to_writer_pretty(file_courses, &json_courses)?;
             to_writer_pretty(file_times, &json_times)?;
+            if let Some(json_occurrences) = json_occurrences {
+                let file_occurrences = File::create("occurrences.json")?;
+                to_writer_pretty(file_occurrences, &json_occurrences)?;
+            }
             Ok(())
         })
     }
 
-    async fn handle_batch_insert(&self) -> anyhow::Result<()> {
+    /// Writes the courses, classes and times tables (and the occurrences
+    /// table if `with_occurrences`) to the current directory in `format`.
+    async fn write_tables(
+        &self,
+        format: TableFormat,
+        with_occurrences: bool,
+    ) -> anyhow::Result<()> {
+        match format {
+            TableFormat::Json => self.write_to_files(with_occurrences).await,
+            TableFormat::Csv => log_execution_time("writing scraped data to CSV files", || {
+                log::info!("Writing scraped data to disk as CSV!");
+                export_csv(Path::new("."), &self.all_courses, with_occurrences)
+            }),
+            TableFormat::Parquet => {
+                log_execution_time("writing scraped data to Parquet files", || {
+                    log::info!("Writing scraped data to disk as Parquet!");
+                    export_parquet(Path::new("."), &self.all_courses, with_occurrences)
+                })
+            }
+        }
+    }
+
+    async fn handle_batch_insert(&self, with_occurrences: bool) -> anyhow::Result<()> {
         let json_classes = convert_classes_to_json(&self.all_courses);
         let json_courses = convert_courses_to_json(&self.all_courses);
         let json_times = convert_classes_times_to_json(&self.all_courses);
+        let json_occurrences =
+            with_occurrences.then(|| convert_occurrences_to_json(&self.all_courses));
         let rfm = ReadFromMemory {
             courses_vec: json_courses,
             classes_vec: json_classes,
             times_vec: json_times,
+            occurrences_vec: json_occurrences,
         };
         send_batch_data(&rfm).await?;
         Ok(())
     }
 }
 
-async fn handle_batch_insert() -> anyhow::Result<()> {
+async fn handle_batch_insert(with_occurrences: bool) -> anyhow::Result<()> {
     log::info!("Handling batch insert...");
     if !Path::new("courses.json").is_file() {
         return Err(anyhow::anyhow!(
@@ -178,7 +456,16 @@ async fn handle_batch_insert() -> anyhow::Result<()> {
         ));
     }
 
-    send_batch_data(&ReadFromFile).await?;
+    if with_occurrences && !Path::new("occurrences.json").is_file() {
+        return Err(anyhow::anyhow!(
+            "occurrences.json doesn't exist, please run cargo r -- scrape --occurrences"
+        ));
+    }
+
+    send_batch_data(&ReadFromFile {
+        occurrences: with_occurrences,
+    })
+    .await?;
     Ok(())
 }
 
@@ -196,76 +483,25 @@ struct Cli {
     /// enable debug logging
     #[argh(switch, short = 'v')]
     verbose: bool,
-}
-
-#[derive(Debug, FromStr)]
-enum YearToScrape {
-    #[display("latest-with-data")]
-    LatestYearWithDataAvailable,
 
-    #[display("{0}")]
-    Year(Year),
-}
+    /// how to format logs: `text` (the default), or `json` for one JSON object per line, including the fields of the spans (year, school area, subject area, course, request) it was logged in
+    #[argh(option, default = "LogFormat::Text")]
+    log_format: LogFormat,
 
-fn get_current_year() -> Year {
-    chrono::Utc::now().year()
-}
+    /// serve Prometheus metrics at `http://<addr>/metrics` while running, e.g. `0.0.0.0:9090`
+    #[argh(option)]
+    metrics_addr: Option<SocketAddr>,
 
-async fn year_has_data(year: Year, ctx: &ScrapingContext) -> anyhow::Result<bool> {
-    let year_url = ctx.scraping_config.get_timetable_api_url_for_year(year);
-    let status = ctx.request_client.fetch_url_status(&year_url, ctx).await?;
-    // UNSW servers will return a 404 if the data for a year isn't available.
-    match status.as_u16() {
-        200 => Ok(true),
-        404 => Ok(false),
-        other => Err(anyhow::anyhow!(
-            "UNSW servers returned an unexpected status code '{}' for a GET request to '{}'",
-            other,
-            year_url
-        )),
-    }
+    /// with `--metrics-addr`, how long to keep serving metrics after the command finishes, so that the final values can be scraped, e.g. `1m`
+    #[argh(option)]
+    metrics_linger: Option<humantime::Duration>,
 }
 
-impl YearToScrape {
-    async fn into_year(&self, ctx: &ScrapingContext) -> anyhow::Result<Year> {
-        match self {
-            YearToScrape::Year(year) => Ok(*year),
-            YearToScrape::LatestYearWithDataAvailable => {
-                // try to find the latest year in the future, the the latest in the past.
-
-                // How far we potentially look into the future and past.
-                const MAX_FUTURE_YEARS: i32 = 20;
-                const MAX_PAST_YEARS: i32 = 20;
-
-                let curr_year = get_current_year();
-
-                // go as far as possible into future.
-                let mut latest_in_future = None;
-                for year in curr_year..curr_year + MAX_FUTURE_YEARS {
-                    if year_has_data(year, ctx).await? {
-                        latest_in_future = Some(year);
-                    } else {
-                        break;
-                    }
-                }
-                if let Some(year) = latest_in_future {
-                    return Ok(year);
-                }
-
-                // go until first possible in the past.
-                // we've already checked the current year.
-                for year in (curr_year - MAX_PAST_YEARS..curr_year).rev() {
-                    if year_has_data(year, ctx).await? {
-                        return Ok(year);
-                    }
-                }
-
-                Err(anyhow::anyhow!(
-                    "no year (neither in the future nor in the past relative to current year) has data"
-                ))
-            }
-        }
-    }
+#[derive(Debug, Clone, Copy, Display, FromStr)]
+#[display(style = "lowercase")]
+enum LogFormat {
+    Text,
+    Json,
 }
 
 #[derive(FromArgs)]
@@ -273,8 +509,17 @@ impl YearToScrape {
 #[enum_dispatch(Exec)]
 enum Command {
     Scrape(Scrape),
+    ScrapeCourse(ScrapeCourse),
+    Canary(Canary),
     BatchInsert(BatchInsert),
     ScrapeAndBatchInsert(ScrapeAndBatchInsert),
+    Export(Export),
+    Ical(Ical),
+    Serve(Serve),
+    Query(QueryClasses),
+    Plan(Plan),
+    Rooms(Rooms),
+    Instructor(Instructor),
 }
 
 /// Perform scraping. Creates a JSON file to store the data.
@@ -288,16 +533,70 @@ struct Scrape {
     /// write to a single JSON file instead
     #[argh(option, long = "to-file")]
     write_to_json_file: Option<String>,
+
+    /// the format of the courses, classes and times files: `json` (the default), `csv` or `parquet`
+    #[argh(option, default = "TableFormat::Json")]
+    format: TableFormat,
+
+    /// also write an occurrences table, with every time expanded into its dated meetings
+    #[argh(switch)]
+    occurrences: bool,
+
+    /// only scrape these subject areas, comma separated, e.g. `COMP,MATH`
+    #[argh(option, long = "subject")]
+    subjects: Vec<String>,
+
+    /// only scrape these courses, comma separated, e.g. `COMP1511`
+    #[argh(option, long = "course")]
+    courses: Vec<String>,
+
+    /// only scrape courses offered to these careers, comma separated, e.g. `Undergraduate`
+    #[argh(option, long = "career")]
+    careers: Vec<String>,
+
+    /// only keep courses whose faculty contains this text (case-insensitive), comma separated
+    #[argh(option, long = "faculty")]
+    faculties: Vec<String>,
+
+    /// only scrape subject areas whose school contains this text (case-insensitive), comma separated
+    #[argh(option, long = "school")]
+    schools: Vec<String>,
+}
+
+/// The format of the courses, classes and times tables. CSV and Parquet have
+/// typed UOC, enrolment and date columns.
+#[derive(Debug, Clone, Copy, Display, FromStr)]
+#[display(style = "lowercase")]
+enum TableFormat {
+    Json,
+    Csv,
+    Parquet,
+}
+
+impl Scrape {
+    fn scrape_filter(&self) -> ScrapeFilter {
+        ScrapeFilter::new(
+            &self.subjects,
+            &self.courses,
+            &self.careers,
+            &self.faculties,
+            &self.schools,
+        )
+    }
 }
 
 impl Exec for Scrape {
     async fn exec(&self) -> anyhow::Result<()> {
         log::info!("Handling scrape...");
 
-        let data = log_execution_time_async("scraping", async || {
-            Data::scrape(&self.year_to_scrape).await
+        let start = Instant::now();
+        let (cancel_token, signal_task) = cancel_on_shutdown_signal();
+        let scraped = log_execution_time_async("scraping", async || {
+            Data::scrape(&self.year_to_scrape, self.scrape_filter(), cancel_token).await
         })
-        .await?;
+        .await;
+        exit_on_shutdown_signal(signal_task);
+        let (data, outcome) = scraped?;
         match &self.write_to_json_file {
             Some(json_file_path) => {
                 log_execution_time_async(
@@ -306,8 +605,137 @@ impl Exec for Scrape {
                 )
                 .await?;
             }
-            None => data.write_to_files().await?,
+            None => data.write_tables(self.format, self.occurrences).await?,
+        }
+        outcome.write_summary(&data, start.elapsed())?;
+        match outcome.incomplete_scrape_report(&data) {
+            Some(report) => {
+                report.write()?;
+                Err(ScrapeCancelled.into())
+            }
+            None => remove_incomplete_scrape_report(),
+        }
+    }
+}
+
+/// Scrape a single course page, e.g. to debug a course that parses wrongly.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "scrape-course")]
+struct ScrapeCourse {
+    /// the course code, e.g. `COMP1511`
+    #[argh(positional)]
+    course_code: String,
+
+    /// the year for which data should be scraped: `latest-with-data` (the latest year with data available), or a calendar year, e.g. `2025`
+    #[argh(option, long = "year", short = 'y')]
+    year_to_scrape: YearToScrape,
+
+    /// the career whose info boxes should be parsed, defaults to `Undergraduate`
+    #[argh(option, long = "career", default = "String::from(\"Undergraduate\")")]
+    career: String,
+
+    /// print the label/data pairs, meeting information and skipped info boxes the parser saw
+    #[argh(switch)]
+    explain: bool,
+}
+
+fn print_course_explanation(explanation: &CourseExplanation) {
+    for skipped in &explanation.skipped_info_boxes {
+        match &skipped.career {
+            Some(career) => println!(
+                "skipped info box {}: career '{}' doesn't match",
+                skipped.info_box_index, career
+            ),
+            None => println!(
+                "skipped info box {}: class belongs to a skipped career",
+                skipped.info_box_index
+            ),
+        }
+    }
+    for (i, class) in explanation.classes.iter().enumerate() {
+        println!("class {}:", i);
+        for (label, data) in &class.label_data_pairs {
+            println!("  {:?} => {:?}", label, data);
+        }
+        println!("  meeting information: {:?}", class.meeting_info);
+    }
+}
+
+impl Exec for ScrapeCourse {
+    async fn exec(&self) -> anyhow::Result<()> {
+        log::info!("Handling scrape course...");
+        let ctx = ScrapingContext::new()?;
+        let year = self.year_to_scrape.resolve_year(&ctx).await?;
+        let partial_course =
+            PartialCourse::for_course_code(&self.course_code, &self.career, year, &ctx);
+        let (course, explanation) = partial_course.complete_with_explanation(&ctx).await?;
+
+        if self.explain {
+            print_course_explanation(&explanation);
+        }
+        if !explanation.found_career_info_box {
+            log::warn!(
+                "{} has no info box for career '{}', try one of: {:?}",
+                course.course_code,
+                self.career,
+                explanation
+                    .skipped_info_boxes
+                    .iter()
+                    .filter_map(|skipped| skipped.career.as_ref())
+                    .collect::<Vec<_>>()
+            );
+        }
+        to_writer_pretty(std::io::stdout(), &course)?;
+        println!();
+        Ok(())
+    }
+}
+
+/// Check that a few known timetable pages still have the structure the scraper expects.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "canary")]
+struct Canary {
+    /// the year whose pages should be checked: `latest-with-data` (the latest year with data available), or a calendar year, e.g. `2025`
+    #[argh(option, long = "year", short = 'y')]
+    year_to_scrape: YearToScrape,
+
+    /// the courses whose subject area and course pages should be checked, comma separated, defaults to `COMP1511`
+    #[argh(option, long = "course")]
+    courses: Vec<String>,
+}
+
+impl Exec for Canary {
+    async fn exec(&self) -> anyhow::Result<()> {
+        log::info!("Handling canary...");
+        let ctx = ScrapingContext::new()?;
+        let year = self.year_to_scrape.resolve_year(&ctx).await?;
+        let mut course_codes = self
+            .courses
+            .iter()
+            .flat_map(|courses| courses.split(','))
+            .map(|course| course.trim().to_string())
+            .filter(|course| !course.is_empty())
+            .collect::<Vec<_>>();
+        if course_codes.is_empty() {
+            course_codes.push("COMP1511".to_string());
+        }
+
+        let checks = run_canary(year, &course_codes, &ctx).await?;
+        let num_failed = checks.iter().filter(|check| !check.passed()).count();
+        for check in &checks {
+            match &check.result {
+                Ok(()) => log::info!("PASS {}: {}", check.url, check.description),
+                Err(e) => log::error!("FAIL {}: {}: {}", check.url, check.description, e),
+            }
+        }
+        if num_failed > 0 {
+            return Err(anyhow::anyhow!(
+                "{} of {} canary checks failed, the timetable website layout probably changed",
+                num_failed,
+                checks.len()
+            ));
         }
+        log::info!("All {} canary checks passed", checks.len());
         Ok(())
     }
 }
@@ -315,12 +743,24 @@ impl Exec for Scrape {
 /// Perform batch insert on JSON files created by `scrape`.
 #[derive(FromArgs)]
 #[argh(subcommand, name = "batch_insert")]
-struct BatchInsert {}
+struct BatchInsert {
+    /// also insert `occurrences.json`, written by `scrape --occurrences`
+    #[argh(switch)]
+    occurrences: bool,
+}
 
 impl Exec for BatchInsert {
     async fn exec(&self) -> anyhow::Result<()> {
         log::info!("Handling batch insert...");
-        handle_batch_insert().await?;
+        // An interrupted `scrape` writes partial files next to the report, and
+        // the batch insert overwrites the tables.
+        if Path::new(INCOMPLETE_SCRAPE_REPORT_PATH).exists() {
+            return Err(anyhow::anyhow!(
+                "not batch inserting an incomplete scrape, see {} or scrape again",
+                INCOMPLETE_SCRAPE_REPORT_PATH
+            ));
+        }
+        handle_batch_insert(self.occurrences).await?;
         Ok(())
     }
 }
@@ -332,14 +772,773 @@ struct ScrapeAndBatchInsert {
     /// the year for which data should be scraped: `latest-with-data` (the latest year with data available), or a calendar year, e.g. `2025`.
     #[argh(option, long = "year", short = 'y')]
     year_to_scrape: YearToScrape,
+
+    /// also insert an occurrences table, with every time expanded into its dated meetings
+    #[argh(switch)]
+    occurrences: bool,
 }
 
 impl Exec for ScrapeAndBatchInsert {
     async fn exec(&self) -> anyhow::Result<()> {
         log::info!("Handling scrape and batch insert...");
-        let data = Data::scrape(&self.year_to_scrape).await?;
-        data.write_to_files().await?;
-        data.handle_batch_insert().await?;
+        let start = Instant::now();
+        let (cancel_token, signal_task) = cancel_on_shutdown_signal();
+        let scraped =
+            Data::scrape(&self.year_to_scrape, ScrapeFilter::default(), cancel_token).await;
+        exit_on_shutdown_signal(signal_task);
+        let (data, outcome) = scraped?;
+        data.write_to_files(self.occurrences).await?;
+        outcome.write_summary(&data, start.elapsed())?;
+        // The batch insert overwrites the tables, so never upload a partial scrape.
+        if let Some(report) = outcome.incomplete_scrape_report(&data) {
+            report.write()?;
+            log::warn!("Not batch inserting an incomplete scrape");
+            return Err(ScrapeCancelled.into());
+        }
+        remove_incomplete_scrape_report()?;
+        data.handle_batch_insert(self.occurrences).await?;
+        Ok(())
+    }
+}
+
+/// Export scraped data to another format.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "export")]
+struct Export {
+    /// the format to export to: `sqlite` (a single database with the courses, classes, times and subject areas tables), `csv` or `parquet` (a file per table), or `static-api` (a tree of JSON files per year, subject area and course, for a static file host)
+    #[argh(positional)]
+    format: ExportFormat,
+
+    /// where to write the export: the database file for `sqlite` (default `timetable.sqlite`), the directory for `csv` and `parquet` (default the current directory), or the root directory for `static-api` (default `static-api`)
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+
+    /// scrape this year to export: `latest-with-data` (the latest year with data available), or a calendar year, e.g. `2025`
+    #[argh(option, long = "year", short = 'y')]
+    year_to_scrape: Option<YearToScrape>,
+
+    /// export a file written by `scrape --to-file` instead of scraping
+    #[argh(option)]
+    from: Option<String>,
+
+    /// also write an occurrences table, with every time expanded into its dated meetings (not for `static-api`)
+    #[argh(switch)]
+    occurrences: bool,
+}
+
+#[derive(Debug, Clone, Copy, Display, FromStr)]
+#[display(style = "kebab-case")]
+enum ExportFormat {
+    Sqlite,
+    Csv,
+    Parquet,
+    StaticApi,
+}
+
+impl Export {
+    fn output(&self) -> &str {
+        match (&self.output, self.format) {
+            (Some(output), _) => output,
+            (None, ExportFormat::Sqlite) => "timetable.sqlite",
+            (None, ExportFormat::Csv | ExportFormat::Parquet) => ".",
+            (None, ExportFormat::StaticApi) => "static-api",
+        }
+    }
+}
+
+impl Exec for Export {
+    async fn exec(&self) -> anyhow::Result<()> {
+        log::info!("Handling export to {}...", self.format);
+        if self.occurrences && matches!(self.format, ExportFormat::StaticApi) {
+            anyhow::bail!("--occurrences isn't supported for static-api");
+        }
+        let data = Data::scrape_or_read(
+            self.year_to_scrape.as_ref(),
+            self.from.as_deref(),
+            ScrapeFilter::default(),
+        )
+        .await?;
+        let output = Path::new(self.output());
+        match self.format {
+            ExportFormat::Sqlite => log_execution_time("exporting to SQLite", || {
+                export_sqlite(
+                    output,
+                    &data.all_courses,
+                    &data.subject_areas,
+                    self.occurrences,
+                )
+            })?,
+            ExportFormat::Csv => log_execution_time("exporting to CSV", || {
+                export_csv(output, &data.all_courses, self.occurrences)
+            })?,
+            ExportFormat::Parquet => log_execution_time("exporting to Parquet", || {
+                export_parquet(output, &data.all_courses, self.occurrences)
+            })?,
+            ExportFormat::StaticApi => {
+                let year = data.year()?;
+                let manifest = log_execution_time("exporting to a static API", || {
+                    export_static_api(output, year, &data.all_courses, &data.subject_areas)
+                })?;
+                log::info!(
+                    "Wrote {} files for {} year(s)",
+                    manifest.files.len(),
+                    manifest.years.len()
+                );
+            }
+        }
+        log::info!(
+            "Exported {} courses to {}",
+            data.all_courses.len(),
+            output.display()
+        );
+        Ok(())
+    }
+}
+
+/// Write an iCalendar (.ics) file with the dated meetings of the chosen classes.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "ical")]
+struct Ical {
+    /// the classes to include: class ids, e.g. `COMP1511Undergraduate-1001-T1-2025`, or a course code, activity and section, e.g. `COMP1511/Lecture/A`
+    #[argh(positional)]
+    classes: Vec<ClassSelection>,
+
+    /// only choose classes in this term, e.g. `T1`, for course code selections that are offered in several terms
+    #[argh(option)]
+    term: Option<String>,
+
+    /// scrape this year for the chosen courses: `latest-with-data` (the latest year with data available), or a calendar year, e.g. `2025`
+    #[argh(option, long = "year", short = 'y')]
+    year_to_scrape: Option<YearToScrape>,
+
+    /// read the classes from a file written by `scrape --to-file` instead of scraping
+    #[argh(option)]
+    from: Option<String>,
+
+    /// where to write the calendar
+    #[argh(option, short = 'o', default = "String::from(\"timetable.ics\")")]
+    output: String,
+}
+
+/// A class chosen on the command line.
+#[derive(Debug, Clone)]
+enum ClassSelection {
+    ClassId(String),
+    Section {
+        course_code: String,
+        activity: String,
+        section: String,
+    },
+}
+
+impl FromStr for ClassSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split('/').collect::<Vec<_>>()[..] {
+            [class_id]
+                if course_code_of(class_id).is_some_and(|code| code.len() < class_id.len()) =>
+            {
+                Ok(ClassSelection::ClassId(class_id.to_string()))
+            }
+            [course_code, activity, section] => Ok(ClassSelection::Section {
+                course_code: course_code.to_uppercase(),
+                activity: activity.to_string(),
+                section: section.to_string(),
+            }),
+            _ => Err(format!(
+                "'{}' is neither a class id nor COURSE/ACTIVITY/SECTION",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ClassSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClassSelection::ClassId(class_id) => write!(f, "{}", class_id),
+            ClassSelection::Section {
+                course_code,
+                activity,
+                section,
+            } => write!(f, "{}/{}/{}", course_code, activity, section),
+        }
+    }
+}
+
+impl ClassSelection {
+    fn course_code(&self) -> &str {
+        match self {
+            ClassSelection::ClassId(class_id) => {
+                course_code_of(class_id).expect("class ids are checked when parsed")
+            }
+            ClassSelection::Section { course_code, .. } => course_code,
+        }
+    }
+
+    fn matches(&self, course: &Course, class: &Class, term: Option<&str>) -> bool {
+        match self {
+            ClassSelection::ClassId(class_id) => &class.class_id == class_id,
+            ClassSelection::Section {
+                course_code,
+                activity,
+                section,
+            } => {
+                &course.course_code == course_code
+                    && class.activity.eq_ignore_ascii_case(activity)
+                    && class.section.eq_ignore_ascii_case(section)
+                    && term.is_none_or(|term| class.term.eq_ignore_ascii_case(term))
+            }
+        }
+    }
+}
+
+impl Exec for Ical {
+    async fn exec(&self) -> anyhow::Result<()> {
+        log::info!("Handling ical...");
+        if self.classes.is_empty() {
+            return Err(anyhow::anyhow!("no classes chosen"));
+        }
+        let course_codes = self
+            .classes
+            .iter()
+            .map(|selection| selection.course_code().to_string())
+            .collect::<Vec<_>>();
+        let scrape_filter = ScrapeFilter::new(&[], &course_codes, &[], &[], &[]);
+        let data = Data::scrape_or_read(
+            self.year_to_scrape.as_ref(),
+            self.from.as_deref(),
+            scrape_filter,
+        )
+        .await?;
+
+        let mut classes = Vec::new();
+        for selection in &self.classes {
+            let matches = data
+                .all_courses
+                .iter()
+                .flat_map(|course| course.classes.iter().map(move |class| (course, class)))
+                .filter(|(course, class)| selection.matches(course, class, self.term.as_deref()))
+                .collect::<Vec<_>>();
+            let terms = matches
+                .iter()
+                .map(|(_, class)| class.term.as_str())
+                .collect::<BTreeSet<_>>();
+            match terms.len() {
+                0 => return Err(anyhow::anyhow!("no class matches '{}'", selection)),
+                1 => classes.extend(matches),
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "'{}' matches classes in {}, choose one with --term",
+                        selection,
+                        terms.into_iter().collect::<Vec<_>>().join(", ")
+                    ));
+                }
+            }
+        }
+
+        let calendar = ical_calendar(&classes, Utc::now());
+        std::fs::write(&self.output, calendar)?;
+        log::info!("Wrote {} classes to {}", classes.len(), self.output);
+        Ok(())
+    }
+}
+
+/// Serve scraped data read-only over REST and GraphQL, in the shape of the Hasuragres tables, for local frontend development.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "serve")]
+struct Serve {
+    /// serve a file written by `scrape --to-file`
+    #[argh(option)]
+    from: Option<String>,
+
+    /// scrape this year to serve: `latest-with-data` (the latest year with data available), or a calendar year, e.g. `2025`
+    #[argh(option, long = "year", short = 'y')]
+    year_to_scrape: Option<YearToScrape>,
+
+    /// the address to listen on (default `127.0.0.1:8080`)
+    #[argh(option, default = "SocketAddr::from(([127, 0, 0, 1], 8080))")]
+    addr: SocketAddr,
+}
+
+impl Exec for Serve {
+    async fn exec(&self) -> anyhow::Result<()> {
+        let data = Data::scrape_or_read(
+            self.year_to_scrape.as_ref(),
+            self.from.as_deref(),
+            ScrapeFilter::default(),
+        )
+        .await?;
+        let listener = TcpListener::bind(self.addr).await?;
+        log::info!(
+            "Serving {} courses at http://{} (GraphiQL at http://{}/graphql)",
+            data.all_courses.len(),
+            self.addr,
+            self.addr
+        );
+        serve_timetable(listener, data.all_courses, shutdown_signal()).await
+    }
+}
+
+/// Find classes in a file written by `scrape --to-file`, e.g. open in-person tutorials on Fridays.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "query")]
+struct QueryClasses {
+    /// the file written by `scrape --to-file` to search
+    #[argh(option)]
+    from: String,
+
+    /// course codes or prefixes of them, e.g. `COMP1511` or `COMP`, comma separated or repeated
+    #[argh(option, long = "course")]
+    courses: Vec<String>,
+
+    /// only classes in this term, e.g. `T2`
+    #[argh(option)]
+    term: Option<String>,
+
+    /// only classes of this activity, e.g. `Tutorial`
+    #[argh(option)]
+    activity: Option<String>,
+
+    /// only classes with this status, e.g. `Open`
+    #[argh(option)]
+    status: Option<String>,
+
+    /// only classes in this mode, e.g. `in-person` or `online`
+    #[argh(option)]
+    mode: Option<String>,
+
+    /// only courses at this campus, e.g. `Sydney`
+    #[argh(option)]
+    campus: Option<String>,
+
+    /// only classes meeting on this day, e.g. `fri`
+    #[argh(option)]
+    day: Option<Weekday>,
+
+    /// only classes meeting at or after this time, e.g. `09:00`
+    #[argh(option, from_str_fn(parse_time_of_day))]
+    after: Option<NaiveTime>,
+
+    /// only classes meeting until this time at the latest, e.g. `17:00`
+    #[argh(option, from_str_fn(parse_time_of_day))]
+    before: Option<NaiveTime>,
+
+    /// only classes with at least this many free seats
+    #[argh(option)]
+    min_free_seats: Option<i32>,
+
+    /// how to print the classes: `table` (the default), `json` or `csv`
+    #[argh(option, default = "QueryFormat::Table")]
+    format: QueryFormat,
+}
+
+#[derive(Debug, Clone, Copy, Display, FromStr)]
+#[display(style = "lowercase")]
+enum QueryFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+fn parse_time_of_day(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_| format!("expected a time like 09:00, got '{}'", value))
+}
+
+impl Exec for QueryClasses {
+    async fn exec(&self) -> anyhow::Result<()> {
+        let data = Data::read_from_single_json(&self.from)?;
+        let query = ClassQuery {
+            courses: self
+                .courses
+                .iter()
+                .flat_map(|courses| courses.split(','))
+                .map(|course| course.trim().to_string())
+                .filter(|course| !course.is_empty())
+                .collect(),
+            term: self.term.clone(),
+            activity: self.activity.clone(),
+            status: self.status.clone(),
+            mode: self.mode.clone(),
+            campus: self.campus.clone(),
+            day: self.day,
+            after: self.after,
+            before: self.before,
+            min_free_seats: self.min_free_seats,
+        };
+        let matches = query.run(&data.all_courses);
+        log::info!("Found {} matching classes", matches.len());
+        let mut stdout = std::io::stdout().lock();
+        match self.format {
+            QueryFormat::Table => write_class_table(&mut stdout, &matches)?,
+            QueryFormat::Json => {
+                to_writer_pretty(&mut stdout, &matches)?;
+                writeln!(stdout)?;
+            }
+            QueryFormat::Csv => write_class_csv(stdout, &matches)?,
+        }
+        Ok(())
+    }
+}
+
+/// Find the best clash-free timetables for a set of courses in a term.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "plan")]
+struct Plan {
+    /// the courses to take, e.g. `COMP1511 MATH1131`
+    #[argh(positional)]
+    course_codes: Vec<String>,
+
+    /// the term to plan, e.g. `T1`
+    #[argh(option)]
+    term: String,
+
+    /// the career to take courses offered in several careers in, e.g. `undergraduate`
+    #[argh(option)]
+    career: Option<String>,
+
+    /// scrape this year for the chosen courses: `latest-with-data` (the latest year with data available), or a calendar year, e.g. `2025`
+    #[argh(option, long = "year", short = 'y')]
+    year_to_scrape: Option<YearToScrape>,
+
+    /// read the classes from a file written by `scrape --to-file` instead of scraping
+    #[argh(option)]
+    from: Option<String>,
+
+    /// prefer no classes before this time, e.g. `10:00`
+    #[argh(option, from_str_fn(parse_time_of_day))]
+    earliest_start: Option<NaiveTime>,
+
+    /// prefer no classes on this day, e.g. `fri`, repeatable
+    #[argh(option, long = "day-off")]
+    days_off: Vec<Weekday>,
+
+    /// prefer classes in this mode, e.g. `in-person` or `online`
+    #[argh(option)]
+    prefer_mode: Option<String>,
+
+    /// also plan with classes that aren't open, e.g. full ones
+    #[argh(switch)]
+    any_status: bool,
+
+    /// how many plans to show (default 5)
+    #[argh(option, default = "5", from_str_fn(parse_plan_limit))]
+    limit: usize,
+
+    /// how to print the plans: `table` (the default) or `json`
+    #[argh(option, default = "PlanFormat::Table")]
+    format: PlanFormat,
+}
+
+#[derive(Debug, Clone, Copy, Display, FromStr)]
+#[display(style = "lowercase")]
+enum PlanFormat {
+    Table,
+    Json,
+}
+
+fn parse_plan_limit(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) | Err(_) => Err(format!("expected a positive number, got '{}'", value)),
+        Ok(limit) => Ok(limit),
+    }
+}
+
+impl Exec for Plan {
+    async fn exec(&self) -> anyhow::Result<()> {
+        log::info!("Handling plan...");
+        if self.course_codes.is_empty() {
+            return Err(anyhow::anyhow!("no courses chosen"));
+        }
+        let scrape_filter = ScrapeFilter::new(&[], &self.course_codes, &[], &[], &[]);
+        let data = Data::scrape_or_read(
+            self.year_to_scrape.as_ref(),
+            self.from.as_deref(),
+            scrape_filter,
+        )
+        .await?;
+        let request = PlanRequest {
+            course_codes: self.course_codes.clone(),
+            term: self.term.clone(),
+            career: self.career.clone(),
+            any_status: self.any_status,
+            preferences: PlanPreferences {
+                earliest_start: self.earliest_start,
+                days_off: self.days_off.clone(),
+                mode: self.prefer_mode.clone(),
+            },
+            limit: self.limit,
+        };
+        let plans =
+            log_execution_time("planning", || plan_timetables(&data.all_courses, &request))?;
+        if plans.is_empty() {
+            return Err(anyhow::anyhow!("every combination of classes has a clash"));
+        }
+        let mut stdout = std::io::stdout().lock();
+        match self.format {
+            PlanFormat::Table => write_plan_table(&mut stdout, &plans)?,
+            PlanFormat::Json => {
+                to_writer_pretty(&mut stdout, &plans)?;
+                writeln!(stdout)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Find free rooms, or how much rooms are used, from the locations of the classes in a term.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "rooms")]
+struct Rooms {
+    #[argh(subcommand)]
+    command: RoomsCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+#[enum_dispatch(Exec)]
+enum RoomsCommand {
+    Free(FreeRooms),
+    Heatmap(RoomHeatmap),
+}
+
+impl Exec for Rooms {
+    async fn exec(&self) -> anyhow::Result<()> {
+        self.command.exec().await
+    }
+}
+
+/// List the rooms not booked by any class at a time, e.g. in building K-J17 on week 5 Tuesday 14:00-16:00.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "free")]
+struct FreeRooms {
+    /// read the classes from a file written by `scrape --to-file`
+    #[argh(option)]
+    from: Option<String>,
+
+    /// scrape this year for the classes: `latest-with-data` (the latest year with data available), or a calendar year, e.g. `2025`
+    #[argh(option, long = "year", short = 'y')]
+    year_to_scrape: Option<YearToScrape>,
+
+    /// the term, e.g. `T1`
+    #[argh(option)]
+    term: String,
+
+    /// only rooms in this building: its code, e.g. `K-J17`, or the start of its name, e.g. `Ainsworth`
+    #[argh(option)]
+    building: Option<String>,
+
+    /// the teaching week, e.g. `5` (default: free in every week of the term)
+    #[argh(option)]
+    week: Option<u32>,
+
+    /// the day, e.g. `tue`
+    #[argh(option)]
+    day: Weekday,
+
+    /// the start of the time, e.g. `14:00`
+    #[argh(option, from_str_fn(parse_time_of_day))]
+    start: NaiveTime,
+
+    /// the end of the time, e.g. `16:00`
+    #[argh(option, from_str_fn(parse_time_of_day))]
+    end: NaiveTime,
+
+    /// how to print the rooms: `table` (the default) or `json`
+    #[argh(option, default = "FreeRoomsFormat::Table")]
+    format: FreeRoomsFormat,
+}
+
+#[derive(Debug, Clone, Copy, Display, FromStr)]
+#[display(style = "lowercase")]
+enum FreeRoomsFormat {
+    Table,
+    Json,
+}
+
+impl Exec for FreeRooms {
+    async fn exec(&self) -> anyhow::Result<()> {
+        if self.start >= self.end {
+            return Err(anyhow::anyhow!(
+                "--start {} isn't before --end {}",
+                self.start.format("%H:%M"),
+                self.end.format("%H:%M")
+            ));
+        }
+        let data = Data::scrape_or_read(
+            self.year_to_scrape.as_ref(),
+            self.from.as_deref(),
+            ScrapeFilter::default(),
+        )
+        .await?;
+        let index = RoomIndex::new(&data.all_courses, &self.term);
+        let rooms = index.free_rooms(
+            self.building.as_deref(),
+            self.week,
+            self.day,
+            self.start,
+            self.end,
+        );
+        log::info!("Found {} free rooms", rooms.len());
+        let mut stdout = std::io::stdout().lock();
+        match self.format {
+            FreeRoomsFormat::Table => write_room_table(&mut stdout, &rooms)?,
+            FreeRoomsFormat::Json => {
+                to_writer_pretty(&mut stdout, &rooms)?;
+                writeln!(stdout)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write how many weeks of a term each room is booked in each hour of the week.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "heatmap")]
+struct RoomHeatmap {
+    /// read the classes from a file written by `scrape --to-file`
+    #[argh(option)]
+    from: Option<String>,
+
+    /// scrape this year for the classes: `latest-with-data` (the latest year with data available), or a calendar year, e.g. `2025`
+    #[argh(option, long = "year", short = 'y')]
+    year_to_scrape: Option<YearToScrape>,
+
+    /// the term, e.g. `T1`
+    #[argh(option)]
+    term: String,
+
+    /// only rooms in this building: its code, e.g. `K-J17`, or the start of its name, e.g. `Ainsworth`
+    #[argh(option)]
+    building: Option<String>,
+
+    /// the format to write: `csv` (the default), with a row per room, day and hour, or `json`
+    #[argh(option, default = "HeatmapFormat::Csv")]
+    format: HeatmapFormat,
+
+    /// the file to write (default: stdout)
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Display, FromStr)]
+#[display(style = "lowercase")]
+enum HeatmapFormat {
+    Csv,
+    Json,
+}
+
+impl Exec for RoomHeatmap {
+    async fn exec(&self) -> anyhow::Result<()> {
+        let data = Data::scrape_or_read(
+            self.year_to_scrape.as_ref(),
+            self.from.as_deref(),
+            ScrapeFilter::default(),
+        )
+        .await?;
+        let heatmap =
+            RoomIndex::new(&data.all_courses, &self.term).heatmap(self.building.as_deref());
+        let mut writer: Box<dyn Write> = match &self.output {
+            Some(output) => Box::new(File::create(output)?),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        match self.format {
+            HeatmapFormat::Csv => write_heatmap_csv(&mut writer, &heatmap)?,
+            HeatmapFormat::Json => {
+                to_writer_pretty(&mut writer, &heatmap)?;
+                writeln!(writer)?;
+            }
+        }
+        writer.flush()?;
+        if let Some(output) = &self.output {
+            log::info!(
+                "Wrote the utilisation of {} rooms to {}",
+                heatmap.rooms.len(),
+                output
+            );
+        }
+        Ok(())
+    }
+}
+
+/// List each instructor's classes per term, flagging times they are double-booked.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "instructor")]
+struct Instructor {
+    /// part of the instructor's name, e.g. `renzella` or `J Renzella` (default: every instructor). Case, titles, punctuation and word order are ignored, initials match full names, and long words may have a typo
+    #[argh(positional)]
+    name: Option<String>,
+
+    /// read the classes from a file written by `scrape --to-file`
+    #[argh(option)]
+    from: Option<String>,
+
+    /// scrape this year for the classes: `latest-with-data` (the latest year with data available), or a calendar year, e.g. `2025`
+    #[argh(option, long = "year", short = 'y')]
+    year_to_scrape: Option<YearToScrape>,
+
+    /// only classes in this term, e.g. `T1`
+    #[argh(option)]
+    term: Option<String>,
+
+    /// how to write the classes: `table` (the default), `json` or `csv`
+    #[argh(option, default = "QueryFormat::Table")]
+    format: QueryFormat,
+
+    /// the file to write (default: stdout)
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+}
+
+impl Exec for Instructor {
+    async fn exec(&self) -> anyhow::Result<()> {
+        let data = Data::scrape_or_read(
+            self.year_to_scrape.as_ref(),
+            self.from.as_deref(),
+            ScrapeFilter::default(),
+        )
+        .await?;
+        let query = InstructorQuery {
+            name: self.name.clone(),
+            term: self.term.clone(),
+        };
+        let timetables = query.run(&data.all_courses);
+        if let Some(name) = &self.name
+            && timetables.is_empty()
+        {
+            return Err(anyhow::anyhow!("no instructor matches '{}'", name));
+        }
+        let double_booked = timetables
+            .iter()
+            .filter(|timetable| timetable.double_booked() > 0)
+            .count();
+        log::info!(
+            "Found {} instructors, {} of them double-booked",
+            timetables.len(),
+            double_booked
+        );
+        let mut writer: Box<dyn Write> = match &self.output {
+            Some(output) => Box::new(File::create(output)?),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        match self.format {
+            QueryFormat::Table => write_instructor_table(&mut writer, &timetables)?,
+            QueryFormat::Json => {
+                to_writer_pretty(&mut writer, &timetables)?;
+                writeln!(writer)?;
+            }
+            QueryFormat::Csv => write_instructor_csv(&mut writer, &timetables)?,
+        }
+        writer.flush()?;
+        if let Some(output) = &self.output {
+            log::info!(
+                "Wrote the classes of {} instructors to {}",
+                timetables.len(),
+                output
+            );
+        }
         Ok(())
     }
 }
@@ -350,18 +1549,44 @@ async fn main() -> anyhow::Result<()> {
 
     let lvl = if cli.verbose {
         // NOTE: we don't currently have any Debug logs, but useful for when we do, or we can config differently.
-        LevelFilter::Debug
+        LevelFilter::DEBUG
     } else {
-        LevelFilter::Info
+        LevelFilter::INFO
     };
-    env_logger::Builder::new()
-        .filter_level(lvl)
-        // Only show error logs from html5ever dependency, since it logs many unnecessary warnings.
-        .filter_module("html5ever", LevelFilter::Error)
-        .format_timestamp_millis()
-        .init();
+    // Only show error logs from html5ever dependency, since it logs many unnecessary warnings.
+    let filter = EnvFilter::new(format!("{lvl},html5ever=error"));
+    // Log to stderr, so that e.g. `scrape-course` output can be piped.
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_writer(std::io::stderr);
+    match cli.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init(),
+    }
 
-    cli.command.exec().await?;
+    if let Some(metrics_addr) = cli.metrics_addr {
+        let listener = TcpListener::bind(metrics_addr).await?;
+        log::info!("Serving metrics at http://{}/metrics", metrics_addr);
+        tokio::spawn(async move {
+            if let Err(e) = serve_metrics(listener).await {
+                log::error!("Metrics server failed: {:?}", e);
+            }
+        });
+    }
 
-    Ok(())
+    let result = cli.command.exec().await;
+
+    if cli.metrics_addr.is_some()
+        && let Some(metrics_linger) = cli.metrics_linger
+    {
+        log::info!("Serving metrics for another {}", metrics_linger);
+        tokio::time::sleep(metrics_linger.into()).await;
+    }
+
+    result
 }
@@ -0,0 +1,231 @@
+use std::{sync::LazyLock, time::Duration};
+
+use axum::{Router, http::header, response::IntoResponse, routing::get};
+use prometheus::{
+    Counter, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use tokio::net::TcpListener;
+
+use crate::parse_error::ParseStage;
+
+/// Buckets for request latencies, from fast cached pages to slow, nearly
+/// timed-out responses.
+const REQUEST_DURATION_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 3.0, 6.0];
+
+/// How a request to the timetable website ended.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum RequestOutcome {
+    Ok,
+    Failed,
+    TimedOut,
+    BodyFailed,
+    BodyTimedOut,
+}
+
+impl RequestOutcome {
+    fn label(self) -> &'static str {
+        match self {
+            RequestOutcome::Ok => "ok",
+            RequestOutcome::Failed => "failed",
+            RequestOutcome::TimedOut => "timed_out",
+            RequestOutcome::BodyFailed => "body_failed",
+            RequestOutcome::BodyTimedOut => "body_timed_out",
+        }
+    }
+}
+
+/// How a batch insert into Hasuragres ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadOutcome {
+    Ok,
+    Unreachable,
+    Rejected,
+    HttpError,
+    InvalidResponse,
+}
+
+impl UploadOutcome {
+    fn label(self) -> &'static str {
+        match self {
+            UploadOutcome::Ok => "ok",
+            UploadOutcome::Unreachable => "unreachable",
+            UploadOutcome::Rejected => "rejected",
+            UploadOutcome::HttpError => "http_error",
+            UploadOutcome::InvalidResponse => "invalid_response",
+        }
+    }
+}
+
+fn parse_stage_label(stage: ParseStage) -> &'static str {
+    match stage {
+        ParseStage::SchoolArea => "school_area",
+        ParseStage::SubjectArea => "subject_area",
+        ParseStage::Course => "course",
+        ParseStage::Class => "class",
+    }
+}
+
+struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    request_rate: IntGauge,
+    rate_limiter_generation: IntGauge,
+    rate_limiter_pauses: IntCounter,
+    rate_limiter_pause_seconds: Counter,
+    parse_errors: IntCounterVec,
+    uploads: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("spooderman".to_string()), None)?;
+        let requests = IntCounterVec::new(
+            Opts::new(
+                "requests_total",
+                "Requests to the timetable website, by outcome.",
+            ),
+            &["outcome"],
+        )?;
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "request_duration_seconds",
+                "How long requests to the timetable website took, including the body, by outcome.",
+            )
+            .buckets(REQUEST_DURATION_BUCKETS.to_vec()),
+            &["outcome"],
+        )?;
+        let request_rate = IntGauge::new(
+            "request_rate",
+            "The request rate of the current rate limiter generation, in requests per second.",
+        )?;
+        let rate_limiter_generation = IntGauge::new(
+            "rate_limiter_generation",
+            "How many times the request rate has been lowered.",
+        )?;
+        let rate_limiter_pauses = IntCounter::new(
+            "rate_limiter_pauses_total",
+            "How many times all requests were paused.",
+        )?;
+        let rate_limiter_pause_seconds = Counter::new(
+            "rate_limiter_pause_seconds_total",
+            "The total length of the pauses started, overlapping pauses included.",
+        )?;
+        let parse_errors = IntCounterVec::new(
+            Opts::new(
+                "parse_errors_total",
+                "Pages that didn't have the structure the parser expected, by stage.",
+            ),
+            &["stage"],
+        )?;
+        let uploads = IntCounterVec::new(
+            Opts::new(
+                "batch_inserts_total",
+                "Batch inserts into Hasuragres, by outcome.",
+            ),
+            &["outcome"],
+        )?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(request_duration.clone()))?;
+        registry.register(Box::new(request_rate.clone()))?;
+        registry.register(Box::new(rate_limiter_generation.clone()))?;
+        registry.register(Box::new(rate_limiter_pauses.clone()))?;
+        registry.register(Box::new(rate_limiter_pause_seconds.clone()))?;
+        registry.register(Box::new(parse_errors.clone()))?;
+        registry.register(Box::new(uploads.clone()))?;
+
+        Ok(Self {
+            registry,
+            requests,
+            request_duration,
+            request_rate,
+            rate_limiter_generation,
+            rate_limiter_pauses,
+            rate_limiter_pause_seconds,
+            parse_errors,
+            uploads,
+        })
+    }
+}
+
+static METRICS: LazyLock<Metrics> =
+    LazyLock::new(|| Metrics::new().expect("metric names and labels are valid and unique"));
+
+pub(crate) fn record_request(outcome: RequestOutcome, duration: Duration) {
+    let labels = [outcome.label()];
+    METRICS.requests.with_label_values(&labels).inc();
+    METRICS
+        .request_duration
+        .with_label_values(&labels)
+        .observe(duration.as_secs_f64());
+}
+
+/// Records that a rate limiter generation with the given request rate started.
+pub(crate) fn record_rate_limiter_generation(generation: u64, req_per_sec: u32) {
+    METRICS.rate_limiter_generation.set(generation as i64);
+    METRICS.request_rate.set(i64::from(req_per_sec));
+}
+
+pub(crate) fn record_pause(duration: Duration) {
+    METRICS.rate_limiter_pauses.inc();
+    METRICS
+        .rate_limiter_pause_seconds
+        .inc_by(duration.as_secs_f64());
+}
+
+pub(crate) fn record_parse_error(stage: ParseStage) {
+    METRICS
+        .parse_errors
+        .with_label_values(&[parse_stage_label(stage)])
+        .inc();
+}
+
+pub(crate) fn record_upload(outcome: UploadOutcome) {
+    METRICS.uploads.with_label_values(&[outcome.label()]).inc();
+}
+
+/// All metrics, in the Prometheus text format.
+pub fn gather_metrics() -> String {
+    TextEncoder::new()
+        .encode_to_string(&METRICS.registry.gather())
+        .expect("metrics are valid UTF-8")
+}
+
+async fn metrics_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        gather_metrics(),
+    )
+}
+
+/// Serves the metrics at `/metrics` until the process exits.
+pub async fn serve_metrics(listener: TcpListener) -> anyhow::Result<()> {
+    let app = Router::new().route("/metrics", get(metrics_handler));
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorded_metrics_are_gathered() {
+        record_request(RequestOutcome::Failed, Duration::from_millis(120));
+        record_parse_error(ParseStage::Class);
+        record_upload(UploadOutcome::Rejected);
+
+        let metrics = gather_metrics();
+        for line in [
+            "spooderman_requests_total{outcome=\"failed\"}",
+            "spooderman_request_duration_seconds_bucket{outcome=\"failed\",le=\"0.25\"}",
+            "spooderman_parse_errors_total{stage=\"class\"}",
+            "spooderman_batch_inserts_total{outcome=\"rejected\"}",
+        ] {
+            assert!(metrics.contains(line), "{line} missing from:\n{metrics}");
+        }
+        assert!(metrics.contains("# TYPE spooderman_rate_limiter_pauses_total counter"));
+    }
+}
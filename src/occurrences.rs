@@ -0,0 +1,206 @@
+use std::collections::BTreeSet;
+
+use anyhow::Context;
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, TimeZone, Weekday};
+use chrono_tz::Tz;
+
+use crate::{Class, Time, rows::ClassRow};
+
+/// The timezone of all times on the timetable website.
+pub const TIMEZONE: Tz = chrono_tz::Australia::Sydney;
+
+/// One dated meeting of a class, e.g. the week 3 lecture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub week: u32,
+    pub start: DateTime<Tz>,
+    pub end: DateTime<Tz>,
+}
+
+/// Expands the weekly pattern of `time` into the dated meetings of `class`.
+///
+/// Week 1 is the week (starting Monday) of the start of the class's offering
+/// period, or of its meeting dates if the offering period can't be parsed.
+/// Meetings outside the class's meeting dates are left out.
+pub fn occurrences(class: &Class, time: &Time) -> anyhow::Result<Vec<Occurrence>> {
+    let row = ClassRow::new(class);
+    let meeting_dates = row.meeting_dates();
+    let (term_start, _) = row.offering_period().or(meeting_dates).with_context(|| {
+        format!(
+            "no offering period ('{}') or meeting dates ('{}') to count weeks from",
+            class.offering_period, class.meeting_dates
+        )
+    })?;
+    let week_1 = term_start - Days::new(term_start.weekday().num_days_from_monday().into());
+    let slot = WeeklySlot::parse(time)?;
+
+    let mut occurrences = Vec::new();
+    for &week in &slot.weeks {
+        let date = week_1
+            + Days::new(u64::from(week - 1) * 7 + u64::from(slot.day.num_days_from_monday()));
+        if let Some((first, last)) = meeting_dates
+            && !(first..=last).contains(&date)
+        {
+            continue;
+        }
+        occurrences.push(Occurrence {
+            week,
+            start: local_datetime(date, slot.start)?,
+            end: local_datetime(date, slot.end)?,
+        });
+    }
+    Ok(occurrences)
+}
+
+/// A weekly meeting of a class, e.g. Mondays 09:00 - 11:00 in weeks 1-5.
+#[derive(Debug)]
+pub(crate) struct WeeklySlot {
+    pub(crate) day: Weekday,
+    pub(crate) start: NaiveTime,
+    pub(crate) end: NaiveTime,
+    pub(crate) weeks: BTreeSet<u32>,
+}
+
+impl WeeklySlot {
+    /// Fails if the day, time range or weeks of `time` can't be parsed, e.g.
+    /// for `TBA` times.
+    pub(crate) fn parse(time: &Time) -> anyhow::Result<Self> {
+        let day = time
+            .day
+            .parse::<Weekday>()
+            .map_err(|_| anyhow::anyhow!("unknown day '{}'", time.day))?;
+        let (start, end) = parse_time_range(&time.time)?;
+        let weeks = parse_weeks(&time.weeks)?;
+        Ok(Self {
+            day,
+            start,
+            end,
+            weeks,
+        })
+    }
+
+    /// Whether the slots meet at the same time in some week.
+    pub(crate) fn clashes(&self, other: &WeeklySlot) -> bool {
+        self.day == other.day
+            && self.start < other.end
+            && other.start < self.end
+            && !self.weeks.is_disjoint(&other.weeks)
+    }
+}
+
+fn local_datetime(date: NaiveDate, time: NaiveTime) -> anyhow::Result<DateTime<Tz>> {
+    TIMEZONE
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .with_context(|| format!("{} {} doesn't exist in {}", date, time, TIMEZONE))
+}
+
+/// Parses e.g. `09:00 - 11:00`.
+pub(crate) fn parse_time_range(time_range: &str) -> anyhow::Result<(NaiveTime, NaiveTime)> {
+    let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M");
+    time_range
+        .split_once('-')
+        .and_then(|(start, end)| Some((parse(start).ok()?, parse(end).ok()?)))
+        .with_context(|| format!("unknown time range '{}'", time_range))
+}
+
+/// Parses e.g. `1-5,7-10` or `2,4,6`.
+pub(crate) fn parse_weeks(weeks: &str) -> anyhow::Result<BTreeSet<u32>> {
+    let parse = |week: &str| {
+        week.trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|week| *week >= 1)
+            .with_context(|| format!("unknown weeks '{}'", weeks))
+    };
+    let mut parsed = BTreeSet::new();
+    for range in weeks.split(',') {
+        match range.split_once('-') {
+            Some((first, last)) => parsed.extend(parse(first)?..=parse(last)?),
+            None => {
+                parsed.insert(parse(range)?);
+            }
+        }
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures;
+
+    fn class(offering_period: &str, meeting_dates: &str) -> Class {
+        Class {
+            offering_period: offering_period.to_string(),
+            meeting_dates: meeting_dates.to_string(),
+            times: None,
+            ..test_fixtures::class("COMP1511", "A", "T1", Vec::new())
+        }
+    }
+
+    fn time(day: &str, time: &str, weeks: &str) -> Time {
+        Time {
+            location: "CLB 7 (K-E19-G05)".to_string(),
+            weeks: weeks.to_string(),
+            ..test_fixtures::time(day, time)
+        }
+    }
+
+    fn starts(occurrences: &[Occurrence]) -> Vec<String> {
+        occurrences
+            .iter()
+            .map(|occurrence| occurrence.start.to_rfc3339())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_weeks() {
+        assert_eq!(
+            parse_weeks("1-3, 5,7-8")
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [1, 2, 3, 5, 7, 8]
+        );
+        assert!(parse_weeks("N1").is_err());
+        assert!(parse_weeks("0-2").is_err());
+    }
+
+    #[test]
+    fn test_occurrences_cross_daylight_saving() {
+        // Daylight saving ended on 6 April 2025.
+        let class = class("17/02/2025 - 18/05/2025", "17/02/2025 - 18/05/2025");
+        let occurrences = occurrences(&class, &time("Wed", "09:00 - 11:00", "1,7-8")).unwrap();
+        assert_eq!(
+            starts(&occurrences),
+            [
+                "2025-02-19T09:00:00+11:00",
+                "2025-04-02T09:00:00+11:00",
+                "2025-04-09T09:00:00+10:00",
+            ]
+        );
+        assert_eq!(occurrences[0].week, 1);
+        assert_eq!(occurrences[0].end.to_rfc3339(), "2025-02-19T11:00:00+11:00");
+    }
+
+    #[test]
+    fn test_occurrences_count_weeks_from_the_offering_period() {
+        // The class only meets from week 3, but weeks are term weeks.
+        let class = class("17/02/2025 - 18/05/2025", "03/03/2025 - 18/05/2025");
+        let occurrences = occurrences(&class, &time("Mon", "13:00 - 14:00", "2-4")).unwrap();
+        assert_eq!(
+            starts(&occurrences),
+            ["2025-03-03T13:00:00+11:00", "2025-03-10T13:00:00+11:00"]
+        );
+    }
+
+    #[test]
+    fn test_occurrences_of_unparseable_times() {
+        let class = class("17/02/2025 - 18/05/2025", "17/02/2025 - 18/05/2025");
+        assert!(occurrences(&class, &time("Someday", "09:00 - 11:00", "1")).is_err());
+        assert!(occurrences(&class, &time("Mon", "TBA", "1")).is_err());
+        let class = self::class("TBA", "TBA");
+        assert!(occurrences(&class, &time("Mon", "09:00 - 11:00", "1")).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt;
+
+use scraper::ElementRef;
+use serde::Serialize;
+
+use crate::metrics;
+
+/// Max number of characters of HTML kept in a `ParseError`.
+const MAX_EXCERPT_LEN: usize = 600;
+
+/// The scraping stage a page was being parsed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ParseStage {
+    SchoolArea,
+    SubjectArea,
+    Course,
+    Class,
+}
+
+impl fmt::Display for ParseStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self {
+            ParseStage::SchoolArea => "school area page",
+            ParseStage::SubjectArea => "subject area page",
+            ParseStage::Course => "course page",
+            ParseStage::Class => "class info box",
+        };
+        write!(f, "{}", stage)
+    }
+}
+
+/// What the parser expected to find, but didn't.
+#[derive(Debug, Clone, Serialize)]
+pub enum Expectation {
+    /// An element matching a CSS selector.
+    Selector(String),
+    /// A `td.label` cell with this text.
+    Label(String),
+    /// Some other structural property of the page.
+    Structure(String),
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expectation::Selector(selector) => write!(f, "an element matching '{}'", selector),
+            Expectation::Label(label) => write!(f, "a '{}' label", label),
+            Expectation::Structure(description) => write!(f, "{}", description),
+        }
+    }
+}
+
+/// A page didn't have the structure the parser expected, most likely because
+/// UNSW changed the timetable website layout.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseError {
+    pub stage: ParseStage,
+    pub url: String,
+    pub expected: Expectation,
+    /// The (truncated) HTML surrounding where the expected element should have been.
+    pub html_excerpt: String,
+}
+
+impl ParseError {
+    pub fn new(stage: ParseStage, url: &str, expected: Expectation, html_excerpt: &str) -> Self {
+        Self {
+            stage,
+            url: url.to_string(),
+            expected,
+            html_excerpt: excerpt(html_excerpt),
+        }
+    }
+
+    /// The expected element wasn't found inside `context`.
+    pub fn in_element(
+        stage: ParseStage,
+        url: &str,
+        expected: Expectation,
+        context: ElementRef,
+    ) -> Self {
+        Self::new(stage, url, expected, &context.html())
+    }
+
+    /// Logs a parse error that the scrape carries on after, and counts it in
+    /// the metrics.
+    pub(crate) fn warn(&self) {
+        metrics::record_parse_error(self.stage);
+        log::warn!("{}", self);
+    }
+
+    pub fn missing_selector(
+        stage: ParseStage,
+        url: &str,
+        selector: &str,
+        context: ElementRef,
+    ) -> Self {
+        Self::in_element(
+            stage,
+            url,
+            Expectation::Selector(selector.to_string()),
+            context,
+        )
+    }
+}
+
+/// Collapses whitespace and truncates, so that excerpts are readable in logs.
+fn excerpt(html: &str) -> String {
+    let collapsed = html.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(MAX_EXCERPT_LEN) {
+        Some((end, _)) => format!("{}...", &collapsed[..end]),
+        None => collapsed,
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to parse {} {}: expected {}, maybe the timetable website layout changed, near: {}",
+            self.stage, self.url, self.expected, self.html_excerpt
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excerpt() {
+        assert_eq!(
+            excerpt("<tr>\n    <td>COMP</td>\n</tr>"),
+            "<tr> <td>COMP</td> </tr>"
+        );
+        let long = "a".repeat(MAX_EXCERPT_LEN + 10);
+        assert_eq!(excerpt(&long).len(), MAX_EXCERPT_LEN + "...".len());
+    }
+}
@@ -0,0 +1,678 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, BinaryHeap},
+    io::Write,
+};
+
+use chrono::{NaiveTime, Timelike, Weekday};
+use serde::Serialize;
+
+use crate::{Class, Course, Time, occurrences::WeeklySlot, utils::write_aligned};
+
+/// What to plan a timetable for.
+#[derive(Debug, Clone)]
+pub struct PlanRequest {
+    /// e.g. `COMP1511`.
+    pub course_codes: Vec<String>,
+    /// e.g. `T1`.
+    pub term: String,
+    /// Needed for courses offered in several careers, e.g. `Undergraduate`.
+    pub career: Option<String>,
+    /// Also plan with classes that aren't open, e.g. full ones.
+    pub any_status: bool,
+    pub preferences: PlanPreferences,
+    /// How many of the best plans to return.
+    pub limit: usize,
+}
+
+/// Preferences for ranking plans. They don't rule any plan out, but plans
+/// that ignore them rank lower.
+#[derive(Debug, Clone, Default)]
+pub struct PlanPreferences {
+    /// No classes before this time of day.
+    pub earliest_start: Option<NaiveTime>,
+    /// No classes on these days.
+    pub days_off: Vec<Weekday>,
+    /// The preferred class mode, e.g. `In Person` or `Online`.
+    pub mode: Option<String>,
+}
+
+/// How well a plan fits the preferences: the lower `total`, the better.
+///
+/// `total` is a weighted sum of the other fields. Classes on a day off weigh
+/// the most, then classes in the wrong mode, then time before the earliest
+/// start, then the number of days with classes and the gaps between classes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct PlanScore {
+    pub total: u32,
+    /// Days off that have classes.
+    pub days_off_with_classes: u32,
+    /// Classes not in the preferred mode.
+    pub mode_mismatches: u32,
+    /// Weekly minutes of classes before the earliest start.
+    pub minutes_before_earliest_start: u32,
+    /// Weekdays with classes.
+    pub days: u32,
+    /// Weekly minutes between classes on the same day.
+    pub gap_minutes: u32,
+}
+
+const DAY_OFF_WEIGHT: u32 = 1000;
+const MODE_MISMATCH_WEIGHT: u32 = 300;
+const MINUTE_BEFORE_EARLIEST_START_WEIGHT: u32 = 2;
+const DAY_WEIGHT: u32 = 120;
+const GAP_MINUTE_WEIGHT: u32 = 1;
+
+/// Stops the search after this many steps, ranking only the plans found so
+/// far, so that courses with many classes can't run forever.
+const MAX_SEARCH_STEPS: usize = 2_000_000;
+
+/// A clash-free choice of one class per activity of every requested course.
+#[derive(Debug, Serialize)]
+pub struct Plan<'a> {
+    pub score: PlanScore,
+    pub classes: Vec<PlannedClass<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedClass<'a> {
+    pub course_code: &'a str,
+    pub activity: &'a str,
+    pub class_id: &'a str,
+    pub section: &'a str,
+    pub status: &'a str,
+    pub mode: &'a str,
+    pub times: &'a [Time],
+    /// Other sections of the activity at the same times and in the same
+    /// mode, any of which could be taken instead.
+    pub alternatives: Vec<&'a str>,
+}
+
+/// Classes of an activity with the same times and mode, so interchangeable.
+struct ClassOption<'a> {
+    course_code: &'a str,
+    classes: Vec<&'a Class>,
+    slots: Vec<WeeklySlot>,
+}
+
+/// An activity of a course that needs a class, e.g. the COMP1511 tutorial.
+struct Activity<'a> {
+    options: Vec<ClassOption<'a>>,
+}
+
+/// The best `request.limit` clash-free timetables for the requested courses,
+/// best first: one class for each activity (lecture, tutorial, ...) of each
+/// course in the term.
+///
+/// Course codes are case insensitive, and a course asked for twice is only
+/// planned once. Times that can't be parsed (e.g. `TBA`) never clash. It is
+/// an error if a course isn't offered in the term, or an activity has no
+/// class to choose.
+pub fn plan_timetables<'a>(
+    courses: &'a [Course],
+    request: &PlanRequest,
+) -> anyhow::Result<Vec<Plan<'a>>> {
+    let course_codes = request
+        .course_codes
+        .iter()
+        .map(|course_code| course_code.trim().to_uppercase())
+        .collect::<BTreeSet<_>>();
+    let mut activities = Vec::new();
+    for course_code in &course_codes {
+        activities.extend(course_activities(courses, course_code, request)?);
+    }
+    // Choosing for the most constrained activities first prunes the most.
+    activities.sort_by_key(|activity| activity.options.len());
+
+    let mut search = Search {
+        activities: &activities,
+        preferences: &request.preferences,
+        limit: request.limit,
+        best: BinaryHeap::new(),
+        chosen: Vec::new(),
+        steps: 0,
+    };
+    search.search();
+    if search.steps >= MAX_SEARCH_STEPS {
+        log::warn!(
+            "Stopped searching after {} steps, so the plans are the best of those found so far",
+            MAX_SEARCH_STEPS
+        );
+    }
+
+    let mut best = search.best.into_vec();
+    best.sort();
+    Ok(best
+        .into_iter()
+        .map(|(score, chosen)| {
+            let mut classes = chosen
+                .iter()
+                .enumerate()
+                .map(|(i, option)| planned_class(&activities[i].options[*option]))
+                .collect::<Vec<_>>();
+            classes.sort_by_key(|class| (class.course_code, class.activity));
+            Plan { score, classes }
+        })
+        .collect())
+}
+
+fn course_activities<'a>(
+    courses: &'a [Course],
+    course_code: &str,
+    request: &PlanRequest,
+) -> anyhow::Result<Vec<Activity<'a>>> {
+    let offerings = courses
+        .iter()
+        .filter(|course| course.course_code == course_code)
+        .filter(|course| {
+            request.career.as_ref().is_none_or(|career| {
+                course
+                    .career
+                    .as_ref()
+                    .is_some_and(|course_career| course_career.eq_ignore_ascii_case(career))
+            })
+        })
+        .filter(|course| {
+            course
+                .classes
+                .iter()
+                .any(|class| class.term.eq_ignore_ascii_case(&request.term))
+        })
+        .collect::<Vec<_>>();
+    let course = match offerings[..] {
+        [course] => course,
+        [] => anyhow::bail!("{} has no classes in {}", course_code, request.term),
+        _ => anyhow::bail!(
+            "{} is offered in several careers ({}), choose one with --career",
+            course_code,
+            offerings
+                .iter()
+                .filter_map(|course| course.career.as_deref())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+
+    let mut classes_by_activity = BTreeMap::<&str, Vec<&Class>>::new();
+    for class in &course.classes {
+        if class.term.eq_ignore_ascii_case(&request.term) {
+            classes_by_activity
+                .entry(&class.activity)
+                .or_default()
+                .push(class);
+        }
+    }
+    let mut activities = Vec::new();
+    for (activity, classes) in classes_by_activity {
+        // Equivalent classes are keyed by their mode and their times as written.
+        let mut options = BTreeMap::<_, ClassOption>::new();
+        for class in classes {
+            if !request.any_status && class.status != "Open" {
+                continue;
+            }
+            let mut key = class
+                .times
+                .iter()
+                .flatten()
+                .map(|time| (time.day.as_str(), time.time.as_str(), time.weeks.as_str()))
+                .collect::<Vec<_>>();
+            key.sort_unstable();
+            options
+                .entry((class.mode.as_str(), key))
+                .or_insert_with(|| ClassOption {
+                    course_code: &course.course_code,
+                    classes: Vec::new(),
+                    slots: class.times.iter().flatten().filter_map(slot).collect(),
+                })
+                .classes
+                .push(class);
+        }
+        if options.is_empty() {
+            anyhow::bail!(
+                "{} has no open {} classes in {}{}",
+                course_code,
+                activity,
+                request.term,
+                if request.any_status {
+                    ""
+                } else {
+                    ", use --any-status to plan with full or closed ones"
+                }
+            );
+        }
+        activities.push(Activity {
+            options: options.into_values().collect(),
+        });
+    }
+    Ok(activities)
+}
+
+/// The weekly meeting of `time`, or `None` if it can't be parsed.
+fn slot(time: &Time) -> Option<WeeklySlot> {
+    match WeeklySlot::parse(time) {
+        Ok(slot) => Some(slot),
+        Err(e) => {
+            log::debug!("Ignoring {} {} for clashes: {:#}", time.day, time.time, e);
+            None
+        }
+    }
+}
+
+fn planned_class<'a>(option: &ClassOption<'a>) -> PlannedClass<'a> {
+    let class = option.classes[0];
+    PlannedClass {
+        course_code: option.course_code,
+        activity: &class.activity,
+        class_id: &class.class_id,
+        section: &class.section,
+        status: &class.status,
+        mode: &class.mode,
+        times: class.times.as_deref().unwrap_or_default(),
+        alternatives: option.classes[1..]
+            .iter()
+            .map(|class| class.section.as_str())
+            .collect(),
+    }
+}
+
+/// A depth-first search over the options of each activity, skipping options
+/// that clash with ones already chosen.
+struct Search<'s, 'a> {
+    activities: &'s [Activity<'a>],
+    preferences: &'s PlanPreferences,
+    limit: usize,
+    /// The best plans so far, worst on top, as the option chosen per activity.
+    best: BinaryHeap<(PlanScore, Vec<usize>)>,
+    chosen: Vec<usize>,
+    steps: usize,
+}
+
+impl Search<'_, '_> {
+    fn search(&mut self) {
+        if self.steps >= MAX_SEARCH_STEPS || self.limit == 0 {
+            return;
+        }
+        self.steps += 1;
+        let depth = self.chosen.len();
+        let Some(activity) = self.activities.get(depth) else {
+            let score = self.score();
+            if self.best.len() < self.limit {
+                self.best.push((score, self.chosen.clone()));
+            } else if let Some(worst) = self.best.peek()
+                && (score, &self.chosen) < (worst.0, &worst.1)
+            {
+                self.best.pop();
+                self.best.push((score, self.chosen.clone()));
+            }
+            return;
+        };
+        for (i, option) in activity.options.iter().enumerate() {
+            let clashes = self.chosen_options().any(|chosen| {
+                chosen
+                    .slots
+                    .iter()
+                    .any(|a| option.slots.iter().any(|b| a.clashes(b)))
+            });
+            if !clashes {
+                self.chosen.push(i);
+                self.search();
+                self.chosen.pop();
+            }
+        }
+    }
+
+    fn chosen_options(&self) -> impl Iterator<Item = &ClassOption<'_>> {
+        self.chosen
+            .iter()
+            .enumerate()
+            .map(|(i, option)| &self.activities[i].options[*option])
+    }
+
+    fn score(&self) -> PlanScore {
+        let preferences = self.preferences;
+        let mut slots_by_day = BTreeMap::<_, Vec<&WeeklySlot>>::new();
+        let mut mode_mismatches = 0;
+        for option in self.chosen_options() {
+            for slot in &option.slots {
+                slots_by_day
+                    .entry(slot.day.num_days_from_monday())
+                    .or_default()
+                    .push(slot);
+            }
+            if let Some(mode) = &preferences.mode
+                && !same_mode(mode, &option.classes[0].mode)
+            {
+                mode_mismatches += 1;
+            }
+        }
+
+        let mut days_off_with_classes = 0;
+        let mut minutes_before_earliest_start = 0;
+        let mut gap_minutes = 0;
+        for slots in slots_by_day.values_mut() {
+            if preferences.days_off.contains(&slots[0].day) {
+                days_off_with_classes += 1;
+            }
+            slots.sort_by_key(|slot| slot.start);
+            let mut end = slots[0].end;
+            for slot in slots.iter() {
+                if let Some(earliest_start) = preferences.earliest_start
+                    && slot.start < earliest_start
+                {
+                    minutes_before_earliest_start +=
+                        minutes(earliest_start.min(slot.end)) - minutes(slot.start);
+                }
+                if slot.start > end {
+                    gap_minutes += minutes(slot.start) - minutes(end);
+                }
+                end = end.max(slot.end);
+            }
+        }
+        let days = slots_by_day.len() as u32;
+        PlanScore {
+            total: days_off_with_classes * DAY_OFF_WEIGHT
+                + mode_mismatches * MODE_MISMATCH_WEIGHT
+                + minutes_before_earliest_start * MINUTE_BEFORE_EARLIEST_START_WEIGHT
+                + days * DAY_WEIGHT
+                + gap_minutes * GAP_MINUTE_WEIGHT,
+            days_off_with_classes,
+            mode_mismatches,
+            minutes_before_earliest_start,
+            days,
+            gap_minutes,
+        }
+    }
+}
+
+fn minutes(time: NaiveTime) -> u32 {
+    time.num_seconds_from_midnight() / 60
+}
+
+/// Compares modes ignoring case, with `-` matching a space, so `in-person`
+/// matches `In Person`.
+fn same_mode(a: &str, b: &str) -> bool {
+    let normalise = |mode: &str| mode.trim().to_lowercase().replace('-', " ");
+    normalise(a) == normalise(b)
+}
+
+/// Writes `plans` for reading in a terminal: a line with the score of each
+/// plan, then a line per class.
+pub fn write_plan_table(writer: &mut impl Write, plans: &[Plan]) -> anyhow::Result<()> {
+    for (i, plan) in plans.iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        let score = plan.score;
+        let mut notes = vec![
+            format!("days: {}", score.days),
+            format!("gaps: {} min", score.gap_minutes),
+        ];
+        if score.days_off_with_classes > 0 {
+            notes.push(format!(
+                "days off with classes: {}",
+                score.days_off_with_classes
+            ));
+        }
+        if score.mode_mismatches > 0 {
+            notes.push(format!(
+                "classes in another mode: {}",
+                score.mode_mismatches
+            ));
+        }
+        if score.minutes_before_earliest_start > 0 {
+            notes.push(format!(
+                "before the earliest start: {} min",
+                score.minutes_before_earliest_start
+            ));
+        }
+        writeln!(
+            writer,
+            "Plan {} (score {}: {})",
+            i + 1,
+            score.total,
+            notes.join(", ")
+        )?;
+        let rows = plan
+            .classes
+            .iter()
+            .map(|class| {
+                let times = class
+                    .times
+                    .iter()
+                    .map(|time| format!("{} {} ({})", time.day, time.time, time.location))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                let alternatives = if class.alternatives.is_empty() {
+                    String::new()
+                } else {
+                    format!("or {}", class.alternatives.join(", "))
+                };
+                [
+                    class.course_code.to_string(),
+                    class.activity.to_string(),
+                    class.section.to_string(),
+                    class.mode.to_string(),
+                    times,
+                    alternatives,
+                ]
+            })
+            .collect::<Vec<_>>();
+        write_aligned(writer, &rows, "  ")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{self, time};
+
+    fn class(
+        course_code: &str,
+        section: &str,
+        activity: &str,
+        mode: &str,
+        times: &[(&str, &str)],
+    ) -> Class {
+        let times = times
+            .iter()
+            .map(|&(day, hours)| Time {
+                weeks: "1-5,7-10".to_string(),
+                ..time(day, hours)
+            })
+            .collect();
+        Class {
+            activity: activity.to_string(),
+            mode: mode.to_string(),
+            ..test_fixtures::class(course_code, section, "T1", times)
+        }
+    }
+
+    fn course(course_code: &str, career: &str, classes: Vec<Class>) -> Course {
+        Course {
+            course_id: format!("{}{}", course_code, career),
+            career: Some(career.to_string()),
+            ..test_fixtures::course(course_code, classes)
+        }
+    }
+
+    fn courses() -> Vec<Course> {
+        let mut full = class(
+            "COMP1511",
+            "F",
+            "Tutorial",
+            "In Person",
+            &[("Fri", "09:00 - 11:00")],
+        );
+        full.status = "Full".to_string();
+        vec![
+            course(
+                "COMP1511",
+                "Undergraduate",
+                vec![
+                    class(
+                        "COMP1511",
+                        "A",
+                        "Lecture",
+                        "In Person",
+                        &[("Mon", "10:00 - 12:00")],
+                    ),
+                    class(
+                        "COMP1511",
+                        "M10A",
+                        "Tutorial",
+                        "In Person",
+                        &[("Mon", "11:00 - 13:00")],
+                    ),
+                    class(
+                        "COMP1511",
+                        "M13A",
+                        "Tutorial",
+                        "In Person",
+                        &[("Mon", "13:00 - 15:00")],
+                    ),
+                    class(
+                        "COMP1511",
+                        "M13B",
+                        "Tutorial",
+                        "In Person",
+                        &[("Mon", "13:00 - 15:00")],
+                    ),
+                    class(
+                        "COMP1511",
+                        "T08A",
+                        "Tutorial",
+                        "In Person",
+                        &[("Tue", "08:00 - 10:00")],
+                    ),
+                    class(
+                        "COMP1511",
+                        "W18A",
+                        "Tutorial",
+                        "Online",
+                        &[("Wed", "18:00 - 20:00")],
+                    ),
+                    full,
+                ],
+            ),
+            course(
+                "MATH1131",
+                "Undergraduate",
+                vec![class(
+                    "MATH1131",
+                    "A",
+                    "Lecture",
+                    "In Person",
+                    &[("Mon", "15:00 - 16:00"), ("Tue", "TBA")],
+                )],
+            ),
+        ]
+    }
+
+    fn request(preferences: PlanPreferences) -> PlanRequest {
+        PlanRequest {
+            course_codes: vec!["comp1511".to_string(), "MATH1131".to_string()],
+            term: "T1".to_string(),
+            career: None,
+            any_status: false,
+            preferences,
+            limit: 10,
+        }
+    }
+
+    fn tutorials(plans: &[Plan]) -> Vec<String> {
+        plans
+            .iter()
+            .map(|plan| {
+                let tutorial = plan
+                    .classes
+                    .iter()
+                    .find(|class| class.activity == "Tutorial")
+                    .unwrap();
+                tutorial.section.to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_plans_avoid_clashes_and_rank_compact_days_first() {
+        let courses = courses();
+        let plans = plan_timetables(&courses, &request(PlanPreferences::default())).unwrap();
+        // M10A clashes with the lecture and F is full. M13A and M13B are
+        // interchangeable, and keep all classes on Monday.
+        assert_eq!(tutorials(&plans), ["M13A", "T08A", "W18A"]);
+        assert_eq!(plans[0].classes.len(), 3);
+        let activities = plans[0]
+            .classes
+            .iter()
+            .map(|class| format!("{} {}", class.course_code, class.activity))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            activities,
+            ["COMP1511 Lecture", "COMP1511 Tutorial", "MATH1131 Lecture"]
+        );
+        assert_eq!(plans[0].classes[1].alternatives, ["M13B"]);
+        assert_eq!(
+            plans[0].score,
+            PlanScore {
+                total: DAY_WEIGHT + 60 * GAP_MINUTE_WEIGHT,
+                days_off_with_classes: 0,
+                mode_mismatches: 0,
+                minutes_before_earliest_start: 0,
+                days: 1,
+                gap_minutes: 60,
+            }
+        );
+    }
+
+    #[test]
+    fn test_plans_rank_by_preferences() {
+        let courses = courses();
+        let preferences = PlanPreferences {
+            earliest_start: Some(NaiveTime::from_hms_opt(9, 0, 0).unwrap()),
+            days_off: vec![Weekday::Mon],
+            mode: Some("online".to_string()),
+        };
+        let plans = plan_timetables(&courses, &request(preferences)).unwrap();
+        assert_eq!(tutorials(&plans), ["W18A", "M13A", "T08A"]);
+        assert_eq!(plans[2].score.minutes_before_earliest_start, 60);
+        assert_eq!(plans[2].score.mode_mismatches, 3);
+    }
+
+    #[test]
+    fn test_plans_a_course_asked_for_twice_once() {
+        let courses = courses();
+        let mut request = request(PlanPreferences::default());
+        request.course_codes.push(" COMP1511".to_string());
+        let plans = plan_timetables(&courses, &request).unwrap();
+        assert_eq!(tutorials(&plans), ["M13A", "T08A", "W18A"]);
+        assert_eq!(plans[0].classes.len(), 3);
+    }
+
+    #[test]
+    fn test_plan_errors() {
+        let courses = courses();
+        let mut request = request(PlanPreferences::default());
+        request.term = "T2".to_string();
+        let error = plan_timetables(&courses, &request).unwrap_err();
+        assert_eq!(error.to_string(), "COMP1511 has no classes in T2");
+
+        let mut courses = courses;
+        courses.push(course(
+            "MATH1131",
+            "Postgraduate",
+            vec![class(
+                "MATH1131",
+                "A",
+                "Lecture",
+                "In Person",
+                &[("Mon", "15:00 - 16:00")],
+            )],
+        ));
+        let error =
+            plan_timetables(&courses, &self::request(PlanPreferences::default())).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "MATH1131 is offered in several careers (Undergraduate, Postgraduate), choose one with --career"
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use governor::{
     Quota, RateLimiter as GovernorRateLimiter,
-    clock::{QuantaClock, QuantaInstant},
+    clock::Clock as GovernorClock,
     middleware::NoOpMiddleware,
     state::{InMemoryState, NotKeyed},
 };
@@ -9,6 +9,7 @@ use num_traits::ToPrimitive;
 use std::{
     cmp::max,
     fmt::{self},
+    future::Future,
     num::NonZeroU32,
     sync::Arc,
     time::Duration,
@@ -20,7 +21,7 @@ use tokio::{
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-use crate::Request;
+use crate::{Request, metrics};
 
 // The higher, the faster.
 const DEFAULT_REQ_PER_SEC: NonZeroU32 = nonzero!(100u32);
@@ -34,6 +35,47 @@ const EXPONENTIAL_REQUEST_RATE_BACKOFF: f64 = 2.0 / 3.0;
 // The lower, the faster we restart after request rate change.
 const PAUSE_AFTER_REQ_RATE_CHANGE: Duration = Duration::from_secs(5);
 
+/// How fast the rate limiter starts, and how it backs off when requests fail.
+#[derive(Debug, Clone)]
+pub struct RateLimitPolicy {
+    /// The initial average request rate.
+    pub req_per_sec: NonZeroU32,
+    /// The minimum time between two requests. Never lowered.
+    pub min_time_between_requests: Duration,
+    /// What the request rate is multiplied by after a failed request, in (0, 1).
+    pub backoff: f64,
+    /// How long to pause all requests for after lowering the request rate.
+    pub pause_after_rate_change: Duration,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        Self {
+            req_per_sec: DEFAULT_REQ_PER_SEC,
+            min_time_between_requests: DEFAULT_MS_BETWEEN_REQ,
+            backoff: EXPONENTIAL_REQUEST_RATE_BACKOFF,
+            pause_after_rate_change: PAUSE_AFTER_REQ_RATE_CHANGE,
+        }
+    }
+}
+
+impl RateLimitPolicy {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.min_time_between_requests.is_zero() {
+            return Err(anyhow::anyhow!(
+                "minimum time between requests should be larger than 0"
+            ));
+        }
+        if !(self.backoff > 0.0 && self.backoff < 1.0) {
+            return Err(anyhow::anyhow!(
+                "request rate backoff should be between 0 and 1 (exclusive), but is {}",
+                self.backoff
+            ));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct RequestRate {
     req_per_sec: NonZeroU32,
@@ -56,6 +98,10 @@ impl RequestRate {
             uuid,
         }
     }
+
+    pub fn req_per_sec(&self) -> u32 {
+        self.req_per_sec.get()
+    }
 }
 
 impl fmt::Display for RequestRate {
@@ -67,40 +113,91 @@ impl fmt::Display for RequestRate {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// The source of time for a `RateLimiter`, and how it waits for a deadline.
+pub trait Clock: Clone + Send + Sync + 'static {
+    fn now(&self) -> Instant;
+    fn sleep_until(&self, deadline: Instant) -> impl Future<Output = ()> + Send;
+}
+
+/// Tokio's clock, so tests can control the rate limiter with
+/// `tokio::time::pause` and `tokio::time::advance`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep_until(deadline)
+    }
+}
+
+/// Adapts our `Clock` for the governor rate limiters, which would otherwise
+/// measure (and wait for) real time.
+#[derive(Clone)]
+struct GovernorClockAdapter<C>(C);
+
+impl<C: Clock> GovernorClock for GovernorClockAdapter<C> {
+    type Instant = std::time::Instant;
+
+    fn now(&self) -> Self::Instant {
+        self.0.now().into_std()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Gate {
     Active,
     PausedUntil(Instant),
 }
 
-type SpecificGovernorRateLimiter =
-    GovernorRateLimiter<NotKeyed, InMemoryState, QuantaClock, NoOpMiddleware<QuantaInstant>>;
+type SpecificGovernorRateLimiter<C> = GovernorRateLimiter<
+    NotKeyed,
+    InMemoryState,
+    GovernorClockAdapter<C>,
+    NoOpMiddleware<std::time::Instant>,
+>;
 
-struct FixedRateLimiter {
+struct FixedRateLimiter<C: Clock> {
     request_rate: RequestRate,
-    req_per_sec_rate_limiter: SpecificGovernorRateLimiter,
-    ms_between_req_rate_limiter: SpecificGovernorRateLimiter,
+    clock: C,
+    req_per_sec_rate_limiter: SpecificGovernorRateLimiter<C>,
+    ms_between_req_rate_limiter: SpecificGovernorRateLimiter<C>,
 }
 
-impl FixedRateLimiter {
-    fn new(request_rate: RequestRate) -> Self {
+impl<C: Clock> FixedRateLimiter<C> {
+    fn new(request_rate: RequestRate, clock: C) -> Self {
         // Limit to X total req/sec on average.
-        let req_per_sec_rate_limiter =
-            GovernorRateLimiter::direct(Quota::per_second(request_rate.req_per_sec));
+        let req_per_sec_rate_limiter = GovernorRateLimiter::direct_with_clock(
+            Quota::per_second(request_rate.req_per_sec),
+            GovernorClockAdapter(clock.clone()),
+        );
 
         // Limit to Y req/ms (i.e. no two requests closer than Y ms).
-        let ms_between_req_rate_limiter = GovernorRateLimiter::direct(
+        let ms_between_req_rate_limiter = GovernorRateLimiter::direct_with_clock(
             Quota::with_period(request_rate.ms_between_req)
                 .expect("millis between requests should be larger than 0"),
+            GovernorClockAdapter(clock.clone()),
         );
 
         Self {
             request_rate,
+            clock,
             req_per_sec_rate_limiter,
             ms_between_req_rate_limiter,
         }
     }
 
+    /// Like governor's `until_ready`, but waits using our clock.
+    async fn until_ready(&self, rate_limiter: &SpecificGovernorRateLimiter<C>) {
+        while let Err(not_until) = rate_limiter.check() {
+            let wait_time = not_until.wait_time_from(self.clock.now().into_std());
+            self.clock.sleep_until(self.clock.now() + wait_time).await;
+        }
+    }
+
     pub async fn wait_until_ready(&self) {
         // The order in which we await the rate limiters matters:
 
@@ -115,13 +212,13 @@ impl FixedRateLimiter {
         // check, which will be strict and only allow 1 caller to pass every Y ms.
 
         // We won't call this more than X times per sec.
-        self.req_per_sec_rate_limiter.until_ready().await;
+        self.until_ready(&self.req_per_sec_rate_limiter).await;
         // Y ms have passed since the last time we called this.
-        self.ms_between_req_rate_limiter.until_ready().await;
+        self.until_ready(&self.ms_between_req_rate_limiter).await;
     }
 }
 
-impl Drop for FixedRateLimiter {
+impl<C: Clock> Drop for FixedRateLimiter<C> {
     fn drop(&mut self) {
         log::info!(
             "rate limiter with request rate {} is no longer referenced",
@@ -130,37 +227,54 @@ impl Drop for FixedRateLimiter {
     }
 }
 
-struct RateLimiterGeneration {
-    rate_limiter: FixedRateLimiter,
+struct RateLimiterGeneration<C: Clock> {
+    rate_limiter: FixedRateLimiter<C>,
     cancel_token: CancellationToken,
+    /// How many times the request rate was lowered before this generation.
+    number: u64,
 }
 
-impl RateLimiterGeneration {
-    fn new(fixed_rate_limiter: FixedRateLimiter) -> Self {
+impl<C: Clock> RateLimiterGeneration<C> {
+    fn new(fixed_rate_limiter: FixedRateLimiter<C>, number: u64) -> Self {
+        metrics::record_rate_limiter_generation(
+            number,
+            fixed_rate_limiter.request_rate.req_per_sec(),
+        );
         Self {
             rate_limiter: fixed_rate_limiter,
             cancel_token: CancellationToken::new(),
+            number,
         }
     }
 }
 
-pub struct RateLimiter {
+pub struct RateLimiter<C: Clock = TokioClock> {
+    policy: RateLimitPolicy,
+    clock: C,
     // Hot-swappable rate limiter, wrapped in an Arc to avoid holding lock during waits.
-    rate_limiter_generation: RwLock<Arc<RateLimiterGeneration>>,
+    rate_limiter_generation: RwLock<Arc<RateLimiterGeneration<C>>>,
     // Gate used to allow pausing all requests for a period of time.
     gate_tx: watch::Sender<Gate>,
     gate_rx: watch::Receiver<Gate>,
 }
 
 impl RateLimiter {
-    pub fn new() -> Self {
-        let request_rate = RequestRate::new(DEFAULT_REQ_PER_SEC, DEFAULT_MS_BETWEEN_REQ);
-        let fixed_rate_limiter = FixedRateLimiter::new(request_rate);
+    pub fn with_policy(policy: RateLimitPolicy) -> Self {
+        Self::with_policy_and_clock(policy, TokioClock)
+    }
+}
+
+impl<C: Clock> RateLimiter<C> {
+    pub fn with_policy_and_clock(policy: RateLimitPolicy, clock: C) -> Self {
+        let request_rate = RequestRate::new(policy.req_per_sec, policy.min_time_between_requests);
+        let fixed_rate_limiter = FixedRateLimiter::new(request_rate, clock.clone());
         let rate_limiter_generation =
-            RwLock::new(Arc::new(RateLimiterGeneration::new(fixed_rate_limiter)));
+            RwLock::new(Arc::new(RateLimiterGeneration::new(fixed_rate_limiter, 0)));
         let (gate_tx, gate_rx) = watch::channel(Gate::Active);
 
         Self {
+            policy,
+            clock,
             rate_limiter_generation,
             gate_tx,
             gate_rx,
@@ -168,12 +282,14 @@ impl RateLimiter {
     }
 
     async fn wait_until_active(&self) {
+        // Mark each gate value we look at as seen, so that `changed` only
+        // resolves once the gate changes again (instead of spinning while paused).
+        let mut rx = self.gate_rx.clone();
         loop {
-            let curr_gate = *self.gate_rx.borrow();
+            let curr_gate = *rx.borrow_and_update();
             match curr_gate {
                 Gate::Active => return,
                 Gate::PausedUntil(_deadline) => {
-                    let mut rx = self.gate_rx.clone();
                     rx.changed()
                         .await
                         .expect("sender can't get dropped, it's part of RateLimiter struct");
@@ -187,11 +303,12 @@ impl RateLimiter {
             "Pausing new requests for {}",
             humantime::Duration::from(duration)
         );
+        metrics::record_pause(duration);
 
-        let new_deadline = Instant::now() + duration;
+        let new_deadline = self.clock.now() + duration;
 
         // Start or extend the pause atomically.
-        let _ = self.gate_tx.send_modify(|gate| {
+        self.gate_tx.send_modify(|gate| {
             *gate = match *gate {
                 Gate::Active => Gate::PausedUntil(new_deadline),
                 Gate::PausedUntil(curr_deadline) => {
@@ -203,9 +320,10 @@ impl RateLimiter {
 
         // Schedule unpause at deadline.
         let tx = self.gate_tx.clone();
+        let clock = self.clock.clone();
         tokio::task::spawn(async move {
-            tokio::time::sleep_until(new_deadline).await;
-            let _ = tx.send_modify(|gate| {
+            clock.sleep_until(new_deadline).await;
+            tx.send_modify(|gate| {
                 *gate = match *gate {
                     Gate::Active => Gate::Active,
                     Gate::PausedUntil(curr_deadline) => {
@@ -240,7 +358,7 @@ pub enum PermitResult {
     Cancelled,
 }
 
-impl RateLimiter {
+impl<C: Clock> RateLimiter<C> {
     pub async fn wait_until_ready(&self) -> PermitResult {
         // Don't hold the read lock while waiting on rate limiter, so writer can quickly get write lock.
         let generation = { Arc::clone(&*self.rate_limiter_generation.read().await) };
@@ -260,10 +378,12 @@ impl RateLimiter {
         }
     }
 
+    /// Returns the new request rate, or `None` if the failure was ignored
+    /// because the request used an old request rate.
     pub async fn lower_request_rate<'a, 'b>(
         &self,
         failed_request: Request<'a, 'b>,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<Option<RequestRate>> {
         {
             // Hold the write lock until update is complete: important to ensure there is
             // only one failed request that wins/comes first.
@@ -290,15 +410,16 @@ impl RateLimiter {
                     failed_request,
                     failed_request.request_rate_used,
                 );
-                return Ok(());
+                return Ok(None);
             }
 
             let old_request_rate = curr_request_rate;
-            let new_req_per_sec_maybe_zero = f64::floor(
-                f64::from(old_request_rate.req_per_sec.get()) * EXPONENTIAL_REQUEST_RATE_BACKOFF,
-            )
-            .to_u32()
-            .expect("request rate will never be negative or too large to represent in a u32");
+            let new_req_per_sec_maybe_zero =
+                f64::floor(f64::from(old_request_rate.req_per_sec.get()) * self.policy.backoff)
+                    .to_u32()
+                    .expect(
+                        "request rate will never be negative or too large to represent in a u32",
+                    );
             let Ok(new_req_per_sec) = NonZeroU32::try_from(new_req_per_sec_maybe_zero) else {
                 let err_msg = "request rate is 0 req/sec, can't be lowered further";
                 log::error!("{}", err_msg);
@@ -317,7 +438,7 @@ impl RateLimiter {
 
             // Before switching to the new request rate, we should back off
             // completely for a while.
-            self.pause_for(PAUSE_AFTER_REQ_RATE_CHANGE).await;
+            self.pause_for(self.policy.pause_after_rate_change).await;
 
             // Kill all spawned requests that are currently waiting on the old
             // rate limiter.
@@ -333,11 +454,233 @@ impl RateLimiter {
             // setup the cancellation logic themselves.
             // Once all requests to the old rate limiter have completed, it will be dropped.
 
-            let new_rate_limiter = FixedRateLimiter::new(new_request_rate);
-            let new_generation = Arc::new(RateLimiterGeneration::new(new_rate_limiter));
+            let new_rate_limiter = FixedRateLimiter::new(new_request_rate, self.clock.clone());
+            let new_generation = Arc::new(RateLimiterGeneration::new(
+                new_rate_limiter,
+                generation.number + 1,
+            ));
             *generation = new_generation;
+
+            Ok(Some(new_request_rate))
         }
+    }
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::task::JoinSet;
+
+    fn failed_request(request_rate_used: RequestRate) -> Request<'static, 'static> {
+        Request::new(
+            "https://timetable.unsw.edu.au/2025/COMP1511.html",
+            request_rate_used,
+            &None,
+        )
+    }
+
+    async fn granted_rate(limiter: &RateLimiter) -> RequestRate {
+        match limiter.wait_until_ready().await {
+            PermitResult::Granted { request_rate_used } => request_rate_used,
+            PermitResult::Cancelled => panic!("nothing should have cancelled this wait"),
+        }
+    }
+
+    fn gate(limiter: &RateLimiter) -> Gate {
+        *limiter.gate_rx.borrow()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_is_extended_by_later_deadline() {
+        let limiter = RateLimiter::with_policy(RateLimitPolicy::default());
+        let start = Instant::now();
+        limiter.pause_for(Duration::from_secs(5)).await;
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        limiter.pause_for(Duration::from_secs(5)).await;
+        assert_eq!(
+            gate(&limiter),
+            Gate::PausedUntil(start + Duration::from_secs(7))
+        );
+
+        // The wake-up for the first deadline must leave the gate paused.
+        limiter.wait_until_active().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(7));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_is_not_shortened_by_earlier_deadline() {
+        let limiter = RateLimiter::with_policy(RateLimitPolicy::default());
+        let start = Instant::now();
+        limiter.pause_for(Duration::from_secs(5)).await;
+        limiter.pause_for(Duration::from_secs(1)).await;
+        assert_eq!(
+            gate(&limiter),
+            Gate::PausedUntil(start + Duration::from_secs(5))
+        );
+
+        limiter.wait_until_active().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_overlapping_pauses_resume_at_latest_deadline() {
+        // Every unpause task wakes up to find its own deadline or a later one,
+        // never an earlier one (the `unreachable!` branch), whatever the order
+        // of the pauses. If that branch were hit, that task would panic without
+        // resuming requests, so the gate would stay paused.
+        let limiter = Arc::new(RateLimiter::with_policy(RateLimitPolicy::default()));
+        let start = Instant::now();
+        let pauses = [
+            (0, 3),
+            (0, 3),
+            (1, 1),
+            (1, 6),
+            (2, 2),
+            (4, 1),
+            (4, 3),
+            (6, 1),
+        ];
+        let latest_deadline = pauses.iter().map(|(at, len)| at + len).max().unwrap();
+
+        let mut waiters = JoinSet::new();
+        for _ in 0..100 {
+            let limiter = Arc::clone(&limiter);
+            waiters.spawn(async move {
+                granted_rate(&limiter).await;
+                start.elapsed()
+            });
+        }
+        for (at, len) in pauses {
+            tokio::time::sleep_until(start + Duration::from_secs(at)).await;
+            limiter.pause_for(Duration::from_secs(len)).await;
+        }
+
+        limiter.wait_until_active().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(latest_deadline));
+        // Some waiters got through before the first pause, but none during it.
+        for granted_after in waiters.join_all().await {
+            assert!(
+                granted_after < Duration::from_millis(10)
+                    || granted_after >= Duration::from_secs(latest_deadline),
+                "granted during pause, after {:?}",
+                granted_after
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lowering_rate_cancels_waiters_on_old_generation() {
+        let limiter = Arc::new(RateLimiter::with_policy(RateLimitPolicy::default()));
+        let old_rate = granted_rate(&limiter).await;
+
+        // These all have to wait for the time between requests to pass, which
+        // it won't, since time only moves when every task is idle.
+        let mut waiters = JoinSet::new();
+        for _ in 0..50 {
+            let limiter = Arc::clone(&limiter);
+            waiters.spawn(async move { limiter.wait_until_ready().await });
+        }
+        tokio::task::yield_now().await;
+
+        let start = Instant::now();
+        limiter
+            .lower_request_rate(failed_request(old_rate))
+            .await
+            .unwrap();
+        for result in waiters.join_all().await {
+            assert!(matches!(result, PermitResult::Cancelled));
+        }
+
+        // New waiters use the new generation, once the pause is over.
+        let new_rate = granted_rate(&limiter).await;
+        assert!(new_rate != old_rate);
+        assert_eq!(new_rate.to_string(), "66 req/s");
+        assert_eq!(start.elapsed(), PAUSE_AFTER_REQ_RATE_CHANGE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failures_with_stale_rate_are_ignored() {
+        let limiter = RateLimiter::with_policy(RateLimitPolicy::default());
+        let old_rate = granted_rate(&limiter).await;
+        limiter
+            .lower_request_rate(failed_request(old_rate))
+            .await
+            .unwrap();
+        let curr_rate = granted_rate(&limiter).await;
+
+        for _ in 0..10 {
+            assert!(
+                limiter
+                    .lower_request_rate(failed_request(old_rate))
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        }
+        assert_eq!(gate(&limiter), Gate::Active);
+        assert!(granted_rate(&limiter).await == curr_rate);
+
+        let new_rate = limiter
+            .lower_request_rate(failed_request(curr_rate))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(new_rate.req_per_sec(), 44);
+        assert!(granted_rate(&limiter).await == new_rate);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_request_rate_backs_off_until_zero() {
+        let limiter = RateLimiter::with_policy(RateLimitPolicy::default());
+        let mut rates = Vec::new();
+        loop {
+            let rate = granted_rate(&limiter).await;
+            rates.push(rate.to_string());
+            if limiter
+                .lower_request_rate(failed_request(rate))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+        assert_eq!(
+            rates,
+            [
+                "100 req/s",
+                "66 req/s",
+                "44 req/s",
+                "29 req/s",
+                "19 req/s",
+                "12 req/s",
+                "8 req/s",
+                "5 req/s",
+                "3 req/s",
+                "2 req/s",
+                "1 req/s",
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrent_waiters_respect_request_rate() {
+        let limiter = Arc::new(RateLimiter::with_policy(RateLimitPolicy::default()));
+        let start = Instant::now();
+        let mut waiters = JoinSet::new();
+        for _ in 0..200 {
+            let limiter = Arc::clone(&limiter);
+            waiters.spawn(async move {
+                granted_rate(&limiter).await;
+                start.elapsed()
+            });
+        }
+        let mut granted_after = waiters.join_all().await;
+        granted_after.sort();
+
+        for pair in granted_after.windows(2) {
+            assert!(pair[1] - pair[0] >= DEFAULT_MS_BETWEEN_REQ);
+        }
+        // After a burst of 100, requests are let through at 100 req/s.
+        assert!(granted_after[199] >= Duration::from_millis(990));
     }
 }
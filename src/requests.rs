@@ -2,17 +2,43 @@ use std::{fmt, time::Duration};
 
 use derive_new::new;
 use reqwest::{Client, ClientBuilder, StatusCode};
+use tokio::time::Instant;
+use tracing::Instrument;
 
 use crate::{
     ScrapingContext,
-    ratelimit::{PermitResult, RateLimiter, RequestRate},
+    metrics::{self, RequestOutcome},
+    ratelimit::{PermitResult, RateLimitPolicy, RateLimiter, RequestRate},
 };
 
 const GET_REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
 const RESPONSE_BODY_TIMEOUT: Duration = Duration::from_secs(3);
 
+/// HTTP settings for requests to the timetable website.
+#[derive(Debug, Clone)]
+pub struct HttpSettings {
+    /// How long to wait for the response headers before retrying.
+    pub request_timeout: Duration,
+    /// How long to wait for the response body before retrying.
+    pub response_body_timeout: Duration,
+    pub user_agent: Option<String>,
+    pub accept_invalid_certs: bool,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            request_timeout: GET_REQUEST_TIMEOUT,
+            response_body_timeout: RESPONSE_BODY_TIMEOUT,
+            user_agent: None,
+            accept_invalid_certs: true,
+        }
+    }
+}
+
 pub struct RequestClient {
     client: Client,
+    http_settings: HttpSettings,
     rate_limiter: RateLimiter,
 }
 
@@ -34,83 +60,53 @@ impl<'a, 'b> fmt::Display for Request<'a, 'b> {
 
 impl RequestClient {
     pub fn new() -> anyhow::Result<Self> {
-        let client = ClientBuilder::new()
-            .danger_accept_invalid_certs(true)
-            .build()?;
-        let rate_limiter = RateLimiter::new();
+        Self::with_settings(HttpSettings::default(), RateLimitPolicy::default())
+    }
+
+    pub fn with_settings(
+        http_settings: HttpSettings,
+        rate_limit_policy: RateLimitPolicy,
+    ) -> anyhow::Result<Self> {
+        rate_limit_policy.validate()?;
+        let mut client_builder =
+            ClientBuilder::new().danger_accept_invalid_certs(http_settings.accept_invalid_certs);
+        if let Some(user_agent) = &http_settings.user_agent {
+            client_builder = client_builder.user_agent(user_agent);
+        }
+        let client = client_builder.build()?;
+        let rate_limiter = RateLimiter::with_policy(rate_limit_policy);
         Ok(Self {
             client,
+            http_settings,
             rate_limiter,
         })
     }
 
-    async fn fetch_url_response_and_body(
+    pub async fn fetch_url_response_and_body(
         &self,
         url: &str,
         ctx: &ScrapingContext,
     ) -> anyhow::Result<(StatusCode, String)> {
         let maybe_course_code = ctx.timetable_url_regex.extract_course_code(url).ok();
+        let mut attempt = 0;
         loop {
             // Wait (non-blocking) until we're allowed to make a request according
             // to our self-imposed rate-limiting policy.
             match self.rate_limiter.wait_until_ready().await {
                 PermitResult::Granted { request_rate_used } => {
+                    attempt += 1;
+                    ctx.stats
+                        .record_request_rate(request_rate_used.req_per_sec());
                     let request = Request::new(url, request_rate_used, &maybe_course_code);
-                    let failed_request = {
-                        match tokio::time::timeout(GET_REQUEST_TIMEOUT, self.client.get(url).send())
-                            .await
-                        {
-                            Ok(Ok(response)) => {
-                                // The server might still rate-limit us by sending the response body very slowly.
-                                let status = response.status().clone();
-                                match tokio::time::timeout(RESPONSE_BODY_TIMEOUT, response.text())
-                                    .await
-                                {
-                                    Ok(Ok(body)) => return Ok((status, body)),
-                                    Ok(Err(e)) => {
-                                        log::warn!(
-                                            "fetching body for {} failed ({}) using {}, maybe reduce request rate",
-                                            request,
-                                            e,
-                                            request_rate_used
-                                        );
-                                        request
-                                    }
-                                    Err(_) => {
-                                        // Maybe UNSW servers are rate-limiting us by responding very slowly instead of returning an error.
-                                        log::warn!(
-                                            "fetching body for {} timed out using {}, maybe reduce request rate",
-                                            request,
-                                            request_rate_used
-                                        );
-                                        request
-                                    }
-                                }
-                            }
-                            Ok(Err(e)) => {
-                                log::warn!(
-                                    "get {} failed ({}) using {}, maybe reduce request rate",
-                                    request,
-                                    e,
-                                    request_rate_used
-                                );
-                                request
-                            }
-                            Err(_) => {
-                                log::warn!(
-                                    "get {} timed out using {}, maybe reduce request rate",
-                                    request,
-                                    request_rate_used
-                                );
-                                request
-                            }
-                        }
-                    };
-
-                    // If we got rate-limited by UNSW servers, we are probably making too
-                    // many requests, so we should send requests at a lower rate (i.e.
-                    // rate-limit ourselves more).
-                    self.rate_limiter.lower_request_rate(failed_request).await?;
+                    let span = tracing::info_span!(
+                        "request",
+                        url,
+                        attempt,
+                        request_rate = %request_rate_used
+                    );
+                    if let Some(response) = self.attempt(request, ctx).instrument(span).await? {
+                        return Ok(response);
+                    }
                 }
                 PermitResult::Cancelled => {
                     // If this rate-limit-wait was cancelled, try again.
@@ -120,6 +116,83 @@ impl RequestClient {
         }
     }
 
+    /// Makes a single request, returning `None` (after lowering the request
+    /// rate) if it should be retried.
+    async fn attempt<'a, 'b>(
+        &self,
+        request: Request<'a, 'b>,
+        ctx: &ScrapingContext,
+    ) -> anyhow::Result<Option<(StatusCode, String)>> {
+        let request_rate_used = request.request_rate_used;
+        let started = Instant::now();
+        match tokio::time::timeout(
+            self.http_settings.request_timeout,
+            self.client.get(request.url).send(),
+        )
+        .await
+        {
+            Ok(Ok(response)) => {
+                // The server might still rate-limit us by sending the response body very slowly.
+                let status = response.status();
+                match tokio::time::timeout(
+                    self.http_settings.response_body_timeout,
+                    response.text(),
+                )
+                .await
+                {
+                    Ok(Ok(body)) => {
+                        metrics::record_request(RequestOutcome::Ok, started.elapsed());
+                        return Ok(Some((status, body)));
+                    }
+                    Ok(Err(e)) => {
+                        metrics::record_request(RequestOutcome::BodyFailed, started.elapsed());
+                        log::warn!(
+                            "fetching body for {} failed ({}) using {}, maybe reduce request rate",
+                            request,
+                            e,
+                            request_rate_used
+                        );
+                    }
+                    Err(_) => {
+                        // Maybe UNSW servers are rate-limiting us by responding very slowly instead of returning an error.
+                        metrics::record_request(RequestOutcome::BodyTimedOut, started.elapsed());
+                        log::warn!(
+                            "fetching body for {} timed out using {}, maybe reduce request rate",
+                            request,
+                            request_rate_used
+                        );
+                    }
+                }
+            }
+            Ok(Err(e)) => {
+                metrics::record_request(RequestOutcome::Failed, started.elapsed());
+                log::warn!(
+                    "get {} failed ({}) using {}, maybe reduce request rate",
+                    request,
+                    e,
+                    request_rate_used
+                );
+            }
+            Err(_) => {
+                metrics::record_request(RequestOutcome::TimedOut, started.elapsed());
+                log::warn!(
+                    "get {} timed out using {}, maybe reduce request rate",
+                    request,
+                    request_rate_used
+                );
+            }
+        }
+
+        // If we got rate-limited by UNSW servers, we are probably making too
+        // many requests, so we should send requests at a lower rate (i.e.
+        // rate-limit ourselves more).
+        ctx.stats.record_retry();
+        if let Some(new_request_rate) = self.rate_limiter.lower_request_rate(request).await? {
+            ctx.stats.record_rate_change(new_request_rate.req_per_sec());
+        }
+        Ok(None)
+    }
+
     pub async fn fetch_url_status(
         &self,
         url: &str,
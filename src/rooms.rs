@@ -0,0 +1,437 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+};
+
+use chrono::{NaiveTime, Timelike, Weekday};
+use serde::Serialize;
+
+use crate::{Course, occurrences::WeeklySlot, utils::write_aligned};
+
+/// The days of a [`RoomHeatmap`], in order.
+const DAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// A room, from a location like `Ainsworth G03 (K-J17-G03)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Room<'a> {
+    /// e.g. `Ainsworth G03 (K-J17-G03)`.
+    pub location: &'a str,
+    /// e.g. `K-J17-G03`.
+    pub room: &'a str,
+    /// The campus and grid reference of the room, e.g. `K-J17`.
+    pub building: &'a str,
+}
+
+impl<'a> Room<'a> {
+    /// `None` for locations without a room code, e.g. `Online`.
+    pub fn new(location: &'a str) -> Option<Self> {
+        let (_, room) = location.trim_end().strip_suffix(')')?.rsplit_once('(')?;
+        let building = match room.match_indices('-').nth(1) {
+            Some((end, _)) => &room[..end],
+            None => return None,
+        };
+        Some(Self {
+            location,
+            room,
+            building,
+        })
+    }
+
+    /// Whether the room is in `building`: either its code, e.g. `K-J17`, or the
+    /// start of the location, e.g. `Ainsworth`. Case is ignored.
+    fn is_in(&self, building: &str) -> bool {
+        let building = building.trim().to_lowercase();
+        self.building.to_lowercase() == building
+            || self.location.to_lowercase().starts_with(&building)
+    }
+}
+
+/// When the rooms in the times of a scrape are booked in a term.
+#[derive(Debug)]
+pub struct RoomIndex<'a> {
+    term: String,
+    /// The number of teaching weeks: the last week any room is booked in.
+    weeks: u32,
+    /// Every room in the scrape, even if only booked in other terms, with its
+    /// bookings in the term. Keyed by room code, as a room can be listed under
+    /// several spellings of its location, and shown with the first one seen.
+    rooms: BTreeMap<&'a str, (Room<'a>, Vec<WeeklySlot>)>,
+}
+
+/// How often a room is booked in each hour of the week, over a term.
+#[derive(Debug, Serialize)]
+pub struct RoomHeatmap<'a> {
+    pub term: String,
+    /// The number of teaching weeks, which [`DayUtilisation::weeks_booked`]
+    /// is out of.
+    pub weeks: u32,
+    /// The hours of the day in the heatmap, from the start of the earliest
+    /// booking to the end of the latest, e.g. `[8, 9, ..., 21]`.
+    pub hours: Vec<u32>,
+    pub rooms: Vec<RoomUtilisation<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoomUtilisation<'a> {
+    #[serde(flatten)]
+    pub room: Room<'a>,
+    /// The hours the room is booked, over all weeks of the term.
+    pub booked_hours: u32,
+    /// For each day, the number of weeks the room is booked in each of
+    /// [`RoomHeatmap::hours`].
+    pub days: Vec<DayUtilisation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DayUtilisation {
+    pub day: Weekday,
+    pub weeks_booked: Vec<u32>,
+}
+
+impl<'a> RoomIndex<'a> {
+    /// Indexes the bookings of the classes of `courses` in `term`. Times whose
+    /// day, time range or weeks can't be parsed (e.g. `TBA`) don't book their
+    /// room.
+    pub fn new(courses: &'a [Course], term: &str) -> Self {
+        let mut rooms = BTreeMap::new();
+        let mut unparsed = 0;
+        for class in courses.iter().flat_map(|course| &course.classes) {
+            for time in class.times.iter().flatten() {
+                let Some(room) = Room::new(&time.location) else {
+                    continue;
+                };
+                let (_, bookings) = rooms.entry(room.room).or_insert_with(|| (room, Vec::new()));
+                if !class.term.eq_ignore_ascii_case(term) {
+                    continue;
+                }
+                match WeeklySlot::parse(time) {
+                    Ok(slot) => bookings.push(slot),
+                    Err(e) => {
+                        log::debug!("{} doesn't book {}: {:#}", class.class_id, room.room, e);
+                        unparsed += 1;
+                    }
+                }
+            }
+        }
+        if unparsed > 0 {
+            log::warn!(
+                "{} times in {} with a room don't book it, as their day, time or weeks can't be parsed",
+                unparsed,
+                term
+            );
+        }
+        let weeks = rooms
+            .values()
+            .flat_map(|(_, bookings)| bookings)
+            .filter_map(|slot| slot.weeks.last().copied())
+            .max()
+            .unwrap_or(0);
+        Self {
+            term: term.to_string(),
+            weeks,
+            rooms,
+        }
+    }
+
+    fn rooms(&self, building: Option<&str>) -> impl Iterator<Item = &(Room<'a>, Vec<WeeklySlot>)> {
+        self.rooms
+            .values()
+            .filter(move |(room, _)| building.is_none_or(|building| room.is_in(building)))
+    }
+
+    /// The rooms (in `building`, if given) not booked on `day` from `start` to
+    /// `end`, in `week` or, if not given, in any week of the term.
+    pub fn free_rooms(
+        &self,
+        building: Option<&str>,
+        week: Option<u32>,
+        day: Weekday,
+        start: NaiveTime,
+        end: NaiveTime,
+    ) -> Vec<Room<'a>> {
+        let wanted = WeeklySlot {
+            day,
+            start,
+            end,
+            weeks: match week {
+                Some(week) => BTreeSet::from([week]),
+                None => (1..=self.weeks).collect(),
+            },
+        };
+        self.rooms(building)
+            .filter(|(_, bookings)| !bookings.iter().any(|slot| slot.clashes(&wanted)))
+            .map(|(room, _)| *room)
+            .collect()
+    }
+
+    /// How often each room (in `building`, if given) is booked in each hour of
+    /// the week.
+    pub fn heatmap(&self, building: Option<&str>) -> RoomHeatmap<'a> {
+        let bookings = || self.rooms(building).flat_map(|(_, bookings)| bookings);
+        let first_hour = bookings().map(|slot| slot.start.hour()).min();
+        let last_hour = bookings().map(|slot| end_hour(slot.end)).max();
+        let hours = match (first_hour, last_hour) {
+            (Some(first_hour), Some(last_hour)) => (first_hour..last_hour).collect(),
+            _ => Vec::new(),
+        };
+        let rooms = self
+            .rooms(building)
+            .map(|(room, bookings)| {
+                let days = DAYS
+                    .into_iter()
+                    .map(|day| DayUtilisation {
+                        day,
+                        weeks_booked: hours
+                            .iter()
+                            .map(|hour| weeks_booked(bookings, day, *hour))
+                            .collect(),
+                    })
+                    .collect::<Vec<_>>();
+                RoomUtilisation {
+                    room: *room,
+                    booked_hours: days.iter().flat_map(|day| &day.weeks_booked).sum(),
+                    days,
+                }
+            })
+            .collect();
+        RoomHeatmap {
+            term: self.term.clone(),
+            weeks: self.weeks,
+            hours,
+            rooms,
+        }
+    }
+}
+
+/// The end of the last hour `end` is in, e.g. 11 for both 10:30 and 11:00.
+fn end_hour(end: NaiveTime) -> u32 {
+    if end.minute() == 0 {
+        end.hour()
+    } else {
+        end.hour() + 1
+    }
+}
+
+/// The number of weeks in which any of `bookings` is on `day` during `hour`.
+fn weeks_booked(bookings: &[WeeklySlot], day: Weekday, hour: u32) -> u32 {
+    let start = NaiveTime::from_hms_opt(hour, 0, 0).expect("hours are before 24:00");
+    // `None` for 24:00.
+    let end = NaiveTime::from_hms_opt(hour + 1, 0, 0);
+    let weeks = bookings
+        .iter()
+        .filter(|slot| {
+            slot.day == day && end.is_none_or(|end| slot.start < end) && start < slot.end
+        })
+        .flat_map(|slot| &slot.weeks)
+        .collect::<BTreeSet<_>>();
+    weeks.len() as u32
+}
+
+/// Writes `rooms` as a table with aligned columns, for reading in a terminal.
+pub fn write_room_table(writer: &mut impl Write, rooms: &[Room]) -> anyhow::Result<()> {
+    let header = ["BUILDING", "ROOM", "LOCATION"].map(String::from);
+    let rows = std::iter::once(header)
+        .chain(
+            rooms
+                .iter()
+                .map(|room| [room.building, room.room, room.location].map(String::from)),
+        )
+        .collect::<Vec<_>>();
+    write_aligned(writer, &rows, "")?;
+    Ok(())
+}
+
+/// Writes `heatmap` as CSV, one row per room, day and hour, with the share of
+/// the weeks of the term the room is booked in that hour.
+pub fn write_heatmap_csv(writer: impl Write, heatmap: &RoomHeatmap) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "location",
+        "room",
+        "building",
+        "day",
+        "hour",
+        "weeks_booked",
+        "utilisation",
+    ])?;
+    for room in &heatmap.rooms {
+        for day in &room.days {
+            for (hour, weeks_booked) in heatmap.hours.iter().zip(&day.weeks_booked) {
+                let utilisation = match heatmap.weeks {
+                    0 => 0.0,
+                    weeks => f64::from(*weeks_booked) / f64::from(weeks),
+                };
+                writer.write_record([
+                    room.room.location,
+                    room.room.room,
+                    room.room.building,
+                    &day.day.to_string(),
+                    &format!("{:02}:00", hour),
+                    &weeks_booked.to_string(),
+                    &format!("{:.2}", utilisation),
+                ])?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Class, Time, test_fixtures};
+
+    fn time(day: &str, time: &str, location: &str, weeks: &str) -> Time {
+        Time {
+            location: location.to_string(),
+            weeks: weeks.to_string(),
+            ..test_fixtures::time(day, time)
+        }
+    }
+
+    fn class(section: &str, term: &str, times: Vec<Time>) -> Class {
+        Class {
+            activity: "Tutorial".to_string(),
+            ..test_fixtures::class("COMP1511", section, term, times)
+        }
+    }
+
+    const AINSWORTH_G03: &str = "Ainsworth G03 (K-J17-G03)";
+    const AINSWORTH_101: &str = "Ainsworth 101 (K-J17-101)";
+    const QUADRANGLE: &str = "Quadrangle G040 (K-E15-G040)";
+
+    fn courses() -> Vec<Course> {
+        vec![test_fixtures::course(
+            "COMP1511",
+            vec![
+                class(
+                    "T14A",
+                    "T1",
+                    vec![
+                        time("Tue", "14:00 - 15:30", AINSWORTH_G03, "1-4,6-10"),
+                        time("Thu", "09:00 - 10:00", "Online", "1-10"),
+                    ],
+                ),
+                class(
+                    "T15A",
+                    "T1",
+                    vec![time("Tue", "15:00 - 17:00", QUADRANGLE, "1-10")],
+                ),
+                class(
+                    "T14B",
+                    "T1",
+                    vec![time("TBA", "TBA", AINSWORTH_101, "1-10")],
+                ),
+                class(
+                    "T14A",
+                    "T2",
+                    vec![time("Tue", "14:00 - 16:00", AINSWORTH_101, "1-10")],
+                ),
+            ],
+        )]
+    }
+
+    fn rooms(rooms: &[Room]) -> Vec<&'static str> {
+        rooms
+            .iter()
+            .map(|room| match room.location {
+                AINSWORTH_G03 => "G03",
+                AINSWORTH_101 => "101",
+                QUADRANGLE => "Quadrangle",
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_room() {
+        assert_eq!(
+            Room::new(AINSWORTH_G03),
+            Some(Room {
+                location: AINSWORTH_G03,
+                room: "K-J17-G03",
+                building: "K-J17",
+            })
+        );
+        assert_eq!(Room::new("Online"), None);
+        assert_eq!(Room::new("See Class Notes (TBA)"), None);
+    }
+
+    #[test]
+    fn test_free_rooms() {
+        let courses = courses();
+        let index = RoomIndex::new(&courses, "T1");
+        let tuesday = |week| {
+            index.free_rooms(
+                Some("k-j17"),
+                week,
+                Weekday::Tue,
+                NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+            )
+        };
+        // 101 is only booked in T2, or at a TBA time.
+        assert_eq!(rooms(&tuesday(Some(4))), ["101"]);
+        assert_eq!(rooms(&tuesday(Some(5))), ["101", "G03"]);
+        assert_eq!(rooms(&tuesday(None)), ["101"]);
+        let quadrangle = index.free_rooms(
+            Some("quad"),
+            Some(5),
+            Weekday::Tue,
+            NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(15, 0, 0).unwrap(),
+        );
+        assert_eq!(rooms(&quadrangle), ["Quadrangle"]);
+    }
+
+    #[test]
+    fn test_free_rooms_merges_spellings_of_a_room() {
+        let mut courses = courses();
+        courses[0].classes.push(class(
+            "T14C",
+            "T1",
+            vec![time(
+                "Tue",
+                "14:00 - 15:00",
+                "Ainsworth Building G03 (K-J17-G03)",
+                "5",
+            )],
+        ));
+        let index = RoomIndex::new(&courses, "T1");
+        let free = index.free_rooms(
+            Some("k-j17"),
+            Some(5),
+            Weekday::Tue,
+            NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+        );
+        assert_eq!(rooms(&free), ["101"]);
+        assert_eq!(index.heatmap(Some("k-j17")).rooms.len(), 2);
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let courses = courses();
+        let heatmap = RoomIndex::new(&courses, "T1").heatmap(Some(AINSWORTH_G03));
+        assert_eq!(heatmap.weeks, 10);
+        assert_eq!(heatmap.hours, [14, 15]);
+        assert_eq!(rooms(&[heatmap.rooms[0].room]), ["G03"]);
+        assert_eq!(heatmap.rooms[0].booked_hours, 18);
+        assert_eq!(heatmap.rooms[0].days[1].weeks_booked, [9, 9]);
+
+        let mut csv = Vec::new();
+        write_heatmap_csv(&mut csv, &heatmap).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 1 + 7 * 2);
+        assert!(csv.contains("Ainsworth G03 (K-J17-G03),K-J17-G03,K-J17,Tue,14:00,9,0.90\n"));
+    }
+}
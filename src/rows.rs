@@ -0,0 +1,369 @@
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+use crate::{Class, Course, Time, occurrences::occurrences};
+
+/// How the timetable website formats dates, e.g. `17/02/2025`.
+const DATE_FORMAT: &str = "%d/%m/%Y";
+
+/// A row of the courses table, as written to `courses.json`, uploaded to
+/// Hasuragres and exported to the other formats.
+#[derive(Debug, Serialize)]
+pub struct CourseRow<'a> {
+    pub course_id: &'a str,
+    pub course_code: &'a str,
+    pub course_name: &'a str,
+    pub uoc: i32,
+    pub faculty: Option<&'a str>,
+    pub school: Option<&'a str>,
+    pub campus: Option<&'a str>,
+    pub career: Option<&'a str>,
+    pub terms: &'a [String],
+    pub modes: &'a [String],
+}
+
+impl<'a> CourseRow<'a> {
+    pub const COLUMNS: &'static [&'static str] = &[
+        "course_id",
+        "course_code",
+        "course_name",
+        "uoc",
+        "faculty",
+        "school",
+        "campus",
+        "career",
+        "terms",
+        "modes",
+    ];
+
+    pub fn new(course: &'a Course) -> Self {
+        Self {
+            course_id: &course.course_id,
+            course_code: &course.course_code,
+            course_name: &course.course_name,
+            uoc: course.uoc,
+            faculty: course.faculty.as_deref(),
+            school: course.school.as_deref(),
+            campus: course.campus.as_deref(),
+            career: course.career.as_deref(),
+            terms: &course.terms,
+            modes: &course.modes,
+        }
+    }
+}
+
+/// A row of the classes table.
+#[derive(Debug, Serialize)]
+pub struct ClassRow<'a> {
+    pub class_id: &'a str,
+    pub career: &'a str,
+    pub course_id: &'a str,
+    pub section: &'a str,
+    pub term: &'a str,
+    pub activity: &'a str,
+    pub year: &'a str,
+    pub status: &'a str,
+    /// e.g. `400/450`, see [`ClassRow::enrolment`].
+    pub course_enrolment: &'a str,
+    /// e.g. `17/02/2025 - 18/05/2025`, see [`ClassRow::offering_period`].
+    pub offering_period: &'a str,
+    /// Usually the same as `offering_period`, see [`ClassRow::meeting_dates`].
+    pub meeting_dates: &'a str,
+    /// e.g. `17/03/2025`, see [`ClassRow::census_date`].
+    pub census_date: &'a str,
+    pub consent: &'a str,
+    pub mode: &'a str,
+    pub class_notes: Option<&'a str>,
+}
+
+impl<'a> ClassRow<'a> {
+    pub const COLUMNS: &'static [&'static str] = &[
+        "class_id",
+        "career",
+        "course_id",
+        "section",
+        "term",
+        "activity",
+        "year",
+        "status",
+        "course_enrolment",
+        "offering_period",
+        "meeting_dates",
+        "census_date",
+        "consent",
+        "mode",
+        "class_notes",
+    ];
+
+    pub fn new(class: &'a Class) -> Self {
+        Self {
+            class_id: &class.class_id,
+            career: &class.career,
+            course_id: &class.course_id,
+            section: &class.section,
+            term: &class.term,
+            activity: &class.activity,
+            year: &class.year,
+            status: &class.status,
+            course_enrolment: &class.course_enrolment,
+            offering_period: &class.offering_period,
+            meeting_dates: &class.meeting_dates,
+            census_date: &class.census_date,
+            consent: &class.consent,
+            mode: &class.mode,
+            class_notes: class.class_notes.as_deref(),
+        }
+    }
+
+    /// The number of students enrolled and the capacity, or `None` if the
+    /// enrolment isn't formatted like `400/450`.
+    pub fn enrolment(&self) -> Option<(i32, i32)> {
+        let (enrolled, capacity) = self.course_enrolment.split_once('/')?;
+        Some((enrolled.trim().parse().ok()?, capacity.trim().parse().ok()?))
+    }
+
+    pub fn offering_period(&self) -> Option<(NaiveDate, NaiveDate)> {
+        parse_date_range(self.offering_period)
+    }
+
+    pub fn meeting_dates(&self) -> Option<(NaiveDate, NaiveDate)> {
+        parse_date_range(self.meeting_dates)
+    }
+
+    pub fn census_date(&self) -> Option<NaiveDate> {
+        parse_date(self.census_date)
+    }
+}
+
+/// A row of the times table.
+#[derive(Debug, Serialize)]
+pub struct TimeRow<'a> {
+    pub id: String,
+    pub class_id: &'a str,
+    pub career: &'a str,
+    pub day: &'a str,
+    pub instructor: Option<&'a str>,
+    pub location: &'a str,
+    pub time: &'a str,
+    pub weeks: &'a str,
+}
+
+impl<'a> TimeRow<'a> {
+    pub const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "class_id",
+        "career",
+        "day",
+        "instructor",
+        "location",
+        "time",
+        "weeks",
+    ];
+
+    pub fn new(time: &'a Time, class: &'a Class) -> Self {
+        Self {
+            id: time.id(class),
+            class_id: &class.class_id,
+            career: &time.career,
+            day: &time.day,
+            instructor: time.instructor.as_deref(),
+            location: &time.location,
+            time: &time.time,
+            weeks: &time.weeks,
+        }
+    }
+}
+
+/// A row of the optional occurrences table: one dated meeting of a time,
+/// e.g. its week 3 lecture.
+#[derive(Debug, Serialize)]
+pub struct OccurrenceRow<'a> {
+    pub id: String,
+    pub time_id: String,
+    pub class_id: &'a str,
+    pub week: u32,
+    /// The date in Australia/Sydney.
+    pub date: NaiveDate,
+    pub starts_at: DateTime<Tz>,
+    pub ends_at: DateTime<Tz>,
+    pub location: &'a str,
+}
+
+impl<'a> OccurrenceRow<'a> {
+    pub const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "time_id",
+        "class_id",
+        "week",
+        "date",
+        "starts_at",
+        "ends_at",
+        "location",
+    ];
+}
+
+pub fn course_rows(courses: &[Course]) -> impl Iterator<Item = CourseRow<'_>> {
+    courses.iter().map(CourseRow::new)
+}
+
+pub fn class_rows(courses: &[Course]) -> impl Iterator<Item = ClassRow<'_>> {
+    courses
+        .iter()
+        .flat_map(|course| course.classes.iter().map(ClassRow::new))
+}
+
+pub fn time_rows(courses: &[Course]) -> impl Iterator<Item = TimeRow<'_>> {
+    courses.iter().flat_map(|course| {
+        course.classes.iter().flat_map(|class| {
+            class
+                .times
+                .iter()
+                .flatten()
+                .map(move |time| TimeRow::new(time, class))
+        })
+    })
+}
+
+/// Expands every time into its dated meetings (see
+/// [`occurrences`](crate::occurrences())). Times whose day, time range or
+/// weeks can't be parsed, e.g. `TBA`, have no occurrences.
+pub fn occurrence_rows(courses: &[Course]) -> Vec<OccurrenceRow<'_>> {
+    let mut rows = Vec::new();
+    let mut unparseable_times = 0;
+    for class in courses.iter().flat_map(|course| &course.classes) {
+        for time in class.times.iter().flatten() {
+            let time_occurrences = match occurrences(class, time) {
+                Ok(time_occurrences) => time_occurrences,
+                Err(e) => {
+                    log::debug!(
+                        "{} on {} at {} has no occurrences: {:#}",
+                        class.class_id,
+                        time.day,
+                        time.time,
+                        e
+                    );
+                    unparseable_times += 1;
+                    continue;
+                }
+            };
+            let time_id = time.id(class);
+            rows.extend(time_occurrences.into_iter().map(|occurrence| {
+                let date = occurrence.start.date_naive();
+                OccurrenceRow {
+                    id: format!("{}{}", time_id, date),
+                    time_id: time_id.clone(),
+                    class_id: &class.class_id,
+                    week: occurrence.week,
+                    date,
+                    starts_at: occurrence.start,
+                    ends_at: occurrence.end,
+                    location: &time.location,
+                }
+            }));
+        }
+    }
+    if unparseable_times > 0 {
+        log::info!(
+            "{} times couldn't be expanded into occurrences, e.g. because they are TBA",
+            unparseable_times
+        );
+    }
+    rows
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), DATE_FORMAT).ok()
+}
+
+/// Parses e.g. `17/02/2025 - 18/05/2025`.
+fn parse_date_range(date_range: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let (start, end) = date_range.split_once('-')?;
+    Some((parse_date(start)?, parse_date(end)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{self, class, time};
+
+    fn course() -> Course {
+        let time = Time {
+            weeks: "1-5,7-10".to_string(),
+            ..time("Mon", "09:00 - 11:00")
+        };
+        let class = Class {
+            course_enrolment: "400/450".to_string(),
+            meeting_dates: "See class notes".to_string(),
+            ..class("COMP1511", "A", "T1", vec![time])
+        };
+        test_fixtures::course("COMP1511", vec![class])
+    }
+
+    fn keys(row: impl Serialize) -> Vec<String> {
+        let serde_json::Value::Object(row) = serde_json::to_value(row).unwrap() else {
+            panic!("rows should serialise to objects");
+        };
+        let mut keys = row.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    fn sorted(columns: &[&str]) -> Vec<String> {
+        let mut columns = columns.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        columns.sort();
+        columns
+    }
+
+    #[test]
+    fn test_rows_have_exactly_their_columns() {
+        let courses = [course()];
+        let course = course_rows(&courses).next().unwrap();
+        assert_eq!(keys(&course), sorted(CourseRow::COLUMNS));
+        let class = class_rows(&courses).next().unwrap();
+        assert_eq!(keys(&class), sorted(ClassRow::COLUMNS));
+        let time = time_rows(&courses).next().unwrap();
+        assert_eq!(keys(&time), sorted(TimeRow::COLUMNS));
+        assert_eq!(
+            time.id,
+            "COMP1511Undergraduate-A-T1-2025MonQuadrangle G040 (K-E15-G040)09:00 - 11:001-5,7-10"
+        );
+        let occurrence = &occurrence_rows(&courses)[0];
+        assert_eq!(keys(occurrence), sorted(OccurrenceRow::COLUMNS));
+    }
+
+    #[test]
+    fn test_occurrence_rows() {
+        let mut courses = [course()];
+        courses[0].classes[0].meeting_dates = "17/02/2025 - 18/05/2025".to_string();
+        let occurrences = occurrence_rows(&courses);
+        assert_eq!(occurrences.len(), 9);
+        assert_eq!(
+            serde_json::to_value(&occurrences[5]).unwrap(),
+            serde_json::json!({
+                "id": "COMP1511Undergraduate-A-T1-2025MonQuadrangle G040 (K-E15-G040)09:00 - 11:001-5,7-102025-03-31",
+                "time_id": "COMP1511Undergraduate-A-T1-2025MonQuadrangle G040 (K-E15-G040)09:00 - 11:001-5,7-10",
+                "class_id": "COMP1511Undergraduate-A-T1-2025",
+                "week": 7,
+                "date": "2025-03-31",
+                "starts_at": "2025-03-31T09:00:00+11:00",
+                "ends_at": "2025-03-31T11:00:00+11:00",
+                "location": "Quadrangle G040 (K-E15-G040)",
+            })
+        );
+
+        courses[0].classes[0].times.as_mut().unwrap()[0].time = "TBA".to_string();
+        assert!(occurrence_rows(&courses).is_empty());
+    }
+
+    #[test]
+    fn test_typed_class_fields() {
+        let courses = [course()];
+        let class = class_rows(&courses).next().unwrap();
+        let date = |d, m| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+        assert_eq!(class.enrolment(), Some((400, 450)));
+        assert_eq!(class.offering_period(), Some((date(17, 2), date(18, 5))));
+        assert_eq!(class.meeting_dates(), None);
+        assert_eq!(class.census_date(), Some(date(17, 3)));
+    }
+}
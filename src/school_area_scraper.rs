@@ -1,12 +1,17 @@
 use crate::{
     Course, ScrapingContext,
+    parse_error::{ParseError, ParseStage},
+    scrape_year::{ScrapeEvent, scrape_year},
+    selector_profile::ProfileSelector,
     subject_area_scraper::SubjectArea,
     text_manipulators::{extract_text, get_html_link_to_page},
+    year_to_scrape::YearToScrape,
 };
 use derive_new::new;
-use scraper::Selector;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Debug)]
 pub struct SchoolArea {
@@ -15,106 +20,41 @@ pub struct SchoolArea {
 }
 
 impl SchoolArea {
+    /// Scrapes every subject area listed on the school area page at `url`,
+    /// grouping the courses of `scrape_year` by their subject area.
+    #[deprecated(note = "use `scrape_year` or `scrape_all_courses` instead")]
     pub async fn scrape(url: String, ctx: &Arc<ScrapingContext>) -> anyhow::Result<Self> {
-        log::info!("Started scraping School Area for: {}", url);
-
-        let html = ctx.request_client.fetch_url_body(&url, ctx).await?;
-
-        // We use a channel so we can start completing a partial page
-        // immediately once it's scraped, so we don't have to wait until all
-        // partial pages have been scraped.
-        let (tx, mut rx) = mpsc::unbounded_channel();
-
-        let producer = async || -> anyhow::Result<()> {
-            let ctx = Arc::clone(ctx);
-            let url = url.clone();
-
-            let cpu_bound = move || -> anyhow::Result<()> {
-                let document = scraper::Html::parse_document(&html);
-
-                // NOTE: We can't return the error message from `Selector::parse`
-                // because it is not Send and, therefore, not sendable across threads.
-                let error_msg = format!("failed to parse {}", url);
-
-                let row_selector = Selector::parse("tr.rowLowlight, tr.rowHighlight")
-                    .map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-                let code_selector =
-                    Selector::parse("td.data").map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-                let name_selector =
-                    Selector::parse("td.data a").map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-                let link_selector =
-                    Selector::parse("td.data a").map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-                let school_selector = Selector::parse("td.data:nth-child(3)")
-                    .map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-
-                for row_node in document.select(&row_selector) {
-                    // Extract data from each row
-                    let course_code = extract_text(
-                        row_node
-                            .select(&code_selector)
-                            .next()
-                            .ok_or_else(|| anyhow::anyhow!(error_msg.clone()))?,
-                    );
-                    let course_name = extract_text(
-                        row_node
-                            .select(&name_selector)
-                            .next()
-                            .ok_or_else(|| anyhow::anyhow!(error_msg.clone()))?,
-                    );
-
-                    let year_to_scrape = ctx.timetable_url_regex.extract_year(&url)?;
-                    let url_to_scrape_further = get_html_link_to_page(
-                        year_to_scrape,
-                        row_node
-                            .select(&link_selector)
-                            .next()
-                            .map_or("", |node| node.value().attr("href").unwrap_or("")),
-                        &ctx,
-                    );
-                    let school = extract_text(
-                        row_node
-                            .select(&school_selector)
-                            .next()
-                            .ok_or_else(|| anyhow::anyhow!(error_msg.clone()))?,
-                    );
-                    let partial_page = PartialSchoolAreaPage::new(
-                        course_code,
-                        course_name,
-                        school,
-                        url_to_scrape_further,
-                    );
-                    tx.send(partial_page)?;
+        let year = ctx.timetable_url_regex.extract_year(&url)?;
+        let mut pages = Vec::<SchoolAreaPage>::new();
+        let mut events = std::pin::pin!(scrape_year(
+            YearToScrape::Year(year),
+            Arc::clone(ctx),
+            CancellationToken::new(),
+        ));
+        while let Some(event) = events.next().await {
+            match event? {
+                ScrapeEvent::SubjectArea(partial_page) => pages.push(SchoolAreaPage::new(
+                    partial_page.subject_code,
+                    partial_page.subject_name,
+                    partial_page.school,
+                    SubjectArea {
+                        courses: Vec::new(),
+                    },
+                )),
+                ScrapeEvent::Course(course) => {
+                    // Subject areas are always sent before their courses.
+                    if let Some(page) = pages
+                        .iter_mut()
+                        .find(|page| course.course_code.starts_with(&page.subject_code))
+                    {
+                        page.subject_area.courses.push(*course);
+                    }
                 }
-                Ok(())
-            };
-            tokio::task::spawn_blocking(cpu_bound).await??;
-            Ok(())
-        };
-
-        let mut consumer = async move || -> anyhow::Result<Vec<SchoolAreaPage>> {
-            let mut tasks = tokio::task::JoinSet::new();
-
-            // Spawn partial-X-completion tasks as soon as we receive them.
-            while let Some(partial_page) = rx.recv().await {
-                let ctx = Arc::clone(ctx);
-                tasks.spawn(async move { partial_page.complete(&ctx).await });
+                ScrapeEvent::YearResolved(_)
+                | ScrapeEvent::Progress(_)
+                | ScrapeEvent::Skipped(_) => {}
             }
-
-            // Wait for all partial-X-completion tasks to complete.
-            // If any of the tasks returns an error, return that error
-            // immediately (without waiting for all other tasks to finish).
-            let mut pages = Vec::new();
-            while let Some(result) = tasks.join_next().await {
-                let course = result??;
-                pages.push(course);
-            }
-
-            Ok(pages)
-        };
-
-        // Wait on producer and consumer.
-        let ((), pages) = tokio::try_join!(producer(), consumer())?;
-
+        }
         Ok(Self { url, pages })
     }
 
@@ -133,22 +73,79 @@ pub struct SchoolAreaPage {
     pub subject_area: SubjectArea,
 }
 
-#[derive(Debug, new)]
-struct PartialSchoolAreaPage {
-    subject_code: String,
-    subject_name: String,
-    school: String,
-    subject_area_url: String,
-}
+/// Parses the subject areas listed on a school area page, calling `emit` for
+/// each one that matches the scrape filter.
+pub fn parse_partial_pages(
+    html: &str,
+    url: &str,
+    ctx: &ScrapingContext,
+    mut emit: impl FnMut(PartialSchoolAreaPage) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let document = scraper::Html::parse_document(html);
+
+    let selectors = &ctx.selector_profile.school_area;
+
+    if document.select(&selectors.row).next().is_none() {
+        return Err(ParseError::missing_selector(
+            ParseStage::SchoolArea,
+            url,
+            selectors.row.as_str(),
+            document.root_element(),
+        )
+        .into());
+    }
 
-impl PartialSchoolAreaPage {
-    async fn complete(self, ctx: &Arc<ScrapingContext>) -> anyhow::Result<SchoolAreaPage> {
-        let subject_area = SubjectArea::scrape(self.subject_area_url, ctx).await?;
-        Ok(SchoolAreaPage::new(
-            self.subject_code,
-            self.subject_name,
-            self.school,
-            subject_area,
-        ))
+    for row_node in document.select(&selectors.row) {
+        let missing = |selector: &ProfileSelector| {
+            ParseError::missing_selector(ParseStage::SchoolArea, url, selector.as_str(), row_node)
+        };
+
+        // Extract data from each row
+        let course_code = extract_text(
+            row_node
+                .select(&selectors.code)
+                .next()
+                .ok_or_else(|| missing(&selectors.code))?,
+        );
+        let course_name = extract_text(
+            row_node
+                .select(&selectors.name)
+                .next()
+                .ok_or_else(|| missing(&selectors.name))?,
+        );
+
+        let year_to_scrape = ctx.timetable_url_regex.extract_year(url)?;
+        let url_to_scrape_further = get_html_link_to_page(
+            year_to_scrape,
+            row_node
+                .select(&selectors.link)
+                .next()
+                .map_or("", |node| node.value().attr("href").unwrap_or("")),
+            ctx,
+        );
+        let school = extract_text(
+            row_node
+                .select(&selectors.school)
+                .next()
+                .ok_or_else(|| missing(&selectors.school))?,
+        );
+        if !ctx
+            .scrape_filter
+            .matches_subject_area(&course_code, &school)
+        {
+            continue;
+        }
+        let partial_page =
+            PartialSchoolAreaPage::new(course_code, course_name, school, url_to_scrape_further);
+        emit(partial_page)?;
     }
+    Ok(())
+}
+
+#[derive(Debug, Clone, new, Serialize, Deserialize)]
+pub struct PartialSchoolAreaPage {
+    pub subject_code: String,
+    pub subject_name: String,
+    pub school: String,
+    pub subject_area_url: String,
 }
@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+
+use crate::{Course, text_manipulators};
+
+/// Restricts a scrape to a subset of the timetable.
+///
+/// Each filter is applied at the earliest stage where the information it needs
+/// is available, so that pages we don't care about are never requested:
+/// - subject, course and school filters prune subject areas on the school area page,
+/// - course and career filters prune courses on the subject area page,
+/// - faculty is only listed on course pages, so it is applied once a course has
+///   been scraped.
+///
+/// An empty filter matches everything.
+#[derive(Debug, Default, Clone)]
+pub struct ScrapeFilter {
+    /// Upper-case subject area codes, e.g. `COMP`.
+    subjects: HashSet<String>,
+    /// Upper-case course codes, e.g. `COMP1511`.
+    courses: HashSet<String>,
+    /// Lower-case careers, e.g. `undergraduate`.
+    careers: HashSet<String>,
+    /// Lower-case faculty name fragments.
+    faculties: Vec<String>,
+    /// Lower-case school name fragments.
+    schools: Vec<String>,
+}
+
+/// Splits comma separated CLI values, e.g. `["COMP,MATH", "PHYS"]` into
+/// `["COMP", "MATH", "PHYS"]`.
+fn split_values(values: &[String]) -> impl Iterator<Item = &str> {
+    values
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+impl ScrapeFilter {
+    pub fn new(
+        subjects: &[String],
+        courses: &[String],
+        careers: &[String],
+        faculties: &[String],
+        schools: &[String],
+    ) -> Self {
+        Self {
+            subjects: split_values(subjects).map(str::to_uppercase).collect(),
+            courses: split_values(courses).map(str::to_uppercase).collect(),
+            careers: split_values(careers).map(str::to_lowercase).collect(),
+            faculties: split_values(faculties).map(str::to_lowercase).collect(),
+            schools: split_values(schools).map(str::to_lowercase).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subjects.is_empty()
+            && self.courses.is_empty()
+            && self.careers.is_empty()
+            && self.faculties.is_empty()
+            && self.schools.is_empty()
+    }
+
+    /// Whether a subject area listed on the school area page should be scraped.
+    pub fn matches_subject_area(&self, subject_code: &str, school: &str) -> bool {
+        let subject_code = subject_code.trim().to_uppercase();
+        let subject_matches = self.subjects.is_empty() || self.subjects.contains(&subject_code);
+        // A course filter implies its subject area, e.g. COMP1511 implies COMP.
+        let course_matches = self.courses.is_empty()
+            || self.courses.iter().any(|course| {
+                text_manipulators::subject_code(course) == Some(subject_code.as_str())
+            });
+        subject_matches && course_matches && matches_fragment(&self.schools, school)
+    }
+
+    /// Whether a course listed on the subject area page should be scraped.
+    pub fn matches_partial_course(&self, course_code: &str, career: &str) -> bool {
+        let course_matches =
+            self.courses.is_empty() || self.courses.contains(&course_code.trim().to_uppercase());
+        let career_matches =
+            self.careers.is_empty() || self.careers.contains(&career.trim().to_lowercase());
+        course_matches && career_matches
+    }
+
+    /// Whether a fully scraped course should be kept.
+    pub fn matches_course(&self, course: &Course) -> bool {
+        self.faculties.is_empty()
+            || course
+                .faculty
+                .as_deref()
+                .is_some_and(|faculty| matches_fragment(&self.faculties, faculty))
+    }
+}
+
+/// Case-insensitive substring match, since UNSW abbreviates long faculty and
+/// school names inconsistently (e.g. "School of Acctng, Audit & Tax").
+fn matches_fragment(fragments: &[String], value: &str) -> bool {
+    let value = value.to_lowercase();
+    fragments.is_empty() || fragments.iter().any(|fragment| value.contains(fragment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_scrape_filter() {
+        let filter = ScrapeFilter::new(
+            &strings(&["comp,MATH"]),
+            &strings(&["COMP1511"]),
+            &strings(&["Undergraduate"]),
+            &[],
+            &strings(&["computer"]),
+        );
+        assert!(filter.matches_subject_area("COMP", "School of Computer Sci & Eng"));
+        // Excluded by the course filter, even though it is a selected subject.
+        assert!(!filter.matches_subject_area("MATH", "School of Computer Sci & Eng"));
+        assert!(!filter.matches_subject_area("COMP", "School of Mathematics & Statistics"));
+        assert!(filter.matches_partial_course("COMP1511", "undergraduate"));
+        assert!(!filter.matches_partial_course("COMP1511", "Postgraduate"));
+        assert!(!filter.matches_partial_course("COMP1521", "Undergraduate"));
+        assert!(ScrapeFilter::default().is_empty());
+        assert!(ScrapeFilter::default().matches_subject_area("ACCT", ""));
+    }
+}
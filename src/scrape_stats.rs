@@ -0,0 +1,62 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use serde::Serialize;
+
+/// Counters for the requests made through a `ScrapingContext`, updated as
+/// pages are fetched so progress can be reported while scraping.
+#[derive(Debug, Default)]
+pub struct ScrapeStats {
+    requests: AtomicU64,
+    retries: AtomicU64,
+    rate_changes: AtomicU64,
+    bytes_downloaded: AtomicU64,
+    /// 0 until a request has been rate-limited.
+    request_rate: AtomicU32,
+}
+
+/// A snapshot of `ScrapeStats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RequestStats {
+    /// Pages fetched, whatever their status.
+    pub requests: u64,
+    /// Requests that failed and were tried again.
+    pub retries: u64,
+    /// How often the request rate was lowered after a failed request.
+    pub rate_changes: u64,
+    pub bytes_downloaded: u64,
+    /// The request rate of the last request, in requests per second. `None`
+    /// if no request went through the rate limiter, e.g. for snapshots.
+    pub request_rate: Option<u32>,
+}
+
+impl ScrapeStats {
+    pub fn record_response(&self, body_len: usize) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes_downloaded
+            .fetch_add(body_len as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_request_rate(&self, req_per_sec: u32) {
+        self.request_rate.store(req_per_sec, Ordering::Relaxed);
+    }
+
+    pub fn record_rate_change(&self, new_req_per_sec: u32) {
+        self.rate_changes.fetch_add(1, Ordering::Relaxed);
+        self.record_request_rate(new_req_per_sec);
+    }
+
+    pub fn snapshot(&self) -> RequestStats {
+        let request_rate = self.request_rate.load(Ordering::Relaxed);
+        RequestStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            rate_changes: self.rate_changes.load(Ordering::Relaxed),
+            bytes_downloaded: self.bytes_downloaded.load(Ordering::Relaxed),
+            request_rate: (request_rate > 0).then_some(request_rate),
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use serde::Serialize;
+
+use crate::{Course, RequestStats, ScrapeProgress, Year, utils::StageTiming};
+
+/// What courses without a faculty are grouped under.
+const UNKNOWN: &str = "(unknown)";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CourseTotals {
+    pub courses: usize,
+    pub classes: usize,
+    pub times: usize,
+}
+
+impl CourseTotals {
+    fn add(&mut self, course: &Course) {
+        self.courses += 1;
+        self.classes += course.classes.len();
+        self.times += course
+            .classes
+            .iter()
+            .filter_map(|class| class.times.as_ref())
+            .map(Vec::len)
+            .sum::<usize>();
+    }
+}
+
+/// A report of a scrape run, written at the end of `scrape`.
+#[derive(Debug, Serialize)]
+pub struct ScrapeSummary {
+    /// `None` if the scrape stopped before the year was resolved.
+    pub year: Option<Year>,
+    /// Whether the scrape ran to completion rather than being interrupted.
+    pub complete: bool,
+    pub elapsed_seconds: f64,
+    pub progress: ScrapeProgress,
+    pub totals: CourseTotals,
+    pub per_faculty: BTreeMap<String, CourseTotals>,
+    pub per_career: BTreeMap<String, CourseTotals>,
+    pub requests: RequestStats,
+    pub stages: Vec<StageTiming>,
+}
+
+impl ScrapeSummary {
+    pub fn new(
+        year: Option<Year>,
+        complete: bool,
+        elapsed: Duration,
+        progress: ScrapeProgress,
+        courses: &[Course],
+        requests: RequestStats,
+        stages: Vec<StageTiming>,
+    ) -> Self {
+        let mut totals = CourseTotals::default();
+        let mut per_faculty = BTreeMap::<String, CourseTotals>::new();
+        let mut per_career = BTreeMap::<String, CourseTotals>::new();
+        for course in courses {
+            totals.add(course);
+            let faculty = course.faculty.as_deref().unwrap_or(UNKNOWN);
+            per_faculty
+                .entry(faculty.to_string())
+                .or_default()
+                .add(course);
+            let career = course.career.as_deref().unwrap_or(UNKNOWN);
+            per_career
+                .entry(career.to_string())
+                .or_default()
+                .add(course);
+        }
+        Self {
+            year,
+            complete,
+            elapsed_seconds: elapsed.as_secs_f64(),
+            progress,
+            totals,
+            per_faculty,
+            per_career,
+            requests,
+            stages,
+        }
+    }
+}
+
+fn format_seconds(seconds: f64) -> humantime::FormattedDuration {
+    // Millisecond precision is plenty, and much easier to read.
+    humantime::format_duration(Duration::from_millis((seconds * 1000.0) as u64))
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes < 1_000_000 {
+        format!("{:.1} kB", bytes as f64 / 1_000.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+    }
+}
+
+fn format_totals(f: &mut fmt::Formatter<'_>, name: &str, totals: &CourseTotals) -> fmt::Result {
+    writeln!(
+        f,
+        "  {}: {} courses, {} classes, {} times",
+        name, totals.courses, totals.classes, totals.times
+    )
+}
+
+impl fmt::Display for ScrapeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.year {
+            Some(year) => write!(f, "Scrape of {}", year)?,
+            None => write!(f, "Scrape")?,
+        }
+        writeln!(
+            f,
+            " {} in {}",
+            if self.complete {
+                "completed"
+            } else {
+                "was interrupted"
+            },
+            format_seconds(self.elapsed_seconds)
+        )?;
+        writeln!(
+            f,
+            "Subject areas: {} of {} scraped",
+            self.progress.subject_areas_scraped, self.progress.subject_areas_found
+        )?;
+        writeln!(
+            f,
+            "Courses: {} of {} scraped",
+            self.progress.courses_scraped, self.progress.courses_found
+        )?;
+        format_totals(f, "Total", &self.totals)?;
+        writeln!(f, "Per faculty:")?;
+        for (faculty, totals) in &self.per_faculty {
+            format_totals(f, faculty, totals)?;
+        }
+        writeln!(f, "Per career:")?;
+        for (career, totals) in &self.per_career {
+            format_totals(f, career, totals)?;
+        }
+        writeln!(
+            f,
+            "Requests: {} ({} retried, {} rate changes, {} downloaded)",
+            self.requests.requests,
+            self.requests.retries,
+            self.requests.rate_changes,
+            format_bytes(self.requests.bytes_downloaded)
+        )?;
+        if let Some(request_rate) = self.requests.request_rate {
+            writeln!(f, "Final request rate: {} req/s", request_rate)?;
+        }
+        write!(f, "Stages:")?;
+        for stage in &self.stages {
+            write!(f, "\n  {}: {}", stage.stage, format_seconds(stage.seconds))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures;
+
+    fn course(course_code: &str, faculty: Option<&str>, career: &str) -> Course {
+        Course {
+            course_id: format!("{course_code}{career}"),
+            faculty: faculty.map(String::from),
+            career: Some(career.to_string()),
+            ..test_fixtures::course(course_code, Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_summary_totals_per_faculty_and_career() {
+        let courses = [
+            course("COMP1511", Some("Faculty of Engineering"), "Undergraduate"),
+            course("COMP9021", Some("Faculty of Engineering"), "Postgraduate"),
+            course("MATH1131", Some("Faculty of Science"), "Undergraduate"),
+            course("GENX1000", None, "Undergraduate"),
+        ];
+        let summary = ScrapeSummary::new(
+            Some(2025),
+            true,
+            Duration::from_millis(61_500),
+            ScrapeProgress::default(),
+            &courses,
+            RequestStats::default(),
+            vec![StageTiming {
+                stage: "scraping".to_string(),
+                seconds: 61.5,
+            }],
+        );
+
+        assert_eq!(summary.totals.courses, 4);
+        let faculties = summary
+            .per_faculty
+            .iter()
+            .map(|(faculty, totals)| (faculty.as_str(), totals.courses))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            faculties,
+            [
+                ("(unknown)", 1),
+                ("Faculty of Engineering", 2),
+                ("Faculty of Science", 1)
+            ]
+        );
+        assert_eq!(summary.per_career["Undergraduate"].courses, 3);
+        assert_eq!(summary.per_career["Postgraduate"].courses, 1);
+
+        let text = summary.to_string();
+        assert!(text.starts_with("Scrape of 2025 completed in 1m 1s 500ms\n"));
+        assert!(text.contains("  Faculty of Engineering: 2 courses, 0 classes, 0 times\n"));
+        assert!(text.ends_with("Stages:\n  scraping: 1m 1s 500ms"));
+    }
+}
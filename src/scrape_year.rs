@@ -0,0 +1,367 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use tokio::{sync::mpsc, task::JoinSet};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, Span, field};
+
+use crate::{
+    Course, ScrapingContext, Year,
+    course_scraper::PartialCourse,
+    metrics,
+    parse_error::ParseError,
+    school_area_scraper::{PartialSchoolAreaPage, parse_partial_pages},
+    subject_area_scraper::parse_partial_courses,
+    utils::sort_by_key_ref,
+    year_to_scrape::YearToScrape,
+};
+
+/// How many events can be buffered before the scrape waits for the consumer.
+const EVENT_BUFFER: usize = 256;
+
+/// Counts of the work found and done so far. The totals grow as pages are
+/// scraped, so they are only final once every subject area has been scraped.
+///
+/// There is no count of failed courses, since it would always be zero: a
+/// request that fails is retried at a lower request rate, and a course page
+/// that still can't be fetched or parsed ends the scrape with its error.
+/// Retried requests are counted in `RequestStats::retries` instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ScrapeProgress {
+    pub subject_areas_found: usize,
+    pub subject_areas_scraped: usize,
+    pub courses_found: usize,
+    pub courses_scraped: usize,
+}
+
+impl ScrapeProgress {
+    /// Courses found whose pages haven't been scraped yet.
+    pub fn courses_queued(&self) -> usize {
+        self.courses_found - self.courses_scraped
+    }
+
+    /// Estimates the time left from how fast courses have been scraped in the
+    /// `elapsed` time so far. `None` until every subject area has been scraped
+    /// (before then the total number of courses isn't known) or a course has
+    /// been scraped.
+    pub fn eta(&self, elapsed: Duration) -> Option<Duration> {
+        if self.subject_areas_scraped < self.subject_areas_found || self.courses_scraped == 0 {
+            return None;
+        }
+        let per_course = elapsed.as_secs_f64() / self.courses_scraped as f64;
+        Some(Duration::from_secs_f64(
+            per_course * self.courses_queued() as f64,
+        ))
+    }
+}
+
+#[derive(Debug)]
+pub enum ScrapeEvent {
+    /// The year being scraped, always the first event.
+    YearResolved(Year),
+    Progress(ScrapeProgress),
+    /// A subject area listed on the school area page and matching the scrape
+    /// filter, sent before any of its courses.
+    SubjectArea(PartialSchoolAreaPage),
+    /// A scraped course matching the scrape filter.
+    Course(Box<Course>),
+    /// A page that wasn't scraped because the scrape was cancelled before it
+    /// was fetched.
+    Skipped(SkippedPage),
+}
+
+/// A page a cancelled scrape didn't get to, along with everything under it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "page", rename_all = "snake_case")]
+pub enum SkippedPage {
+    SchoolArea {
+        url: String,
+    },
+    SubjectArea {
+        subject_code: String,
+        url: String,
+    },
+    Course {
+        course_code: String,
+        career: String,
+        url: String,
+    },
+}
+
+/// The error a scrape stream ends with when its `CancellationToken` is cancelled.
+#[derive(Debug)]
+pub struct ScrapeCancelled;
+
+impl fmt::Display for ScrapeCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The scrape was cancelled!")
+    }
+}
+
+impl std::error::Error for ScrapeCancelled {}
+
+/// Scrapes all courses of a year, yielding each course as soon as it has been
+/// scraped, along with progress events.
+///
+/// The stream ends after the last course, or with an error if any page fails.
+///
+/// Cancelling `cancel_token` stops all outstanding fetches, but pages that were
+/// already fetched are still parsed and their courses yielded. Every page that
+/// wasn't fetched is yielded as `Skipped`, and the stream then ends with a
+/// `ScrapeCancelled` error. Dropping the stream stops the scrape immediately.
+pub fn scrape_year(
+    year_to_scrape: YearToScrape,
+    ctx: Arc<ScrapingContext>,
+    cancel_token: CancellationToken,
+) -> impl Stream<Item = anyhow::Result<ScrapeEvent>> + Send + 'static {
+    let (tx, rx) = mpsc::channel(EVENT_BUFFER);
+
+    let span = tracing::info_span!("year", ?year_to_scrape, year = field::Empty);
+    tokio::spawn(
+        async move {
+            // If the stream is dropped, sending fails and this returns early, which
+            // drops its `JoinSet`s and so aborts all in-flight page tasks.
+            let result = scrape_year_into(year_to_scrape, ctx, &cancel_token, &tx).await;
+            if let Err(e) = result {
+                // Counted here rather than where the error is built, since
+                // `canary` builds parse errors for checks that fail.
+                if let Some(parse_error) = e.downcast_ref::<ParseError>() {
+                    metrics::record_parse_error(parse_error.stage);
+                }
+                // Nobody to report to if the stream was dropped.
+                let _ = tx.send(Err(e)).await;
+            }
+        }
+        .instrument(span),
+    );
+
+    futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (event, rx))
+    })
+}
+
+/// Scrapes all courses of a year, sorted by course id.
+pub async fn scrape_all_courses(
+    year_to_scrape: YearToScrape,
+    ctx: Arc<ScrapingContext>,
+    cancel_token: CancellationToken,
+) -> anyhow::Result<(Year, Vec<Course>)> {
+    let mut year = None;
+    let mut courses = Vec::new();
+    let mut events = std::pin::pin!(scrape_year(year_to_scrape, ctx, cancel_token));
+    while let Some(event) = events.next().await {
+        match event? {
+            ScrapeEvent::YearResolved(resolved_year) => year = Some(resolved_year),
+            ScrapeEvent::Progress(_) | ScrapeEvent::SubjectArea(_) | ScrapeEvent::Skipped(_) => {}
+            ScrapeEvent::Course(course) => courses.push(*course),
+        }
+    }
+    let year = year.ok_or_else(|| anyhow::anyhow!("scrape ended without resolving a year"))?;
+    sort_by_key_ref(&mut courses, |course| &course.course_id);
+    Ok((year, courses))
+}
+
+type EventSender = mpsc::Sender<anyhow::Result<ScrapeEvent>>;
+
+async fn send(tx: &EventSender, event: ScrapeEvent) -> anyhow::Result<()> {
+    tx.send(Ok(event))
+        .await
+        .map_err(|_| anyhow::Error::new(ScrapeCancelled))
+}
+
+fn is_cancelled(error: &anyhow::Error) -> bool {
+    error.is::<ScrapeCancelled>()
+}
+
+/// Fetches `url`, unless the scrape is cancelled first.
+pub(crate) async fn fetch_url_body(
+    url: &str,
+    ctx: &ScrapingContext,
+    cancel_token: &CancellationToken,
+) -> anyhow::Result<String> {
+    tokio::select! {
+        body = ctx.fetch_url_body(url) => body,
+        _ = cancel_token.cancelled() => Err(ScrapeCancelled.into()),
+    }
+}
+
+async fn scrape_year_into(
+    year_to_scrape: YearToScrape,
+    ctx: Arc<ScrapingContext>,
+    cancel_token: &CancellationToken,
+    tx: &EventSender,
+) -> anyhow::Result<()> {
+    let year = tokio::select! {
+        year = year_to_scrape.resolve_year(&ctx) => year?,
+        _ = cancel_token.cancelled() => return Err(ScrapeCancelled.into()),
+    };
+    Span::current().record("year", year);
+    log::info!("Starting scrape for year: {year}");
+    send(tx, ScrapeEvent::YearResolved(year)).await?;
+
+    let url = ctx.scraping_config.get_timetable_api_url_for_year(year);
+    let school_area_span = tracing::info_span!("school_area", url);
+    let partial_pages = match scrape_partial_pages(url.clone(), &ctx, cancel_token)
+        .instrument(school_area_span.clone())
+        .await
+    {
+        Err(e) if is_cancelled(&e) => {
+            send(tx, ScrapeEvent::Skipped(SkippedPage::SchoolArea { url })).await?;
+            return Err(e);
+        }
+        partial_pages => partial_pages?,
+    };
+    for partial_page in &partial_pages {
+        send(tx, ScrapeEvent::SubjectArea(partial_page.clone())).await?;
+    }
+    let mut progress = ScrapeProgress {
+        subject_areas_found: partial_pages.len(),
+        ..ScrapeProgress::default()
+    };
+    send(tx, ScrapeEvent::Progress(progress)).await?;
+
+    // Complete courses across all subject areas in one flat set of tasks, so
+    // each can be yielded as soon as it's done.
+    let mut subject_area_tasks = JoinSet::new();
+    for partial_page in partial_pages {
+        let ctx = Arc::clone(&ctx);
+        let cancel_token = cancel_token.clone();
+        let span = tracing::info_span!(
+            parent: &school_area_span,
+            "subject_area",
+            subject_code = partial_page.subject_code,
+            url = partial_page.subject_area_url,
+        );
+        subject_area_tasks.spawn(
+            async move {
+                let result = scrape_partial_courses(
+                    partial_page.subject_area_url.clone(),
+                    &ctx,
+                    &cancel_token,
+                )
+                .await;
+                (partial_page, Span::current(), result)
+            }
+            .instrument(span),
+        );
+    }
+    let mut course_tasks = JoinSet::new();
+
+    while !subject_area_tasks.is_empty() || !course_tasks.is_empty() {
+        tokio::select! {
+            Some(joined) = subject_area_tasks.join_next() => {
+                let (partial_page, subject_area_span, result) = joined?;
+                let partial_courses = match result {
+                    Err(e) if is_cancelled(&e) => {
+                        let skipped = SkippedPage::SubjectArea {
+                            subject_code: partial_page.subject_code,
+                            url: partial_page.subject_area_url,
+                        };
+                        send(tx, ScrapeEvent::Skipped(skipped)).await?;
+                        continue;
+                    }
+                    result => result?,
+                };
+                progress.subject_areas_scraped += 1;
+                progress.courses_found += partial_courses.len();
+                for partial_course in partial_courses {
+                    let ctx = Arc::clone(&ctx);
+                    let cancel_token = cancel_token.clone();
+                    // The course span is opened by `PartialCourse::complete_impl`.
+                    course_tasks.spawn(
+                        async move { complete_course(partial_course, &ctx, &cancel_token).await }
+                            .instrument(subject_area_span.clone()),
+                    );
+                }
+            }
+            Some(joined) = course_tasks.join_next() => {
+                let course = match joined? {
+                    Err(CourseError::Cancelled(skipped)) => {
+                        send(tx, ScrapeEvent::Skipped(skipped)).await?;
+                        continue;
+                    }
+                    Err(CourseError::Failed(e)) => return Err(e),
+                    Ok(course) => course,
+                };
+                progress.courses_scraped += 1;
+                if ctx.scrape_filter.matches_course(&course) {
+                    send(tx, ScrapeEvent::Course(Box::new(course))).await?;
+                }
+            }
+        }
+        send(tx, ScrapeEvent::Progress(progress)).await?;
+    }
+
+    if cancel_token.is_cancelled() {
+        return Err(ScrapeCancelled.into());
+    }
+    Ok(())
+}
+
+async fn scrape_partial_pages(
+    url: String,
+    ctx: &Arc<ScrapingContext>,
+    cancel_token: &CancellationToken,
+) -> anyhow::Result<Vec<PartialSchoolAreaPage>> {
+    log::info!("Started scraping School Area for: {}", url);
+    let html = fetch_url_body(&url, ctx, cancel_token).await?;
+    let ctx = Arc::clone(ctx);
+    let span = Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _entered = span.enter();
+        let mut partial_pages = Vec::new();
+        parse_partial_pages(&html, &url, &ctx, |partial_page| {
+            partial_pages.push(partial_page);
+            Ok(())
+        })?;
+        Ok(partial_pages)
+    })
+    .await?
+}
+
+async fn scrape_partial_courses(
+    url: String,
+    ctx: &Arc<ScrapingContext>,
+    cancel_token: &CancellationToken,
+) -> anyhow::Result<Vec<PartialCourse>> {
+    log::info!("Started scraping Subject Area for: {}", url);
+    let html = fetch_url_body(&url, ctx, cancel_token).await?;
+    let ctx = Arc::clone(ctx);
+    let span = Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _entered = span.enter();
+        let mut partial_courses = Vec::new();
+        parse_partial_courses(&html, &url, &ctx, |partial_course| {
+            partial_courses.push(partial_course);
+            Ok(())
+        })?;
+        Ok(partial_courses)
+    })
+    .await?
+}
+
+enum CourseError {
+    /// The course page wasn't fetched before the scrape was cancelled.
+    Cancelled(SkippedPage),
+    Failed(anyhow::Error),
+}
+
+/// Completes a course, telling a fetch cut short by cancellation apart from
+/// a failure.
+async fn complete_course(
+    partial_course: PartialCourse,
+    ctx: &ScrapingContext,
+    cancel_token: &CancellationToken,
+) -> Result<Course, CourseError> {
+    let skipped = SkippedPage::Course {
+        course_code: partial_course.course_code.clone(),
+        career: partial_course.career.clone(),
+        url: partial_course.url.clone(),
+    };
+    match partial_course.complete_impl(ctx, false, cancel_token).await {
+        Ok((course, _explanation)) => Ok(course),
+        Err(e) if is_cancelled(&e) => Err(CourseError::Cancelled(skipped)),
+        Err(e) => Err(CourseError::Failed(e)),
+    }
+}
@@ -1,23 +1,275 @@
+use std::sync::Arc;
+
+use reqwest::StatusCode;
+use tokio::sync::Semaphore;
+
 use crate::{
-    config::{ScrapingConfig, TimetableUrlRegex},
-    requests::RequestClient,
+    config::{DEFAULT_TIMETABLE_API_URL, ScrapingConfig, TimetableUrlRegex},
+    fetcher::Fetcher,
+    ratelimit::RateLimitPolicy,
+    requests::{HttpSettings, RequestClient},
+    scrape_filter::ScrapeFilter,
+    scrape_stats::ScrapeStats,
+    selector_profile::SelectorProfile,
 };
 
 pub struct ScrapingContext {
     pub scraping_config: ScrapingConfig,
     pub timetable_url_regex: TimetableUrlRegex,
-    pub request_client: RequestClient,
+    pub fetcher: Arc<dyn Fetcher>,
+    pub scrape_filter: ScrapeFilter,
+    // Arc'd so that it can be moved into the blocking tasks that parse pages.
+    pub selector_profile: Arc<SelectorProfile>,
+    /// Counts the requests made through this context.
+    pub stats: ScrapeStats,
+    /// Limits how many pages are fetched at once, if set.
+    request_permits: Option<Semaphore>,
 }
 
 impl ScrapingContext {
+    /// A context configured from the env vars (see `ScrapingEnv`).
     pub fn new() -> anyhow::Result<Self> {
-        let scraping_config = ScrapingConfig::new()?;
-        let timetable_url_regex = TimetableUrlRegex::new()?;
-        let request_client = RequestClient::new()?;
+        Self::with_scrape_filter(ScrapeFilter::default())
+    }
+
+    pub fn with_scrape_filter(scrape_filter: ScrapeFilter) -> anyhow::Result<Self> {
+        Self::with_config(ScrapingConfig::new()?, scrape_filter)
+    }
+
+    pub fn with_config(
+        scraping_config: ScrapingConfig,
+        scrape_filter: ScrapeFilter,
+    ) -> anyhow::Result<Self> {
+        Self::builder()
+            .scraping_config(scraping_config)
+            .scrape_filter(scrape_filter)
+            .build()
+    }
+
+    pub fn with_fetcher(
+        scraping_config: ScrapingConfig,
+        scrape_filter: ScrapeFilter,
+        fetcher: Arc<dyn Fetcher>,
+    ) -> anyhow::Result<Self> {
+        Self::builder()
+            .scraping_config(scraping_config)
+            .scrape_filter(scrape_filter)
+            .fetcher(fetcher)
+            .build()
+    }
+
+    /// Configures a context explicitly, without reading any env vars.
+    pub fn builder() -> ScrapingContextBuilder {
+        ScrapingContextBuilder::default()
+    }
+
+    pub async fn fetch_url_status(&self, url: &str) -> anyhow::Result<StatusCode> {
+        let (status, _body) = self.fetch(url).await?;
+        Ok(status)
+    }
+
+    /// Returns the body of `url`, whatever the response status.
+    pub async fn fetch_url_body(&self, url: &str) -> anyhow::Result<String> {
+        let (_status, body) = self.fetch(url).await?;
+        Ok(body)
+    }
+
+    async fn fetch(&self, url: &str) -> anyhow::Result<(StatusCode, String)> {
+        let _permit = match &self.request_permits {
+            Some(request_permits) => Some(request_permits.acquire().await?),
+            None => None,
+        };
+        let (status, body) = self.fetcher.fetch(url, self).await?;
+        self.stats.record_response(body.len());
+        Ok((status, body))
+    }
+}
+
+pub struct ScrapingContextBuilder {
+    timetable_api_url: String,
+    selector_profile_path: Option<String>,
+    scrape_filter: ScrapeFilter,
+    http_settings: HttpSettings,
+    rate_limit_policy: RateLimitPolicy,
+    max_concurrent_requests: Option<usize>,
+    fetcher: Option<Arc<dyn Fetcher>>,
+}
+
+impl Default for ScrapingContextBuilder {
+    fn default() -> Self {
+        Self {
+            timetable_api_url: DEFAULT_TIMETABLE_API_URL.to_string(),
+            selector_profile_path: None,
+            scrape_filter: ScrapeFilter::default(),
+            http_settings: HttpSettings::default(),
+            rate_limit_policy: RateLimitPolicy::default(),
+            max_concurrent_requests: None,
+            fetcher: None,
+        }
+    }
+}
+
+impl ScrapingContextBuilder {
+    /// The timetable website to scrape, containing `year`, which is replaced
+    /// with the year being scraped. Defaults to `DEFAULT_TIMETABLE_API_URL`.
+    pub fn timetable_api_url(mut self, timetable_api_url: impl Into<String>) -> Self {
+        self.timetable_api_url = timetable_api_url.into();
+        self
+    }
+
+    /// A JSON file overriding fields of the default selector profile.
+    pub fn selector_profile_path(mut self, selector_profile_path: impl Into<String>) -> Self {
+        self.selector_profile_path = Some(selector_profile_path.into());
+        self
+    }
+
+    /// Sets both the timetable API url and selector profile path.
+    pub fn scraping_config(mut self, scraping_config: ScrapingConfig) -> Self {
+        self.timetable_api_url = scraping_config.timetable_api_url().to_string();
+        self.selector_profile_path = scraping_config.selector_profile_path().map(String::from);
+        self
+    }
+
+    pub fn scrape_filter(mut self, scrape_filter: ScrapeFilter) -> Self {
+        self.scrape_filter = scrape_filter;
+        self
+    }
+
+    /// Settings for the default, live fetcher. Ignored if `fetcher` is set.
+    pub fn http_settings(mut self, http_settings: HttpSettings) -> Self {
+        self.http_settings = http_settings;
+        self
+    }
+
+    /// Rate limiting for the default, live fetcher. Ignored if `fetcher` is set.
+    pub fn rate_limit_policy(mut self, rate_limit_policy: RateLimitPolicy) -> Self {
+        self.rate_limit_policy = rate_limit_policy;
+        self
+    }
+
+    /// The most pages fetched at once, whatever the fetcher. Unlimited by
+    /// default, leaving it to the rate limiter.
+    pub fn max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = Some(max_concurrent_requests);
+        self
+    }
+
+    /// Where pages are fetched from, instead of the live website.
+    pub fn fetcher(mut self, fetcher: Arc<dyn Fetcher>) -> Self {
+        self.fetcher = Some(fetcher);
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<ScrapingContext> {
+        let scraping_config = ScrapingConfig::from_timetable_api_url(&self.timetable_api_url)
+            .with_selector_profile_path(self.selector_profile_path);
+        let timetable_url_regex = TimetableUrlRegex::new(scraping_config.timetable_api_url())?;
+        let fetcher = match self.fetcher {
+            Some(fetcher) => fetcher,
+            None => Arc::new(RequestClient::with_settings(
+                self.http_settings,
+                self.rate_limit_policy,
+            )?),
+        };
+        let selector_profile = Arc::new(SelectorProfile::load(
+            scraping_config.selector_profile_path(),
+        )?);
+        let request_permits = match self.max_concurrent_requests {
+            Some(0) => {
+                return Err(anyhow::anyhow!(
+                    "max concurrent requests should be larger than 0"
+                ));
+            }
+            Some(max_concurrent_requests) => Some(Semaphore::new(max_concurrent_requests)),
+            None => None,
+        };
         Ok(ScrapingContext {
             scraping_config,
             timetable_url_regex,
-            request_client,
+            fetcher,
+            scrape_filter: self.scrape_filter,
+            selector_profile,
+            stats: ScrapeStats::default(),
+            request_permits,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use futures::future::{BoxFuture, join_all};
+
+    use super::*;
+
+    /// Records the most requests it was handling at once.
+    #[derive(Default)]
+    struct SlowFetcher {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl Fetcher for SlowFetcher {
+        fn fetch<'a>(
+            &'a self,
+            _url: &'a str,
+            _ctx: &'a ScrapingContext,
+        ) -> BoxFuture<'a, anyhow::Result<(StatusCode, String)>> {
+            Box::pin(async move {
+                let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok((StatusCode::OK, String::new()))
+            })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_builder_limits_concurrent_requests() {
+        let fetcher = Arc::new(SlowFetcher::default());
+        let ctx = ScrapingContext::builder()
+            .timetable_api_url("http://localhost:8080/year/")
+            .max_concurrent_requests(3)
+            .fetcher(fetcher.clone())
+            .build()
+            .unwrap();
+        assert_eq!(
+            ctx.scraping_config.get_timetable_api_url_for_year(2025),
+            "http://localhost:8080/2025/"
+        );
+
+        join_all((0..20).map(|_| ctx.fetch_url_body("http://localhost:8080/2025/"))).await;
+        assert_eq!(fetcher.max_in_flight.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_settings() {
+        let invalid_policy = RateLimitPolicy {
+            backoff: 1.5,
+            ..RateLimitPolicy::default()
+        };
+        assert!(
+            ScrapingContext::builder()
+                .rate_limit_policy(invalid_policy)
+                .build()
+                .is_err()
+        );
+        assert!(
+            ScrapingContext::builder()
+                .max_concurrent_requests(0)
+                .build()
+                .is_err()
+        );
+        assert!(
+            ScrapingContext::builder()
+                .timetable_api_url("http://localhost:8080/")
+                .build()
+                .is_err()
+        );
+    }
+}
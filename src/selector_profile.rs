@@ -0,0 +1,241 @@
+use std::{fs, ops::Deref};
+
+use anyhow::Context;
+use scraper::Selector;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The selector profile shipped with the scraper, matching the current
+/// timetable website layout.
+const DEFAULT_SELECTOR_PROFILE: &str = include_str!("../selector_profile.json");
+
+/// The profile version this scraper understands. Bump whenever the meaning of
+/// an existing field changes, so stale override files are rejected instead of
+/// silently misparsing pages.
+const SUPPORTED_PROFILE_VERSION: u32 = 1;
+
+/// A CSS selector, parsed once, that remembers its source for error messages.
+pub struct ProfileSelector {
+    source: String,
+    selector: Selector,
+}
+
+impl ProfileSelector {
+    fn parse(source: String, field: &str) -> anyhow::Result<Self> {
+        // NOTE: We can't return the error from `Selector::parse` because it is not Send.
+        let selector = Selector::parse(&source)
+            .map_err(|_| anyhow::anyhow!("invalid selector '{}' for '{}'", source, field))?;
+        Ok(Self { source, selector })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl Deref for ProfileSelector {
+    type Target = Selector;
+
+    fn deref(&self) -> &Selector {
+        &self.selector
+    }
+}
+
+pub struct SchoolAreaSelectors {
+    pub row: ProfileSelector,
+    pub code: ProfileSelector,
+    pub name: ProfileSelector,
+    pub link: ProfileSelector,
+    pub school: ProfileSelector,
+}
+
+pub struct SubjectAreaSelectors {
+    pub career: ProfileSelector,
+    pub row: ProfileSelector,
+    pub code: ProfileSelector,
+    pub name: ProfileSelector,
+    pub link: ProfileSelector,
+    pub uoc: ProfileSelector,
+}
+
+pub struct CourseSelectors {
+    pub info_box: ProfileSelector,
+    pub terms_table: ProfileSelector,
+    pub terms_row: ProfileSelector,
+    pub label: ProfileSelector,
+    pub data: ProfileSelector,
+    pub class_cell: ProfileSelector,
+}
+
+/// The `td.label` texts the course page parser looks for.
+#[derive(Debug, Deserialize)]
+pub struct Labels {
+    pub faculty: String,
+    pub school: String,
+    pub campus: String,
+    pub career: String,
+    pub class_nbr: String,
+    pub section: String,
+    pub activity: String,
+    pub teaching_period: String,
+    pub status: String,
+    pub enrols_capacity: String,
+    pub offering_period: String,
+    pub meeting_dates: String,
+    pub census_date: String,
+    pub mode_of_delivery: String,
+    pub consent: String,
+    pub meeting_information: String,
+    pub class_notes: String,
+}
+
+/// All CSS selectors and labels the HTML parsers use, compiled once per scrape.
+///
+/// The defaults come from `selector_profile.json`. An override file only needs
+/// to contain the fields that differ, so a minor timetable website change can be
+/// fixed without recompiling.
+pub struct SelectorProfile {
+    pub version: u32,
+    pub school_area: SchoolAreaSelectors,
+    pub subject_area: SubjectAreaSelectors,
+    pub course: CourseSelectors,
+    pub labels: Labels,
+    pub days: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct SelectorProfileFile {
+    version: u32,
+    school_area: SchoolAreaSelectorsFile,
+    subject_area: SubjectAreaSelectorsFile,
+    course: CourseSelectorsFile,
+    labels: Labels,
+    days: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct SchoolAreaSelectorsFile {
+    row: String,
+    code: String,
+    name: String,
+    link: String,
+    school: String,
+}
+
+#[derive(Deserialize)]
+struct SubjectAreaSelectorsFile {
+    career: String,
+    row: String,
+    code: String,
+    name: String,
+    link: String,
+    uoc: String,
+}
+
+#[derive(Deserialize)]
+struct CourseSelectorsFile {
+    info_box: String,
+    terms_table: String,
+    terms_row: String,
+    label: String,
+    data: String,
+    class_cell: String,
+}
+
+/// Recursively overwrites the fields of `base` with those set in `overrides`.
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+impl SelectorProfile {
+    /// Loads the default profile, with the fields set in the JSON file at
+    /// `override_path` (if any) taking precedence.
+    pub fn load(override_path: Option<&str>) -> anyhow::Result<Self> {
+        let mut profile: Value = serde_json::from_str(DEFAULT_SELECTOR_PROFILE)
+            .context("failed to parse default selector profile")?;
+        if let Some(path) = override_path {
+            let overrides = fs::read_to_string(path)
+                .with_context(|| format!("failed to read selector profile {}", path))?;
+            let overrides: Value = serde_json::from_str(&overrides)
+                .with_context(|| format!("failed to parse selector profile {}", path))?;
+            merge(&mut profile, overrides);
+            log::info!("Using selector profile overrides from {}", path);
+        }
+        let profile: SelectorProfileFile =
+            serde_json::from_value(profile).context("invalid selector profile")?;
+        Self::compile(profile)
+    }
+
+    fn compile(profile: SelectorProfileFile) -> anyhow::Result<Self> {
+        if profile.version != SUPPORTED_PROFILE_VERSION {
+            return Err(anyhow::anyhow!(
+                "selector profile has version {}, but only version {} is supported",
+                profile.version,
+                SUPPORTED_PROFILE_VERSION
+            ));
+        }
+        let school_area = profile.school_area;
+        let subject_area = profile.subject_area;
+        let course = profile.course;
+        Ok(Self {
+            version: profile.version,
+            school_area: SchoolAreaSelectors {
+                row: ProfileSelector::parse(school_area.row, "school_area.row")?,
+                code: ProfileSelector::parse(school_area.code, "school_area.code")?,
+                name: ProfileSelector::parse(school_area.name, "school_area.name")?,
+                link: ProfileSelector::parse(school_area.link, "school_area.link")?,
+                school: ProfileSelector::parse(school_area.school, "school_area.school")?,
+            },
+            subject_area: SubjectAreaSelectors {
+                career: ProfileSelector::parse(subject_area.career, "subject_area.career")?,
+                row: ProfileSelector::parse(subject_area.row, "subject_area.row")?,
+                code: ProfileSelector::parse(subject_area.code, "subject_area.code")?,
+                name: ProfileSelector::parse(subject_area.name, "subject_area.name")?,
+                link: ProfileSelector::parse(subject_area.link, "subject_area.link")?,
+                uoc: ProfileSelector::parse(subject_area.uoc, "subject_area.uoc")?,
+            },
+            course: CourseSelectors {
+                info_box: ProfileSelector::parse(course.info_box, "course.info_box")?,
+                terms_table: ProfileSelector::parse(course.terms_table, "course.terms_table")?,
+                terms_row: ProfileSelector::parse(course.terms_row, "course.terms_row")?,
+                label: ProfileSelector::parse(course.label, "course.label")?,
+                data: ProfileSelector::parse(course.data, "course.data")?,
+                class_cell: ProfileSelector::parse(course.class_cell, "course.class_cell")?,
+            },
+            labels: profile.labels,
+            days: profile.days,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_selector_profile_overrides() {
+        let mut profile: Value = serde_json::from_str(DEFAULT_SELECTOR_PROFILE).unwrap();
+        merge(
+            &mut profile,
+            json!({ "course": { "info_box": "div.box" }, "labels": { "class_nbr": "Class No" } }),
+        );
+        let profile = SelectorProfile::compile(serde_json::from_value(profile).unwrap()).unwrap();
+        assert_eq!(profile.course.info_box.as_str(), "div.box");
+        assert_eq!(profile.course.label.as_str(), "td.label");
+        assert_eq!(profile.labels.class_nbr, "Class No");
+        assert_eq!(profile.labels.faculty, "Faculty");
+    }
+}
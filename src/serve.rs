@@ -0,0 +1,648 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_graphql::{
+    EmptyMutation, EmptySubscription, InputObject, Object, Schema, http::GraphiQLSource,
+};
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+};
+use serde::Deserialize;
+use tokio::net::TcpListener;
+use tower_http::cors::CorsLayer;
+
+use crate::{Class, ClassRow, Course, CourseRow, Time, TimeRow};
+
+/// The scraped courses, indexed for the REST endpoints' lookups.
+struct Tables {
+    courses: Vec<Course>,
+    courses_by_code: HashMap<String, Vec<usize>>,
+    classes_by_id: HashMap<String, (usize, usize)>,
+}
+
+impl Tables {
+    fn new(courses: Vec<Course>) -> Self {
+        let mut courses_by_code = HashMap::<_, Vec<_>>::new();
+        let mut classes_by_id = HashMap::new();
+        for (i, course) in courses.iter().enumerate() {
+            courses_by_code
+                .entry(course.course_code.clone())
+                .or_default()
+                .push(i);
+            for (j, class) in course.classes.iter().enumerate() {
+                classes_by_id.insert(class.class_id.clone(), (i, j));
+            }
+        }
+        Self {
+            courses,
+            courses_by_code,
+            classes_by_id,
+        }
+    }
+
+    /// The courses with `course_code`, one per career it is offered in.
+    fn courses_with_code(&self, course_code: &str) -> Option<impl Iterator<Item = &Course>> {
+        let indices = self.courses_by_code.get(course_code)?;
+        Some(indices.iter().map(|i| &self.courses[*i]))
+    }
+
+    fn class(&self, class_id: &str) -> Option<&Class> {
+        let (i, j) = self.classes_by_id.get(class_id)?;
+        Some(&self.courses[*i].classes[*j])
+    }
+
+    fn classes(&self) -> impl Iterator<Item = &Class> {
+        self.courses.iter().flat_map(|course| &course.classes)
+    }
+}
+
+type TimetableSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Serves `courses` read-only until `shutdown` completes, with the same rows
+/// as the Hasuragres tables:
+///
+/// - `GET /courses/{course_code}`: the course in every career it is offered in
+/// - `GET /courses/{course_code}/classes?term=T1`: its classes, optionally in one term
+/// - `GET /classes/{class_id}/times`: the times of a class
+/// - `POST /graphql`: a subset of the Hasura GraphQL API over the courses,
+///   classes and times tables, with GraphiQL at `GET /graphql`
+///
+/// Any origin may call it, for frontends served from another port.
+pub async fn serve_timetable(
+    listener: TcpListener,
+    courses: Vec<Course>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    axum::serve(listener, timetable_router(courses))
+        .with_graceful_shutdown(shutdown)
+        .await?;
+    Ok(())
+}
+
+fn timetable_router(courses: Vec<Course>) -> Router {
+    let tables = Arc::new(Tables::new(courses));
+    let schema = Schema::new(
+        QueryRoot(Arc::clone(&tables)),
+        EmptyMutation,
+        EmptySubscription,
+    );
+    let rest = Router::new()
+        .route("/courses/{course_code}", get(course_handler))
+        .route("/courses/{course_code}/classes", get(classes_handler))
+        .route("/classes/{class_id}/times", get(times_handler))
+        .with_state(tables);
+    let graphql = Router::new()
+        .route("/graphql", get(graphiql_handler).post(graphql_handler))
+        .with_state(schema);
+    rest.merge(graphql).layer(CorsLayer::permissive())
+}
+
+fn not_found(message: String) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": message })),
+    )
+        .into_response()
+}
+
+async fn course_handler(
+    State(tables): State<Arc<Tables>>,
+    Path(course_code): Path<String>,
+) -> Response {
+    match tables.courses_with_code(&course_code) {
+        Some(courses) => Json(courses.map(course_json).collect::<Vec<_>>()).into_response(),
+        None => not_found(format!("no course {}", course_code)),
+    }
+}
+
+#[derive(Deserialize)]
+struct ClassesParams {
+    term: Option<String>,
+}
+
+async fn classes_handler(
+    State(tables): State<Arc<Tables>>,
+    Path(course_code): Path<String>,
+    Query(params): Query<ClassesParams>,
+) -> Response {
+    let Some(courses) = tables.courses_with_code(&course_code) else {
+        return not_found(format!("no course {}", course_code));
+    };
+    let classes = courses
+        .flat_map(|course| &course.classes)
+        .filter(|class| params.term.as_ref().is_none_or(|term| class.term == *term))
+        .map(ClassRow::new)
+        .collect::<Vec<_>>();
+    Json(classes).into_response()
+}
+
+async fn times_handler(
+    State(tables): State<Arc<Tables>>,
+    Path(class_id): Path<String>,
+) -> Response {
+    let Some(class) = tables.class(&class_id) else {
+        return not_found(format!("no class {}", class_id));
+    };
+    let times = class
+        .times
+        .iter()
+        .flatten()
+        .map(|time| TimeRow::new(time, class))
+        .collect::<Vec<_>>();
+    Json(times).into_response()
+}
+
+async fn graphql_handler(
+    State(schema): State<TimetableSchema>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(schema.execute(request).await)
+}
+
+async fn graphiql_handler() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+/// The root query, named like Hasura's: a field per table and a `_by_pk`
+/// field per table, in snake case.
+struct QueryRoot(Arc<Tables>);
+
+#[Object(rename_fields = "snake_case", rename_args = "snake_case")]
+impl QueryRoot {
+    async fn courses(
+        &self,
+        #[graphql(name = "where")] filter: Option<CoursesBoolExp>,
+        limit: Option<usize>,
+        offset: Option<usize>,
+    ) -> Vec<CourseObject<'_>> {
+        let courses = self
+            .0
+            .courses
+            .iter()
+            .filter(|course| filter.as_ref().is_none_or(|filter| filter.matches(course)));
+        paginate(courses, limit, offset).map(CourseObject).collect()
+    }
+
+    async fn courses_by_pk(&self, course_id: String) -> Option<CourseObject<'_>> {
+        self.0
+            .courses
+            .iter()
+            .find(|course| course.course_id == course_id)
+            .map(CourseObject)
+    }
+
+    async fn classes(
+        &self,
+        #[graphql(name = "where")] filter: Option<ClassesBoolExp>,
+        limit: Option<usize>,
+        offset: Option<usize>,
+    ) -> Vec<ClassObject<'_>> {
+        let classes = self
+            .0
+            .classes()
+            .filter(|class| filter.as_ref().is_none_or(|filter| filter.matches(class)));
+        paginate(classes, limit, offset).map(ClassObject).collect()
+    }
+
+    async fn classes_by_pk(&self, class_id: String) -> Option<ClassObject<'_>> {
+        self.0.class(&class_id).map(ClassObject)
+    }
+
+    async fn times(
+        &self,
+        #[graphql(name = "where")] filter: Option<TimesBoolExp>,
+        limit: Option<usize>,
+        offset: Option<usize>,
+    ) -> Vec<TimeObject<'_>> {
+        let times = self
+            .0
+            .classes()
+            .flat_map(class_times)
+            .filter(|time| filter.as_ref().is_none_or(|filter| filter.matches(time)));
+        paginate(times, limit, offset).collect()
+    }
+
+    async fn times_by_pk(&self, id: String) -> Option<TimeObject<'_>> {
+        self.0
+            .classes()
+            .flat_map(class_times)
+            .find(|time| time.id() == id)
+    }
+}
+
+fn paginate<T>(
+    rows: impl Iterator<Item = T>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> impl Iterator<Item = T> {
+    rows.skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(usize::MAX))
+}
+
+fn class_times(class: &Class) -> impl Iterator<Item = TimeObject<'_>> {
+    class
+        .times
+        .iter()
+        .flatten()
+        .map(move |time| TimeObject(time, class))
+}
+
+struct CourseObject<'a>(&'a Course);
+
+#[Object(
+    name = "courses",
+    rename_fields = "snake_case",
+    rename_args = "snake_case"
+)]
+impl CourseObject<'_> {
+    async fn course_id(&self) -> &str {
+        &self.0.course_id
+    }
+
+    async fn course_code(&self) -> &str {
+        &self.0.course_code
+    }
+
+    async fn course_name(&self) -> &str {
+        &self.0.course_name
+    }
+
+    async fn uoc(&self) -> i32 {
+        self.0.uoc
+    }
+
+    async fn faculty(&self) -> Option<&str> {
+        self.0.faculty.as_deref()
+    }
+
+    async fn school(&self) -> Option<&str> {
+        self.0.school.as_deref()
+    }
+
+    async fn campus(&self) -> Option<&str> {
+        self.0.campus.as_deref()
+    }
+
+    async fn career(&self) -> Option<&str> {
+        self.0.career.as_deref()
+    }
+
+    /// A JSON array, as in the `TEXT` column of Hasuragres.
+    async fn terms(&self) -> String {
+        terms_text(self.0)
+    }
+
+    async fn modes(&self) -> &[String] {
+        &self.0.modes
+    }
+
+    async fn classes(
+        &self,
+        #[graphql(name = "where")] filter: Option<ClassesBoolExp>,
+    ) -> Vec<ClassObject<'_>> {
+        self.0
+            .classes
+            .iter()
+            .filter(|class| filter.as_ref().is_none_or(|filter| filter.matches(class)))
+            .map(ClassObject)
+            .collect()
+    }
+}
+
+fn terms_text(course: &Course) -> String {
+    serde_json::to_string(&course.terms).expect("terms serialise to JSON")
+}
+
+/// A `CourseRow` with `terms` as it is read back from Hasuragres.
+fn course_json(course: &Course) -> serde_json::Value {
+    let mut row = serde_json::to_value(CourseRow::new(course)).expect("rows serialise to JSON");
+    row["terms"] = terms_text(course).into();
+    row
+}
+
+struct ClassObject<'a>(&'a Class);
+
+#[Object(
+    name = "classes",
+    rename_fields = "snake_case",
+    rename_args = "snake_case"
+)]
+impl ClassObject<'_> {
+    async fn class_id(&self) -> &str {
+        &self.0.class_id
+    }
+
+    async fn career(&self) -> &str {
+        &self.0.career
+    }
+
+    async fn course_id(&self) -> &str {
+        &self.0.course_id
+    }
+
+    async fn section(&self) -> &str {
+        &self.0.section
+    }
+
+    async fn term(&self) -> &str {
+        &self.0.term
+    }
+
+    async fn year(&self) -> &str {
+        &self.0.year
+    }
+
+    async fn activity(&self) -> &str {
+        &self.0.activity
+    }
+
+    async fn status(&self) -> &str {
+        &self.0.status
+    }
+
+    async fn course_enrolment(&self) -> &str {
+        &self.0.course_enrolment
+    }
+
+    async fn offering_period(&self) -> &str {
+        &self.0.offering_period
+    }
+
+    async fn meeting_dates(&self) -> &str {
+        &self.0.meeting_dates
+    }
+
+    async fn census_date(&self) -> &str {
+        &self.0.census_date
+    }
+
+    async fn consent(&self) -> &str {
+        &self.0.consent
+    }
+
+    async fn mode(&self) -> &str {
+        &self.0.mode
+    }
+
+    async fn class_notes(&self) -> Option<&str> {
+        self.0.class_notes.as_deref()
+    }
+
+    async fn times(
+        &self,
+        #[graphql(name = "where")] filter: Option<TimesBoolExp>,
+    ) -> Vec<TimeObject<'_>> {
+        class_times(self.0)
+            .filter(|time| filter.as_ref().is_none_or(|filter| filter.matches(time)))
+            .collect()
+    }
+}
+
+struct TimeObject<'a>(&'a Time, &'a Class);
+
+impl TimeObject<'_> {
+    fn id(&self) -> String {
+        self.0.id(self.1)
+    }
+}
+
+#[Object(
+    name = "times",
+    rename_fields = "snake_case",
+    rename_args = "snake_case"
+)]
+impl TimeObject<'_> {
+    #[graphql(name = "id")]
+    async fn id_field(&self) -> String {
+        self.id()
+    }
+
+    async fn class_id(&self) -> &str {
+        &self.1.class_id
+    }
+
+    async fn career(&self) -> &str {
+        &self.0.career
+    }
+
+    async fn day(&self) -> &str {
+        &self.0.day
+    }
+
+    async fn instructor(&self) -> Option<&str> {
+        self.0.instructor.as_deref()
+    }
+
+    async fn location(&self) -> &str {
+        &self.0.location
+    }
+
+    async fn time(&self) -> &str {
+        &self.0.time
+    }
+
+    async fn weeks(&self) -> &str {
+        &self.0.weeks
+    }
+}
+
+/// Hasura's operators on text columns. Like in SQL, only `_is_null` matches
+/// `null` values.
+#[derive(InputObject)]
+#[graphql(name = "String_comparison_exp")]
+struct StringComparisonExp {
+    #[graphql(name = "_eq")]
+    eq: Option<String>,
+    #[graphql(name = "_neq")]
+    neq: Option<String>,
+    #[graphql(name = "_in")]
+    is_in: Option<Vec<String>>,
+    #[graphql(name = "_nin")]
+    not_in: Option<Vec<String>>,
+    #[graphql(name = "_is_null")]
+    is_null: Option<bool>,
+}
+
+impl StringComparisonExp {
+    fn matches(&self, value: Option<&str>) -> bool {
+        if let Some(is_null) = self.is_null
+            && is_null != value.is_none()
+        {
+            return false;
+        }
+        let Some(value) = value else {
+            return self.eq.is_none()
+                && self.neq.is_none()
+                && self.is_in.is_none()
+                && self.not_in.is_none();
+        };
+        self.eq.as_ref().is_none_or(|eq| value == eq)
+            && self.neq.as_ref().is_none_or(|neq| value != neq)
+            && self
+                .is_in
+                .as_ref()
+                .is_none_or(|values| values.iter().any(|v| value == v))
+            && self
+                .not_in
+                .as_ref()
+                .is_none_or(|values| values.iter().all(|v| value != v))
+    }
+}
+
+/// Hasura's operators on integer columns.
+#[derive(InputObject)]
+#[graphql(name = "Int_comparison_exp")]
+struct IntComparisonExp {
+    #[graphql(name = "_eq")]
+    eq: Option<i32>,
+    #[graphql(name = "_neq")]
+    neq: Option<i32>,
+    #[graphql(name = "_gt")]
+    gt: Option<i32>,
+    #[graphql(name = "_gte")]
+    gte: Option<i32>,
+    #[graphql(name = "_lt")]
+    lt: Option<i32>,
+    #[graphql(name = "_lte")]
+    lte: Option<i32>,
+}
+
+impl IntComparisonExp {
+    fn matches(&self, value: i32) -> bool {
+        self.eq.is_none_or(|eq| value == eq)
+            && self.neq.is_none_or(|neq| value != neq)
+            && self.gt.is_none_or(|gt| value > gt)
+            && self.gte.is_none_or(|gte| value >= gte)
+            && self.lt.is_none_or(|lt| value < lt)
+            && self.lte.is_none_or(|lte| value <= lte)
+    }
+}
+
+fn text_matches(comparison: &Option<StringComparisonExp>, value: Option<&str>) -> bool {
+    comparison
+        .as_ref()
+        .is_none_or(|comparison| comparison.matches(value))
+}
+
+/// Filters courses by their columns. Unlike Hasura, there is no `_and`,
+/// `_or` or `_not`: all given columns must match.
+#[derive(InputObject)]
+#[graphql(name = "courses_bool_exp", rename_fields = "snake_case")]
+struct CoursesBoolExp {
+    course_id: Option<StringComparisonExp>,
+    course_code: Option<StringComparisonExp>,
+    course_name: Option<StringComparisonExp>,
+    uoc: Option<IntComparisonExp>,
+    faculty: Option<StringComparisonExp>,
+    school: Option<StringComparisonExp>,
+    campus: Option<StringComparisonExp>,
+    career: Option<StringComparisonExp>,
+    terms: Option<StringComparisonExp>,
+}
+
+impl CoursesBoolExp {
+    fn matches(&self, course: &Course) -> bool {
+        text_matches(&self.course_id, Some(&course.course_id))
+            && text_matches(&self.course_code, Some(&course.course_code))
+            && text_matches(&self.course_name, Some(&course.course_name))
+            && self.uoc.as_ref().is_none_or(|uoc| uoc.matches(course.uoc))
+            && text_matches(&self.faculty, course.faculty.as_deref())
+            && text_matches(&self.school, course.school.as_deref())
+            && text_matches(&self.campus, course.campus.as_deref())
+            && text_matches(&self.career, course.career.as_deref())
+            && text_matches(&self.terms, Some(&terms_text(course)))
+    }
+}
+
+/// Filters classes by their columns, see [`CoursesBoolExp`].
+#[derive(InputObject)]
+#[graphql(name = "classes_bool_exp", rename_fields = "snake_case")]
+struct ClassesBoolExp {
+    class_id: Option<StringComparisonExp>,
+    career: Option<StringComparisonExp>,
+    course_id: Option<StringComparisonExp>,
+    section: Option<StringComparisonExp>,
+    term: Option<StringComparisonExp>,
+    year: Option<StringComparisonExp>,
+    activity: Option<StringComparisonExp>,
+    status: Option<StringComparisonExp>,
+    consent: Option<StringComparisonExp>,
+    mode: Option<StringComparisonExp>,
+}
+
+impl ClassesBoolExp {
+    fn matches(&self, class: &Class) -> bool {
+        text_matches(&self.class_id, Some(&class.class_id))
+            && text_matches(&self.career, Some(&class.career))
+            && text_matches(&self.course_id, Some(&class.course_id))
+            && text_matches(&self.section, Some(&class.section))
+            && text_matches(&self.term, Some(&class.term))
+            && text_matches(&self.year, Some(&class.year))
+            && text_matches(&self.activity, Some(&class.activity))
+            && text_matches(&self.status, Some(&class.status))
+            && text_matches(&self.consent, Some(&class.consent))
+            && text_matches(&self.mode, Some(&class.mode))
+    }
+}
+
+/// Filters times by their columns, see [`CoursesBoolExp`].
+#[derive(InputObject)]
+#[graphql(name = "times_bool_exp", rename_fields = "snake_case")]
+struct TimesBoolExp {
+    class_id: Option<StringComparisonExp>,
+    career: Option<StringComparisonExp>,
+    day: Option<StringComparisonExp>,
+    instructor: Option<StringComparisonExp>,
+    location: Option<StringComparisonExp>,
+    time: Option<StringComparisonExp>,
+    weeks: Option<StringComparisonExp>,
+}
+
+impl TimesBoolExp {
+    fn matches(&self, time: &TimeObject) -> bool {
+        let TimeObject(time, class) = time;
+        text_matches(&self.class_id, Some(&class.class_id))
+            && text_matches(&self.career, Some(&time.career))
+            && text_matches(&self.day, Some(&time.day))
+            && text_matches(&self.instructor, time.instructor.as_deref())
+            && text_matches(&self.location, Some(&time.location))
+            && text_matches(&self.time, Some(&time.time))
+            && text_matches(&self.weeks, Some(&time.weeks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comparison() -> StringComparisonExp {
+        StringComparisonExp {
+            eq: None,
+            neq: None,
+            is_in: None,
+            not_in: None,
+            is_null: None,
+        }
+    }
+
+    #[test]
+    fn test_string_comparison_of_null() {
+        let neq = StringComparisonExp {
+            neq: Some("Dr A Smith".to_string()),
+            ..comparison()
+        };
+        assert!(neq.matches(Some("Dr B Jones")));
+        assert!(!neq.matches(Some("Dr A Smith")));
+        assert!(!neq.matches(None));
+        let is_null = StringComparisonExp {
+            is_null: Some(true),
+            ..comparison()
+        };
+        assert!(is_null.matches(None));
+        assert!(!is_null.matches(Some("Dr A Smith")));
+        assert!(comparison().matches(None));
+    }
+}
@@ -0,0 +1,242 @@
+use std::{collections::HashSet, fs, io, path::Path};
+
+use anyhow::Context;
+use rusqlite::{Connection, params};
+
+use crate::{
+    Course, PartialSchoolAreaPage,
+    rows::{class_rows, course_rows, occurrence_rows, time_rows},
+    text_manipulators::subject_code,
+};
+
+const SCHEMA: &str = include_str!("../sql/sqlite.sql");
+
+/// Writes courses, their classes and times, and the subject areas they are
+/// listed under into a new SQLite database at `path`, replacing any existing
+/// file. The occurrences table is only filled in if `with_occurrences`. The
+/// schema is in `sql/sqlite.sql`.
+pub fn export_sqlite(
+    path: &Path,
+    courses: &[Course],
+    subject_areas: &[PartialSchoolAreaPage],
+    with_occurrences: bool,
+) -> anyhow::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            return Err(e).with_context(|| format!("failed to replace {}", path.display()));
+        }
+        _ => {}
+    }
+    let mut connection = Connection::open(path)
+        .with_context(|| format!("failed to create SQLite database {}", path.display()))?;
+    connection.pragma_update(None, "foreign_keys", true)?;
+    connection.execute_batch(SCHEMA)?;
+
+    let transaction = connection.transaction()?;
+    {
+        let mut insert_subject_area = transaction.prepare(
+            "INSERT INTO SubjectAreas (subject_code, subject_name, school, url)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for subject_area in subject_areas {
+            insert_subject_area
+                .execute(params![
+                    subject_area.subject_code,
+                    subject_area.subject_name,
+                    subject_area.school,
+                    subject_area.subject_area_url,
+                ])
+                .with_context(|| {
+                    format!(
+                        "failed to insert subject area {}",
+                        subject_area.subject_code
+                    )
+                })?;
+        }
+        let subject_codes = subject_areas
+            .iter()
+            .map(|subject_area| subject_area.subject_code.as_str())
+            .collect::<HashSet<_>>();
+
+        let mut insert_course = transaction.prepare(
+            "INSERT INTO Courses (course_id, course_code, course_name, uoc, faculty, school, campus, career, terms, modes, subject_code)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        )?;
+        let mut insert_class = transaction.prepare(
+            "INSERT INTO Classes (class_id, career, course_id, section, term, year, activity, status, course_enrolment, offering_period, meeting_dates, census_date, consent, mode, class_notes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        )?;
+        let mut insert_time = transaction.prepare(
+            "INSERT INTO Times (id, class_id, day, instructor, location, time, weeks, career)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for course in course_rows(courses) {
+            let subject_code = subject_code(course.course_code)
+                .filter(|subject_code| subject_codes.contains(subject_code));
+            insert_course
+                .execute(params![
+                    course.course_id,
+                    course.course_code,
+                    course.course_name,
+                    course.uoc,
+                    course.faculty,
+                    course.school,
+                    course.campus,
+                    course.career,
+                    serde_json::to_string(course.terms)?,
+                    serde_json::to_string(course.modes)?,
+                    subject_code,
+                ])
+                .with_context(|| format!("failed to insert course {}", course.course_id))?;
+        }
+        for class in class_rows(courses) {
+            insert_class
+                .execute(params![
+                    class.class_id,
+                    class.career,
+                    class.course_id,
+                    class.section,
+                    class.term,
+                    class.year,
+                    class.activity,
+                    class.status,
+                    class.course_enrolment,
+                    class.offering_period,
+                    class.meeting_dates,
+                    class.census_date,
+                    class.consent,
+                    class.mode,
+                    class.class_notes,
+                ])
+                .with_context(|| format!("failed to insert class {}", class.class_id))?;
+        }
+        for time in time_rows(courses) {
+            insert_time
+                .execute(params![
+                    time.id,
+                    time.class_id,
+                    time.day,
+                    time.instructor,
+                    time.location,
+                    time.time,
+                    time.weeks,
+                    time.career,
+                ])
+                .with_context(|| format!("failed to insert time {}", time.id))?;
+        }
+        if with_occurrences {
+            let mut insert_occurrence = transaction.prepare(
+                "INSERT INTO Occurrences (id, time_id, class_id, week, date, starts_at, ends_at, location)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for occurrence in occurrence_rows(courses) {
+                insert_occurrence
+                    .execute(params![
+                        occurrence.id,
+                        occurrence.time_id,
+                        occurrence.class_id,
+                        occurrence.week,
+                        occurrence.date.to_string(),
+                        occurrence.starts_at.to_rfc3339(),
+                        occurrence.ends_at.to_rfc3339(),
+                        occurrence.location,
+                    ])
+                    .with_context(|| format!("failed to insert occurrence {}", occurrence.id))?;
+            }
+        }
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Time,
+        test_fixtures::{self, class, time},
+    };
+
+    fn course() -> Course {
+        let time = Time {
+            location: "CLB 7 (K-E19-G05)".to_string(),
+            weeks: "1-5,7-10".to_string(),
+            instructor: Some("Dr A Smith".to_string()),
+            ..time("Mon", "09:00 - 11:00")
+        };
+        test_fixtures::course("COMP1511", vec![class("COMP1511", "A", "T1", vec![time])])
+    }
+
+    #[test]
+    fn test_export_sqlite() {
+        let dir = std::env::temp_dir().join(format!("spooderman-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timetable.sqlite");
+        let subject_areas = [PartialSchoolAreaPage::new(
+            "COMP".to_string(),
+            "Computer Science".to_string(),
+            "School of Computer Science and Engineering".to_string(),
+            "https://timetable.unsw.edu.au/2025/COMPKENS.html".to_string(),
+        )];
+        // Exporting again replaces the database instead of failing on duplicates.
+        export_sqlite(&path, &[course()], &subject_areas, true).unwrap();
+        export_sqlite(&path, &[course()], &subject_areas, true).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let row = connection
+            .query_row(
+                "SELECT SubjectAreas.subject_name, Courses.modes, Classes.status, Times.instructor
+                 FROM Courses
+                 JOIN SubjectAreas USING (subject_code)
+                 JOIN Classes USING (course_id)
+                 JOIN Times USING (class_id)",
+                [],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            row,
+            (
+                "Computer Science".to_string(),
+                "[\"In Person\"]".to_string(),
+                "Open".to_string(),
+                "Dr A Smith".to_string()
+            )
+        );
+
+        let occurrences = connection
+            .query_row(
+                "SELECT COUNT(*), MIN(starts_at) FROM Occurrences
+                 WHERE location = 'CLB 7 (K-E19-G05)' AND date BETWEEN '2025-02-17' AND '2025-03-02'",
+                [],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+            )
+            .unwrap();
+        assert_eq!(occurrences, (2, "2025-02-17T09:00:00+11:00".to_string()));
+
+        // Courses whose subject area wasn't scraped have no subject code.
+        export_sqlite(&path, &[course()], &[], false).unwrap();
+        let connection = Connection::open(&path).unwrap();
+        let subject_code = connection
+            .query_row("SELECT subject_code FROM Courses", [], |row| {
+                row.get::<_, Option<String>>(0)
+            })
+            .unwrap();
+        assert_eq!(subject_code, None);
+        let occurrences = connection
+            .query_row("SELECT COUNT(*) FROM Occurrences", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .unwrap();
+        assert_eq!(occurrences, 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,357 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{Course, PartialSchoolAreaPage, Year, text_manipulators::subject_code};
+
+/// Lists every file of a static API tree with its content hash, so that
+/// clients and CDNs can tell which files changed.
+#[derive(Debug, Serialize)]
+pub struct StaticApiManifest {
+    /// The years in the tree, latest first.
+    pub years: Vec<Year>,
+    /// Sorted by path.
+    pub files: Vec<StaticApiFile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StaticApiFile {
+    /// The URL path, e.g. `/2025/courses/COMP1511.json`.
+    pub path: String,
+    pub bytes: u64,
+    /// Hex encoded.
+    pub sha256: String,
+}
+
+#[derive(Serialize)]
+struct YearIndex<'a> {
+    year: Year,
+    subjects: Vec<SubjectSummary<'a>>,
+    courses: Vec<CourseSummary<'a>>,
+}
+
+#[derive(Serialize)]
+struct SubjectSummary<'a> {
+    subject_code: &'a str,
+    subject_name: Option<&'a str>,
+    school: Option<&'a str>,
+    courses: usize,
+    path: String,
+}
+
+#[derive(Serialize)]
+struct Subject<'a> {
+    year: Year,
+    subject_code: &'a str,
+    subject_name: Option<&'a str>,
+    school: Option<&'a str>,
+    courses: Vec<CourseSummary<'a>>,
+}
+
+/// A course offered in one career, without its classes.
+#[derive(Serialize)]
+struct CourseSummary<'a> {
+    course_code: &'a str,
+    course_name: &'a str,
+    career: Option<&'a str>,
+    uoc: i32,
+    terms: &'a [String],
+    modes: &'a [String],
+    path: String,
+}
+
+/// Every career a course is offered in, with its classes and times.
+#[derive(Serialize)]
+struct CourseFile<'a> {
+    year: Year,
+    course_code: &'a str,
+    courses: Vec<&'a Course>,
+}
+
+/// Writes a read-only JSON API for `year` into `dir`, for a plain file server:
+///
+/// - `/{year}/index.json`: the subject areas and courses of the year
+/// - `/{year}/subjects/{subject_code}.json`: a subject area and its courses
+/// - `/{year}/courses/{course_code}.json`: a course in every career it is
+///   offered in, with its classes and times
+/// - `/manifest.json`: every file in the tree with its size and SHA-256
+///
+/// The `{year}` directory is replaced, and other years already in `dir` are
+/// kept and listed in the manifest.
+pub fn export_static_api(
+    dir: &Path,
+    year: Year,
+    courses: &[Course],
+    subject_areas: &[PartialSchoolAreaPage],
+) -> anyhow::Result<StaticApiManifest> {
+    let year_dir = dir.join(year.to_string());
+    match fs::remove_dir_all(&year_dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(e).with_context(|| format!("failed to replace {}", year_dir.display()));
+        }
+        _ => {}
+    }
+    fs::create_dir_all(year_dir.join("subjects"))?;
+    fs::create_dir_all(year_dir.join("courses"))?;
+
+    let mut courses_by_code = BTreeMap::<&str, Vec<&Course>>::new();
+    for course in courses {
+        if !is_path_safe(&course.course_code) {
+            anyhow::bail!("unexpected course code '{}'", course.course_code);
+        }
+        courses_by_code
+            .entry(&course.course_code)
+            .or_default()
+            .push(course);
+    }
+    let mut subject_areas_by_code = BTreeMap::new();
+    for subject_area in subject_areas {
+        if !is_path_safe(&subject_area.subject_code) {
+            anyhow::bail!("unexpected subject code '{}'", subject_area.subject_code);
+        }
+        subject_areas_by_code.insert(subject_area.subject_code.as_str(), subject_area);
+    }
+    // Courses whose subject area wasn't recorded still get a subject file.
+    let mut courses_by_subject = subject_areas_by_code
+        .keys()
+        .map(|subject_code| (*subject_code, Vec::new()))
+        .collect::<BTreeMap<_, _>>();
+    for course in courses_by_code.values().flatten() {
+        let subject_code = subject_code(&course.course_code).unwrap_or(&course.course_code);
+        courses_by_subject
+            .entry(subject_code)
+            .or_default()
+            .push(*course);
+    }
+
+    for (course_code, courses) in &courses_by_code {
+        let course_file = CourseFile {
+            year,
+            course_code,
+            courses: courses.clone(),
+        };
+        write_json(
+            &year_dir.join(format!("courses/{}.json", course_code)),
+            &course_file,
+        )?;
+    }
+    let mut subjects = Vec::new();
+    for (subject_code, courses) in &courses_by_subject {
+        let subject_area = subject_areas_by_code.get(subject_code);
+        let subject = Subject {
+            year,
+            subject_code,
+            subject_name: subject_area.map(|subject_area| subject_area.subject_name.as_str()),
+            school: subject_area.map(|subject_area| subject_area.school.as_str()),
+            courses: courses
+                .iter()
+                .map(|course| course_summary(year, course))
+                .collect(),
+        };
+        let path = format!("subjects/{}.json", subject_code);
+        write_json(&year_dir.join(&path), &subject)?;
+        subjects.push(SubjectSummary {
+            subject_code,
+            subject_name: subject.subject_name,
+            school: subject.school,
+            courses: courses.len(),
+            path: format!("/{}/{}", year, path),
+        });
+    }
+    let index = YearIndex {
+        year,
+        subjects,
+        courses: courses_by_code
+            .values()
+            .flatten()
+            .map(|course| course_summary(year, course))
+            .collect(),
+    };
+    write_json(&year_dir.join("index.json"), &index)?;
+
+    let manifest = manifest(dir)?;
+    write_json(&dir.join("manifest.json"), &manifest)?;
+    Ok(manifest)
+}
+
+fn course_summary(year: Year, course: &Course) -> CourseSummary<'_> {
+    CourseSummary {
+        course_code: &course.course_code,
+        course_name: &course.course_name,
+        career: course.career.as_deref(),
+        uoc: course.uoc,
+        terms: &course.terms,
+        modes: &course.modes,
+        path: format!("/{}/courses/{}.json", year, course.course_code),
+    }
+}
+
+fn is_path_safe(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> anyhow::Result<()> {
+    fs::write(path, serde_json::to_vec(value)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Hashes every file of every year directory in `dir`.
+fn manifest(dir: &Path) -> anyhow::Result<StaticApiManifest> {
+    let mut years = Vec::new();
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if let Some(year) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<Year>().ok())
+            && entry.file_type()?.is_dir()
+        {
+            years.push(year);
+            collect_files(&entry.path(), &mut paths)?;
+        }
+    }
+    years.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut files = Vec::new();
+    for path in paths {
+        let contents = fs::read(&path)?;
+        let relative_path = path.strip_prefix(dir)?;
+        files.push(StaticApiFile {
+            path: format!(
+                "/{}",
+                relative_path
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            ),
+            bytes: contents.len() as u64,
+            sha256: format!("{:x}", Sha256::digest(&contents)),
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(StaticApiManifest { years, files })
+}
+
+fn collect_files(dir: &Path, paths: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), paths)?;
+        } else {
+            paths.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures;
+    use serde_json::{Value, json};
+
+    fn course(course_code: &str, career: &str) -> Course {
+        Course {
+            course_id: format!("{}{}", course_code, career),
+            career: Some(career.to_string()),
+            terms: vec!["T1".to_string()],
+            ..test_fixtures::course(course_code, Vec::new())
+        }
+    }
+
+    fn read(dir: &Path, path: &str) -> Value {
+        serde_json::from_slice(&fs::read(dir.join(path)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_export_static_api() {
+        let dir = std::env::temp_dir().join(format!("spooderman-{}", uuid::Uuid::new_v4()));
+        let courses = [
+            course("COMP1511", "Postgraduate"),
+            course("COMP1511", "Undergraduate"),
+            course("MATH1131", "Undergraduate"),
+        ];
+        let subject_areas = [PartialSchoolAreaPage {
+            subject_code: "COMP".to_string(),
+            subject_name: "Computer Science".to_string(),
+            school: "School of Computer Science and Engineering".to_string(),
+            subject_area_url: "https://timetable.unsw.edu.au/2025/COMPKENS.html".to_string(),
+        }];
+        export_static_api(&dir, 2024, &courses[2..], &[]).unwrap();
+        // A stale course from an earlier export of the same year is removed.
+        export_static_api(&dir, 2025, &courses[2..], &[]).unwrap();
+        let manifest = export_static_api(&dir, 2025, &courses[..2], &subject_areas).unwrap();
+
+        let index = read(&dir, "2025/index.json");
+        assert_eq!(
+            index["subjects"],
+            json!([{
+                "subject_code": "COMP",
+                "subject_name": "Computer Science",
+                "school": "School of Computer Science and Engineering",
+                "courses": 2,
+                "path": "/2025/subjects/COMP.json",
+            }])
+        );
+        assert_eq!(index["courses"].as_array().unwrap().len(), 2);
+        let subject = read(&dir, "2025/subjects/COMP.json");
+        assert_eq!(subject["courses"][1]["career"], "Undergraduate");
+        assert_eq!(subject["courses"][1]["path"], "/2025/courses/COMP1511.json");
+        let course = read(&dir, "2025/courses/COMP1511.json");
+        assert_eq!(course["course_code"], "COMP1511");
+        assert_eq!(course["courses"][0]["course_id"], "COMP1511Postgraduate");
+        assert!(!dir.join("2025/courses/MATH1131.json").exists());
+
+        assert_eq!(manifest.years, [2025, 2024]);
+        let paths = manifest
+            .files
+            .iter()
+            .map(|file| file.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                "/2024/courses/MATH1131.json",
+                "/2024/index.json",
+                "/2024/subjects/MATH.json",
+                "/2025/courses/COMP1511.json",
+                "/2025/index.json",
+                "/2025/subjects/COMP.json",
+            ]
+        );
+        let contents = fs::read(dir.join("2025/index.json")).unwrap();
+        let file = &manifest.files[4];
+        assert_eq!(file.bytes, contents.len() as u64);
+        assert_eq!(file.sha256, format!("{:x}", Sha256::digest(&contents)));
+        assert_eq!(
+            read(&dir, "manifest.json")["files"][4]["sha256"],
+            file.sha256
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_static_api_rejects_unsafe_subject_code() {
+        let dir = std::env::temp_dir().join(format!("spooderman-{}", uuid::Uuid::new_v4()));
+        let subject_areas = [PartialSchoolAreaPage {
+            subject_code: "../COMP".to_string(),
+            subject_name: "Computer Science".to_string(),
+            school: "School of Computer Science and Engineering".to_string(),
+            subject_area_url: "https://timetable.unsw.edu.au/2025/COMPKENS.html".to_string(),
+        }];
+        let err = export_static_api(&dir, 2025, &[], &subject_areas).unwrap_err();
+        assert_eq!(err.to_string(), "unexpected subject code '../COMP'");
+        assert!(!dir.join("2025/COMP.json").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,12 +1,14 @@
 use std::{collections::HashSet, sync::Arc};
 
-use anyhow::Context;
-use scraper::{ElementRef, Selector};
-use tokio::sync::mpsc;
+use scraper::ElementRef;
+use tokio::{sync::mpsc, task::JoinSet};
+use tracing::{Instrument, Span};
 
 use crate::{
     Course, ScrapingContext,
     course_scraper::PartialCourse,
+    parse_error::{Expectation, ParseError, ParseStage},
+    selector_profile::ProfileSelector,
     text_manipulators::{extract_text, get_html_link_to_page},
 };
 
@@ -16,155 +18,176 @@ pub struct SubjectArea {
 }
 
 impl SubjectArea {
+    /// Scrapes a subject area page and every course it lists. Runs in the
+    /// caller's span, since the subject code is only known to the caller.
+    #[deprecated(note = "use `scrape_year` or `scrape_all_courses` instead")]
     pub async fn scrape(url: String, ctx: &Arc<ScrapingContext>) -> anyhow::Result<Self> {
         log::info!("Started scraping Subject Area for: {}", url);
-
-        let html = ctx.request_client.fetch_url_body(&url, ctx).await?;
+        let html = ctx.fetch_url_body(&url).await?;
 
         // We use a channel so we can start completing a partial course
-        // immediately once it's scraped, so we don't have to wait until all
-        // partial courses have been scraped.
+        // immediately once it's parsed, without waiting for the whole page.
         let (tx, mut rx) = mpsc::unbounded_channel();
-
-        let producer = async move || -> anyhow::Result<()> {
+        let producer = {
             let ctx = Arc::clone(ctx);
-
-            let cpu_bound = move || -> anyhow::Result<()> {
-                let document = scraper::Html::parse_document(&html);
-
-                // NOTE: We can't return the error message from `Selector::parse`
-                // because it is not Send and, therefore, not sendable across threads.
-                let error_msg = format!("failed to parse {}", url);
-
-                let career_selector = Selector::parse("td.classSearchMinorHeading")
-                    .map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-                let row_selector = Selector::parse("tr.rowLowlight, tr.rowHighlight")
-                    .map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-                let code_selector =
-                    Selector::parse("td.data").map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-                let name_selector =
-                    Selector::parse("td.data a").map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-                let link_selector =
-                    Selector::parse("td.data a").map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-                let uoc_selector = Selector::parse("td.data:nth-child(3)")
-                    .map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-                let mut visited_courses = HashSet::<String>::new();
-
-                for career_elem_ref in document.select(&career_selector) {
-                    let career = extract_text(career_elem_ref);
-                    if career.is_empty() {
-                        continue;
-                    };
-                    for row_node in ElementRef::wrap(
-                        career_elem_ref
-                            .parent()
-                            .ok_or_else(|| {
-                                anyhow::anyhow!(format!(
-                                    "{}: {}",
-                                    &error_msg, "Expected career to be inside td element"
-                                ))
-                            })?
-                            .next_sibling()
-                            .ok_or_else(|| {
-                                anyhow::anyhow!(format!(
-                                    "{}: {}",
-                                    &error_msg,
-                                    "Expected career classes td element to come after careers"
-                                ))
-                            })?
-                            .next_sibling()
-                            .ok_or_else(|| {
-                                anyhow::anyhow!(format!(
-                                    "{}: {}",
-                                    &error_msg,
-                                    "Expected career classes td element to come after careers"
-                                ))
-                            })?,
-                    )
-                    .ok_or_else(|| anyhow::anyhow!(error_msg.clone()))?
-                    .select(&row_selector)
-                    {
-                        // Extract data from each row
-                        let course_code = extract_text(
-                            row_node
-                                .select(&code_selector)
-                                .next()
-                                .ok_or_else(|| anyhow::anyhow!(error_msg.clone()))?,
-                        );
-                        let course_name = extract_text(
-                            row_node
-                                .select(&name_selector)
-                                .nth(1)
-                                .ok_or_else(|| anyhow::anyhow!(error_msg.clone()))?,
-                        );
-                        let name_hash = format!("{}{}", &course_code, &career);
-                        if visited_courses.contains(&name_hash) {
-                            continue;
-                        }
-                        visited_courses.insert(name_hash);
-                        let year_to_scrape = ctx
-                            .timetable_url_regex
-                            .extract_year(&url)
-                            .map_err(|_| anyhow::anyhow!(error_msg.clone()))?;
-                        let url_to_scrape_further = get_html_link_to_page(
-                            year_to_scrape,
-                            row_node
-                                .select(&link_selector)
-                                .next()
-                                .map_or("", |node| node.value().attr("href").unwrap_or("")),
-                            &ctx,
-                        );
-                        let uoc = extract_text(
-                            row_node
-                                .select(&uoc_selector)
-                                .next()
-                                .ok_or_else(|| anyhow::anyhow!(error_msg.clone()))?,
-                        )
-                        .parse()
-                        .context("Could not parse UOC!")?;
-
-                        let course_scraper = PartialCourse::new(
-                            course_code,
-                            course_name,
-                            career.trim().to_string(),
-                            uoc,
-                            url_to_scrape_further,
-                        );
-                        tx.send(course_scraper)?;
-                    }
-                }
-                Ok(())
-            };
-
-            // NOTE: tokio is, by default, not designed for long running cpu bound tasks to be spawned, since it's designed for doing blocking IO asyncronously. current bottleneck: we're doing heavy cpu bound work on e.g. 42 OS threads (example execution measured once), which creates some scheduling overhead -> either limit to num cpus OS threads, since we don't use spawn_blocking for blocking io anyways, or look for different tokio API.
-            tokio::task::spawn_blocking(cpu_bound).await??;
-            Ok(())
+            let span = Span::current();
+            tokio::task::spawn_blocking(move || {
+                let _entered = span.enter();
+                parse_partial_courses(&html, &url, &ctx, |partial_course| {
+                    tx.send(partial_course)
+                        .map_err(|_| anyhow::anyhow!("course tasks stopped receiving"))
+                })
+            })
         };
 
-        let mut consumer = async move || -> anyhow::Result<Vec<Course>> {
-            let mut tasks = tokio::task::JoinSet::new();
-
-            // Spawn partial-X-completion tasks as soon as we receive them.
-            while let Some(partial_course) = rx.recv().await {
-                let ctx = Arc::clone(ctx);
-                tasks.spawn(async move { partial_course.complete(&ctx).await });
-            }
-
-            // Wait for all partial-X-completion tasks to complete.
-            // If any of the tasks returns an error, return that error
-            // immediately (without waiting for all other tasks to finish).
-            let mut courses = Vec::new();
-            while let Some(result) = tasks.join_next().await {
-                let course = result??;
+        let mut tasks = JoinSet::new();
+        while let Some(partial_course) = rx.recv().await {
+            let ctx = Arc::clone(ctx);
+            tasks.spawn(async move { partial_course.complete(&ctx).await }.in_current_span());
+        }
+        producer.await??;
+
+        let mut courses = Vec::new();
+        while let Some(result) = tasks.join_next().await {
+            let course = result??;
+            if ctx.scrape_filter.matches_course(&course) {
                 courses.push(course);
             }
+        }
+        Ok(Self { courses })
+    }
+}
 
-            Ok(courses)
-        };
+/// Parses the courses listed on a subject area page, calling `emit` for each
+/// one that matches the scrape filter.
+pub fn parse_partial_courses(
+    html: &str,
+    url: &str,
+    ctx: &ScrapingContext,
+    mut emit: impl FnMut(PartialCourse) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let document = scraper::Html::parse_document(html);
+
+    let selectors = &ctx.selector_profile.subject_area;
+    let mut visited_courses = HashSet::<String>::new();
+
+    if document.select(&selectors.career).next().is_none() {
+        // Subject areas without any classes this year have no careers
+        // listed, so this isn't fatal. `canary` checks for the career
+        // headings instead.
+        ParseError::missing_selector(
+            ParseStage::SubjectArea,
+            url,
+            selectors.career.as_str(),
+            document.root_element(),
+        )
+        .warn();
+        return Ok(());
+    }
 
-        // Wait on producer and consumer.
-        let ((), courses) = tokio::try_join!(producer(), consumer())?;
+    for career_elem_ref in document.select(&selectors.career) {
+        let career = extract_text(career_elem_ref);
+        if career.is_empty() {
+            continue;
+        };
+        let career_row = career_elem_ref
+            .parent()
+            .and_then(ElementRef::wrap)
+            .unwrap_or(career_elem_ref);
+        let unexpected_structure = |description: &str| {
+            ParseError::in_element(
+                ParseStage::SubjectArea,
+                url,
+                Expectation::Structure(description.to_string()),
+                career_row,
+            )
+        };
+        for row_node in ElementRef::wrap(
+            career_elem_ref
+                .parent()
+                .ok_or_else(|| unexpected_structure("career to be inside a td element"))?
+                .next_sibling()
+                .ok_or_else(|| {
+                    unexpected_structure("career classes td element to come after careers")
+                })?
+                .next_sibling()
+                .ok_or_else(|| {
+                    unexpected_structure("career classes td element to come after careers")
+                })?,
+        )
+        .ok_or_else(|| unexpected_structure("career classes to be inside an element"))?
+        .select(&selectors.row)
+        {
+            let missing = |selector: &ProfileSelector| {
+                ParseError::missing_selector(
+                    ParseStage::SubjectArea,
+                    url,
+                    selector.as_str(),
+                    row_node,
+                )
+            };
 
-        Ok(Self { courses })
+            // Extract data from each row
+            let course_code = extract_text(
+                row_node
+                    .select(&selectors.code)
+                    .next()
+                    .ok_or_else(|| missing(&selectors.code))?,
+            );
+            let course_name = extract_text(
+                row_node
+                    .select(&selectors.name)
+                    .nth(1)
+                    .ok_or_else(|| missing(&selectors.name))?,
+            );
+            if !ctx
+                .scrape_filter
+                .matches_partial_course(&course_code, &career)
+            {
+                continue;
+            }
+            let name_hash = format!("{}{}", &course_code, &career);
+            if visited_courses.contains(&name_hash) {
+                continue;
+            }
+            visited_courses.insert(name_hash);
+            let year_to_scrape = ctx.timetable_url_regex.extract_year(url)?;
+            let url_to_scrape_further = get_html_link_to_page(
+                year_to_scrape,
+                row_node
+                    .select(&selectors.link)
+                    .next()
+                    .map_or("", |node| node.value().attr("href").unwrap_or("")),
+                ctx,
+            );
+            let uoc = extract_text(
+                row_node
+                    .select(&selectors.uoc)
+                    .next()
+                    .ok_or_else(|| missing(&selectors.uoc))?,
+            )
+            .parse()
+            .map_err(|_| {
+                ParseError::in_element(
+                    ParseStage::SubjectArea,
+                    url,
+                    Expectation::Structure(format!(
+                        "a whole number of UOC in '{}'",
+                        selectors.uoc.as_str()
+                    )),
+                    row_node,
+                )
+            })?;
+
+            let course_scraper = PartialCourse::new(
+                course_code,
+                course_name,
+                career.trim().to_string(),
+                uoc,
+                url_to_scrape_further,
+            );
+            emit(course_scraper)?;
+        }
     }
+    Ok(())
 }
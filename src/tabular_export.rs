@@ -0,0 +1,471 @@
+use std::{fs, fs::File, path::Path, sync::Arc};
+
+use anyhow::Context;
+use arrow_array::{
+    ArrayRef, Date32Array, Int32Array, RecordBatch, StringArray, TimestampMillisecondArray,
+    builder::{ListBuilder, StringBuilder},
+    types::Date32Type,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::{DateTime, NaiveDate, SecondsFormat};
+use chrono_tz::Tz;
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+
+use crate::{
+    Course,
+    occurrences::TIMEZONE,
+    rows::{
+        ClassRow, CourseRow, OccurrenceRow, TimeRow, class_rows, course_rows, occurrence_rows,
+        time_rows,
+    },
+};
+
+/// A table of rows in column order, so that each column can be written with
+/// its own type.
+struct Table {
+    name: &'static str,
+    columns: Vec<Column>,
+}
+
+struct Column {
+    name: &'static str,
+    values: Values,
+}
+
+enum Values {
+    Text(Vec<Option<String>>),
+    Int(Vec<Option<i32>>),
+    Date(Vec<Option<NaiveDate>>),
+    Timestamp(Vec<Option<DateTime<Tz>>>),
+    List(Vec<Vec<String>>),
+}
+
+impl Column {
+    fn text<R>(name: &'static str, rows: &[R], f: impl Fn(&R) -> Option<&str>) -> Self {
+        let values = rows.iter().map(|row| f(row).map(str::to_string)).collect();
+        Self {
+            name,
+            values: Values::Text(values),
+        }
+    }
+
+    fn int<R>(name: &'static str, rows: &[R], f: impl Fn(&R) -> Option<i32>) -> Self {
+        Self {
+            name,
+            values: Values::Int(rows.iter().map(f).collect()),
+        }
+    }
+
+    fn date<R>(name: &'static str, rows: &[R], f: impl Fn(&R) -> Option<NaiveDate>) -> Self {
+        Self {
+            name,
+            values: Values::Date(rows.iter().map(f).collect()),
+        }
+    }
+
+    fn timestamp<R>(
+        name: &'static str,
+        rows: &[R],
+        f: impl Fn(&R) -> Option<DateTime<Tz>>,
+    ) -> Self {
+        Self {
+            name,
+            values: Values::Timestamp(rows.iter().map(f).collect()),
+        }
+    }
+
+    fn list<R>(name: &'static str, rows: &[R], f: impl Fn(&R) -> &[String]) -> Self {
+        Self {
+            name,
+            values: Values::List(rows.iter().map(|row| f(row).to_vec()).collect()),
+        }
+    }
+}
+
+fn courses_table(rows: &[CourseRow]) -> Table {
+    Table {
+        name: "courses",
+        columns: vec![
+            Column::text("course_id", rows, |r| Some(r.course_id)),
+            Column::text("course_code", rows, |r| Some(r.course_code)),
+            Column::text("course_name", rows, |r| Some(r.course_name)),
+            Column::int("uoc", rows, |r| Some(r.uoc)),
+            Column::text("faculty", rows, |r| r.faculty),
+            Column::text("school", rows, |r| r.school),
+            Column::text("campus", rows, |r| r.campus),
+            Column::text("career", rows, |r| r.career),
+            Column::list("terms", rows, |r| r.terms),
+            Column::list("modes", rows, |r| r.modes),
+        ],
+    }
+}
+
+/// The enrolment, offering period, meeting dates and census date are kept as
+/// the website's text, followed by typed columns which are empty where that
+/// text couldn't be parsed, e.g. `Canc`.
+fn classes_table(rows: &[ClassRow]) -> Table {
+    Table {
+        name: "classes",
+        columns: vec![
+            Column::text("class_id", rows, |r| Some(r.class_id)),
+            Column::text("career", rows, |r| Some(r.career)),
+            Column::text("course_id", rows, |r| Some(r.course_id)),
+            Column::text("section", rows, |r| Some(r.section)),
+            Column::text("term", rows, |r| Some(r.term)),
+            Column::text("activity", rows, |r| Some(r.activity)),
+            Column::int("year", rows, |r| r.year.parse().ok()),
+            Column::text("status", rows, |r| Some(r.status)),
+            Column::text("course_enrolment", rows, |r| Some(r.course_enrolment)),
+            Column::int("enrolled", rows, |r| {
+                r.enrolment().map(|(enrolled, _)| enrolled)
+            }),
+            Column::int("capacity", rows, |r| {
+                r.enrolment().map(|(_, capacity)| capacity)
+            }),
+            Column::text("offering_period", rows, |r| Some(r.offering_period)),
+            Column::date("offering_start", rows, |r| {
+                r.offering_period().map(|(start, _)| start)
+            }),
+            Column::date("offering_end", rows, |r| {
+                r.offering_period().map(|(_, end)| end)
+            }),
+            Column::text("meeting_dates", rows, |r| Some(r.meeting_dates)),
+            Column::date("meeting_start", rows, |r| {
+                r.meeting_dates().map(|(start, _)| start)
+            }),
+            Column::date("meeting_end", rows, |r| {
+                r.meeting_dates().map(|(_, end)| end)
+            }),
+            Column::text("census_date", rows, |r| Some(r.census_date)),
+            Column::date("census", rows, ClassRow::census_date),
+            Column::text("consent", rows, |r| Some(r.consent)),
+            Column::text("mode", rows, |r| Some(r.mode)),
+            Column::text("class_notes", rows, |r| r.class_notes),
+        ],
+    }
+}
+
+fn times_table(rows: &[TimeRow]) -> Table {
+    Table {
+        name: "times",
+        columns: vec![
+            Column::text("id", rows, |r| Some(&r.id)),
+            Column::text("class_id", rows, |r| Some(r.class_id)),
+            Column::text("career", rows, |r| Some(r.career)),
+            Column::text("day", rows, |r| Some(r.day)),
+            Column::text("instructor", rows, |r| r.instructor),
+            Column::text("location", rows, |r| Some(r.location)),
+            Column::text("time", rows, |r| Some(r.time)),
+            Column::text("weeks", rows, |r| Some(r.weeks)),
+        ],
+    }
+}
+
+fn occurrences_table(rows: &[OccurrenceRow]) -> Table {
+    Table {
+        name: "occurrences",
+        columns: vec![
+            Column::text("id", rows, |r| Some(&r.id)),
+            Column::text("time_id", rows, |r| Some(&r.time_id)),
+            Column::text("class_id", rows, |r| Some(r.class_id)),
+            Column::int("week", rows, |r| i32::try_from(r.week).ok()),
+            Column::date("date", rows, |r| Some(r.date)),
+            Column::timestamp("starts_at", rows, |r| Some(r.starts_at)),
+            Column::timestamp("ends_at", rows, |r| Some(r.ends_at)),
+            Column::text("location", rows, |r| Some(r.location)),
+        ],
+    }
+}
+
+fn tables(courses: &[Course], with_occurrences: bool) -> Vec<Table> {
+    let mut tables = vec![
+        courses_table(&course_rows(courses).collect::<Vec<_>>()),
+        classes_table(&class_rows(courses).collect::<Vec<_>>()),
+        times_table(&time_rows(courses).collect::<Vec<_>>()),
+    ];
+    if with_occurrences {
+        tables.push(occurrences_table(&occurrence_rows(courses)));
+    }
+    tables
+}
+
+impl Table {
+    fn len(&self) -> usize {
+        self.columns
+            .first()
+            .map_or(0, |column| match &column.values {
+                Values::Text(values) => values.len(),
+                Values::Int(values) => values.len(),
+                Values::Date(values) => values.len(),
+                Values::Timestamp(values) => values.len(),
+                Values::List(values) => values.len(),
+            })
+    }
+
+    fn write_csv(&self, path: &Path) -> anyhow::Result<()> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(self.columns.iter().map(|column| column.name))?;
+        for i in 0..self.len() {
+            let record = self
+                .columns
+                .iter()
+                .map(|column| column.values.csv_field(i))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            writer.write_record(record)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn write_parquet(&self, path: &Path) -> anyhow::Result<()> {
+        let schema = Arc::new(Schema::new(
+            self.columns
+                .iter()
+                .map(|column| Field::new(column.name, column.values.data_type(), true))
+                .collect::<Vec<_>>(),
+        ));
+        let arrays = self
+            .columns
+            .iter()
+            .map(|column| column.values.to_array())
+            .collect();
+        let batch = RecordBatch::try_new(Arc::clone(&schema), arrays)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer = ArrowWriter::try_new(File::create(path)?, schema, Some(properties))?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+}
+
+impl Values {
+    /// Lists are written as JSON arrays, the same as in the SQLite export.
+    fn csv_field(&self, i: usize) -> anyhow::Result<String> {
+        Ok(match self {
+            Values::Text(values) => values[i].clone().unwrap_or_default(),
+            Values::Int(values) => values[i].map(|value| value.to_string()).unwrap_or_default(),
+            Values::Date(values) => values[i]
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            Values::Timestamp(values) => values[i]
+                .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, false))
+                .unwrap_or_default(),
+            Values::List(values) => serde_json::to_string(&values[i])?,
+        })
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            Values::Text(_) => DataType::Utf8,
+            Values::Int(_) => DataType::Int32,
+            Values::Date(_) => DataType::Date32,
+            Values::Timestamp(_) => {
+                DataType::Timestamp(TimeUnit::Millisecond, Some(TIMEZONE.name().into()))
+            }
+            Values::List(_) => {
+                DataType::List(Arc::new(Field::new_list_field(DataType::Utf8, true)))
+            }
+        }
+    }
+
+    fn to_array(&self) -> ArrayRef {
+        match self {
+            Values::Text(values) => Arc::new(StringArray::from(values.clone())),
+            Values::Int(values) => Arc::new(Int32Array::from(values.clone())),
+            Values::Date(values) => Arc::new(Date32Array::from(
+                values
+                    .iter()
+                    .map(|date| date.map(Date32Type::from_naive_date))
+                    .collect::<Vec<_>>(),
+            )),
+            Values::Timestamp(values) => Arc::new(
+                TimestampMillisecondArray::from(
+                    values
+                        .iter()
+                        .map(|time| time.map(|time| time.timestamp_millis()))
+                        .collect::<Vec<_>>(),
+                )
+                .with_timezone(TIMEZONE.name()),
+            ),
+            Values::List(values) => {
+                let mut builder = ListBuilder::new(StringBuilder::new());
+                for list in values {
+                    for value in list {
+                        builder.values().append_value(value);
+                    }
+                    builder.append(true);
+                }
+                Arc::new(builder.finish())
+            }
+        }
+    }
+}
+
+/// Writes `courses.csv`, `classes.csv` and `times.csv` (and `occurrences.csv`
+/// if `with_occurrences`) into `dir`, creating it if needed.
+pub fn export_csv(dir: &Path, courses: &[Course], with_occurrences: bool) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    for table in tables(courses, with_occurrences) {
+        let path = dir.join(format!("{}.csv", table.name));
+        table
+            .write_csv(&path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Writes `courses.parquet`, `classes.parquet` and `times.parquet` (and
+/// `occurrences.parquet` if `with_occurrences`) into `dir`, creating it if
+/// needed.
+pub fn export_parquet(
+    dir: &Path,
+    courses: &[Course],
+    with_occurrences: bool,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    for table in tables(courses, with_occurrences) {
+        let path = dir.join(format!("{}.parquet", table.name));
+        table
+            .write_parquet(&path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Class, Time,
+        test_fixtures::{class, course, time},
+    };
+    use arrow_array::{Array, ListArray, cast::AsArray, types::Int32Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn courses() -> Vec<Course> {
+        // The comma in the location has to be quoted in the CSV.
+        let time = Time {
+            location: "CLB 7, K-E19-G05".to_string(),
+            weeks: "1-5,7-10".to_string(),
+            ..time("Mon", "09:00 - 11:00")
+        };
+        let class = Class {
+            census_date: "Unknown".to_string(),
+            ..class("COMP1511", "A", "T1", vec![time])
+        };
+        vec![Course {
+            faculty: Some("Faculty of Engineering".to_string()),
+            terms: vec!["T1".to_string(), "T3".to_string()],
+            ..course("COMP1511", vec![class])
+        }]
+    }
+
+    fn temp_dir() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("spooderman-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let dir = temp_dir();
+        export_csv(&dir, &courses(), true).unwrap();
+
+        let courses_csv = fs::read_to_string(dir.join("courses.csv")).unwrap();
+        assert_eq!(
+            courses_csv,
+            "course_id,course_code,course_name,uoc,faculty,school,campus,career,terms,modes\n\
+             COMP1511Undergraduate,COMP1511,COMP1511 course,6,Faculty of Engineering,,Sydney,Undergraduate,\"[\"\"T1\"\",\"\"T3\"\"]\",\"[\"\"In Person\"\"]\"\n"
+        );
+        let classes_csv = fs::read_to_string(dir.join("classes.csv")).unwrap();
+        assert_eq!(
+            classes_csv.lines().nth(1).unwrap(),
+            "COMP1511Undergraduate-A-T1-2025,Undergraduate,COMP1511Undergraduate,A,T1,Lecture,2025,Open,20/24,20,24,17/02/2025 - 18/05/2025,2025-02-17,2025-05-18,17/02/2025 - 18/05/2025,2025-02-17,2025-05-18,Unknown,,Not Required,In Person,"
+        );
+        let times_csv = fs::read_to_string(dir.join("times.csv")).unwrap();
+        assert_eq!(
+            times_csv.lines().nth(1).unwrap(),
+            "\"COMP1511Undergraduate-A-T1-2025MonCLB 7, K-E19-G0509:00 - 11:001-5,7-10\",COMP1511Undergraduate-A-T1-2025,Undergraduate,Mon,,\"CLB 7, K-E19-G05\",09:00 - 11:00,\"1-5,7-10\""
+        );
+        let occurrences_csv = fs::read_to_string(dir.join("occurrences.csv")).unwrap();
+        assert_eq!(occurrences_csv.lines().count(), 10);
+        assert_eq!(
+            occurrences_csv.lines().nth(1).unwrap(),
+            "\"COMP1511Undergraduate-A-T1-2025MonCLB 7, K-E19-G0509:00 - 11:001-5,7-102025-02-17\",\"COMP1511Undergraduate-A-T1-2025MonCLB 7, K-E19-G0509:00 - 11:001-5,7-10\",COMP1511Undergraduate-A-T1-2025,1,2025-02-17,2025-02-17T09:00:00+11:00,2025-02-17T11:00:00+11:00,\"CLB 7, K-E19-G05\""
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_parquet() {
+        let dir = temp_dir();
+        export_parquet(&dir, &courses(), false).unwrap();
+        assert!(!dir.join("occurrences.parquet").exists());
+        export_parquet(&dir, &courses(), true).unwrap();
+
+        let read = |table: &str| {
+            let file = File::open(dir.join(format!("{table}.parquet"))).unwrap();
+            let mut batches = ParquetRecordBatchReaderBuilder::try_new(file)
+                .unwrap()
+                .build()
+                .unwrap();
+            let batch = batches.next().unwrap().unwrap();
+            assert!(batches.next().is_none());
+            batch
+        };
+
+        let courses = read("courses");
+        assert_eq!(courses.num_rows(), 1);
+        let uoc = courses.column_by_name("uoc").unwrap();
+        assert_eq!(uoc.as_primitive::<Int32Type>().value(0), 6);
+        let terms = courses.column_by_name("terms").unwrap();
+        let terms = terms.as_any().downcast_ref::<ListArray>().unwrap().value(0);
+        assert_eq!(
+            terms.as_string::<i32>().iter().collect::<Vec<_>>(),
+            [Some("T1"), Some("T3")]
+        );
+        assert!(courses.column_by_name("school").unwrap().is_null(0));
+
+        let classes = read("classes");
+        let capacity = classes.column_by_name("capacity").unwrap();
+        assert_eq!(capacity.as_primitive::<Int32Type>().value(0), 24);
+        let offering_start = classes.column_by_name("offering_start").unwrap();
+        assert_eq!(
+            offering_start
+                .as_any()
+                .downcast_ref::<Date32Array>()
+                .unwrap()
+                .value_as_date(0),
+            NaiveDate::from_ymd_opt(2025, 2, 17)
+        );
+        assert_eq!(
+            classes
+                .column_by_name("census_date")
+                .unwrap()
+                .as_string::<i32>()
+                .value(0),
+            "Unknown"
+        );
+        assert!(classes.column_by_name("census").unwrap().is_null(0));
+
+        assert_eq!(read("times").num_rows(), 1);
+
+        let occurrences = read("occurrences");
+        assert_eq!(occurrences.num_rows(), 9);
+        let starts_at = occurrences.column_by_name("starts_at").unwrap();
+        let starts_at = starts_at
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+            .unwrap();
+        assert_eq!(starts_at.timezone(), Some("Australia/Sydney"));
+        assert_eq!(
+            starts_at.value_as_datetime(0),
+            NaiveDate::from_ymd_opt(2025, 2, 16)
+                .unwrap()
+                .and_hms_opt(22, 0, 0)
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+//! Courses, classes and times for unit tests.
+//!
+//! The builders describe undergraduate classes in 2025, so a test only
+//! overrides the fields it checks, e.g.
+//! `Class { activity: "Tutorial".to_string(), ..class("COMP1511", "T14A", "T1", times) }`.
+
+use crate::{Class, Course, Time};
+
+/// An undergraduate course offered in person, in the terms of its classes.
+pub(crate) fn course(course_code: &str, classes: Vec<Class>) -> Course {
+    let mut terms = classes
+        .iter()
+        .map(|class| class.term.clone())
+        .collect::<Vec<_>>();
+    terms.sort();
+    terms.dedup();
+    Course {
+        course_id: format!("{}Undergraduate", course_code),
+        course_code: course_code.to_string(),
+        course_name: format!("{} course", course_code),
+        uoc: 6,
+        faculty: None,
+        school: None,
+        career: Some("Undergraduate".to_string()),
+        modes: vec!["In Person".to_string()],
+        campus: Some("Sydney".to_string()),
+        terms,
+        classes,
+    }
+}
+
+/// An open, in person lecture of the undergraduate course `course_code`,
+/// running for the whole of `term` in 2025.
+pub(crate) fn class(course_code: &str, section: &str, term: &str, times: Vec<Time>) -> Class {
+    let (offering_period, census_date) = match term {
+        "T1" => ("17/02/2025 - 18/05/2025", "17/03/2025"),
+        "T2" => ("02/06/2025 - 17/08/2025", "30/06/2025"),
+        "T3" => ("15/09/2025 - 14/12/2025", "13/10/2025"),
+        _ => panic!("no fixture dates for term {}", term),
+    };
+    Class {
+        course_id: format!("{}Undergraduate", course_code),
+        career: "Undergraduate".to_string(),
+        class_id: format!("{}Undergraduate-{}-{}-2025", course_code, section, term),
+        section: section.to_string(),
+        term: term.to_string(),
+        year: "2025".to_string(),
+        activity: "Lecture".to_string(),
+        status: "Open".to_string(),
+        course_enrolment: "20/24".to_string(),
+        offering_period: offering_period.to_string(),
+        meeting_dates: offering_period.to_string(),
+        census_date: census_date.to_string(),
+        consent: "Not Required".to_string(),
+        mode: "In Person".to_string(),
+        times: Some(times),
+        class_notes: None,
+    }
+}
+
+/// A meeting in the Quadrangle in weeks 1 to 10, without an instructor.
+pub(crate) fn time(day: &str, time: &str) -> Time {
+    Time {
+        career: "Undergraduate".to_string(),
+        day: day.to_string(),
+        time: time.to_string(),
+        location: "Quadrangle G040 (K-E15-G040)".to_string(),
+        weeks: "1-10".to_string(),
+        instructor: None,
+    }
+}
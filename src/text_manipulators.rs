@@ -9,3 +9,14 @@ pub fn extract_text(node: ElementRef) -> String {
 pub fn get_html_link_to_page(year: Year, html_fragment: &str, ctx: &ScrapingContext) -> String {
     ctx.scraping_config.get_timetable_api_url_for_year(year) + html_fragment
 }
+
+/// Subject codes are the first 4 letters of a course code, e.g. `COMP` for `COMP1511`.
+pub fn subject_code(course_code: &str) -> Option<&str> {
+    course_code.get(..4)
+}
+
+/// Course codes are 4 letters and 4 digits, and course and class ids start
+/// with theirs, e.g. `COMP1511` for `COMP1511Undergraduate-1001-T1-2025`.
+pub fn course_code_of(id: &str) -> Option<&str> {
+    id.get(..8)
+}
@@ -1,4 +1,33 @@
-use std::time::{Duration, Instant};
+use std::{
+    io::Write,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+/// How long a stage timed by `log_execution_time` or `log_execution_time_async` took.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub seconds: f64,
+}
+
+static STAGE_TIMINGS: Mutex<Vec<StageTiming>> = Mutex::new(Vec::new());
+
+/// All stages timed so far in this process, in the order they finished.
+pub fn stage_timings() -> Vec<StageTiming> {
+    STAGE_TIMINGS.lock().unwrap().clone()
+}
+
+fn log_and_record_execution_time(op_description: &str, elapsed_time: Duration) {
+    let elapsed_time_human_readable: humantime::Duration = elapsed_time.into();
+    log::info!("{} took {}", op_description, elapsed_time_human_readable);
+    STAGE_TIMINGS.lock().unwrap().push(StageTiming {
+        stage: op_description.to_string(),
+        seconds: elapsed_time.as_secs_f64(),
+    });
+}
 
 pub fn sort_by_key_ref<T, B, F>(slice: &mut [T], mut f: F)
 where
@@ -8,6 +37,33 @@ where
     slice.sort_by(|a, b| f(a).cmp(f(b)))
 }
 
+/// Writes `rows` as lines of columns padded to the same width, each line
+/// starting with `indent`. The last column isn't padded.
+pub(crate) fn write_aligned<const N: usize>(
+    writer: &mut impl Write,
+    rows: &[[String; N]],
+    indent: &str,
+) -> std::io::Result<()> {
+    let mut widths = [0; N];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in rows {
+        let mut line = indent.to_string();
+        for (i, (cell, width)) in row.iter().zip(widths).enumerate() {
+            if i + 1 == N {
+                line.push_str(cell);
+            } else {
+                line.push_str(&format!("{:<width$}  ", cell, width = width));
+            }
+        }
+        writeln!(writer, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
 /// Executes f and measures how long it took.
 async fn measure_async<F, Fut, T>(f: F) -> (T, Duration)
 where
@@ -31,25 +87,23 @@ where
     (res, elapsed)
 }
 
-/// Executes f and measures how long it took.
+/// Executes f, and logs and records (see `stage_timings`) how long it took.
 pub async fn log_execution_time_async<F, Fut, T>(op_description: &str, f: F) -> T
 where
     F: FnOnce() -> Fut,
     Fut: Future<Output = T>,
 {
     let (res, elapsed_time) = measure_async(f).await;
-    let elapsed_time_human_readable: humantime::Duration = elapsed_time.into();
-    log::info!("{} took {}", op_description, elapsed_time_human_readable);
+    log_and_record_execution_time(op_description, elapsed_time);
     res
 }
 
-/// Executes f and measures how long it took.
+/// Executes f, and logs and records (see `stage_timings`) how long it took.
 pub fn log_execution_time<F, T>(op_description: &str, f: F) -> T
 where
     F: FnOnce() -> T,
 {
     let (res, elapsed_time) = measure(f);
-    let elapsed_time_human_readable: humantime::Duration = elapsed_time.into();
-    log::info!("{} took {}", op_description, elapsed_time_human_readable);
+    log_and_record_execution_time(op_description, elapsed_time);
     res
 }
@@ -0,0 +1,74 @@
+use chrono::Datelike;
+use parse_display::FromStr;
+
+use crate::{ScrapingContext, Year};
+
+#[derive(Debug, Clone, Copy, FromStr)]
+pub enum YearToScrape {
+    #[display("latest-with-data")]
+    LatestYearWithDataAvailable,
+
+    #[display("{0}")]
+    Year(Year),
+}
+
+fn get_current_year() -> Year {
+    chrono::Utc::now().year()
+}
+
+async fn year_has_data(year: Year, ctx: &ScrapingContext) -> anyhow::Result<bool> {
+    let year_url = ctx.scraping_config.get_timetable_api_url_for_year(year);
+    let status = ctx.fetch_url_status(&year_url).await?;
+    // UNSW servers will return a 404 if the data for a year isn't available.
+    match status.as_u16() {
+        200 => Ok(true),
+        404 => Ok(false),
+        other => Err(anyhow::anyhow!(
+            "UNSW servers returned an unexpected status code '{}' for a GET request to '{}'",
+            other,
+            year_url
+        )),
+    }
+}
+
+impl YearToScrape {
+    pub async fn resolve_year(&self, ctx: &ScrapingContext) -> anyhow::Result<Year> {
+        match self {
+            YearToScrape::Year(year) => Ok(*year),
+            YearToScrape::LatestYearWithDataAvailable => {
+                // try to find the latest year in the future, the the latest in the past.
+
+                // How far we potentially look into the future and past.
+                const MAX_FUTURE_YEARS: i32 = 20;
+                const MAX_PAST_YEARS: i32 = 20;
+
+                let curr_year = get_current_year();
+
+                // go as far as possible into future.
+                let mut latest_in_future = None;
+                for year in curr_year..curr_year + MAX_FUTURE_YEARS {
+                    if year_has_data(year, ctx).await? {
+                        latest_in_future = Some(year);
+                    } else {
+                        break;
+                    }
+                }
+                if let Some(year) = latest_in_future {
+                    return Ok(year);
+                }
+
+                // go until first possible in the past.
+                // we've already checked the current year.
+                for year in (curr_year - MAX_PAST_YEARS..curr_year).rev() {
+                    if year_has_data(year, ctx).await? {
+                        return Ok(year);
+                    }
+                }
+
+                Err(anyhow::anyhow!(
+                    "no year (neither in the future nor in the past relative to current year) has data"
+                ))
+            }
+        }
+    }
+}
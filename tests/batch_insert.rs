@@ -0,0 +1,196 @@
+//! Contract tests for uploading to Hasuragres, against a local mock of its
+//! `/batch_insert` endpoint (see `support::mock_hasuragres`).
+
+mod support;
+
+use std::fs;
+
+use serde_json::{Value, json};
+use spooderman::{ReadFromMemory, UploadOutcome, send_batch_data_with_config};
+use support::mock_hasuragres::{MockHasuragres, Scripted};
+
+const API_KEY: &str = "test_key";
+
+fn data() -> ReadFromMemory {
+    ReadFromMemory {
+        courses_vec: vec![json!({
+            "course_id": "COMP1511Undergraduate",
+            "course_code": "COMP1511",
+            "course_name": "Programming Fundamentals",
+            "uoc": 6,
+            "faculty": "Faculty of Engineering",
+            "school": "School of Computer Science and Engineering",
+            "campus": "Sydney",
+            "career": "Undergraduate",
+            "terms": "[\"T1\"]",
+            "modes": ["In Person"],
+        })],
+        classes_vec: vec![json!({
+            "course_id": "COMP1511Undergraduate",
+            "class_id": "COMP1511-1234-T1-Undergraduate",
+            "section": "A",
+            "term": "T1",
+            "career": "Undergraduate",
+            "year": "2025",
+            "activity": "Lecture",
+            "status": "Open",
+            "course_enrolment": "400/450",
+            "offering_period": "17/02/2025 - 18/05/2025",
+            "meeting_dates": "17/02/2025 - 18/05/2025",
+            "census_date": "17/03/2025",
+            "consent": "Not Required",
+            "mode": "In Person",
+            "class_notes": "",
+        })],
+        times_vec: vec![json!({
+            "id": "COMP1511-1234-T1-UndergraduateMonCLB 7 (K-E19-G05)09:00 - 11:001-5,7-10",
+            "class_id": "COMP1511-1234-T1-Undergraduate",
+            "career": "Undergraduate",
+            "day": "Mon",
+            "instructor": "Dr A Smith",
+            "location": "CLB 7 (K-E19-G05)",
+            "time": "09:00 - 11:00",
+            "weeks": "1-5,7-10",
+        })],
+        occurrences_vec: None,
+    }
+}
+
+#[tokio::test]
+async fn uploads_every_table() {
+    let mock = MockHasuragres::start(API_KEY).await;
+    let data = data();
+    let outcome = send_batch_data_with_config(&data, &mock.uploading_config(API_KEY))
+        .await
+        .unwrap();
+    assert_eq!(outcome, UploadOutcome::Ok);
+
+    let batches = mock.batches();
+    assert_eq!(batches.len(), 1);
+    let tables = batches[0]
+        .iter()
+        .map(|batch| batch.metadata.table_name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(tables, ["courses", "classes", "times"]);
+
+    for (batch, (dir, rows)) in batches[0].iter().zip([
+        ("Courses", &data.courses_vec),
+        ("Classes", &data.classes_vec),
+        ("Times", &data.times_vec),
+    ]) {
+        let metadata = &batch.metadata;
+        assert_eq!(
+            metadata.sql_up,
+            fs::read_to_string(format!("sql/{}/up.sql", dir)).unwrap()
+        );
+        assert_eq!(
+            metadata.sql_down,
+            fs::read_to_string(format!("sql/{}/down.sql", dir)).unwrap()
+        );
+        assert_eq!(metadata.write_mode.as_deref(), Some("overwrite"));
+        let payload = batch
+            .payload
+            .iter()
+            .cloned()
+            .map(Value::Object)
+            .collect::<Vec<_>>();
+        assert_eq!(&payload, rows);
+    }
+}
+
+#[tokio::test]
+async fn uploads_occurrences_after_times_if_present() {
+    let mock = MockHasuragres::start(API_KEY).await;
+    let mut data = data();
+    let occurrence = json!({
+        "id": "COMP1511-1234-T1-UndergraduateMonCLB 7 (K-E19-G05)09:00 - 11:001-5,7-102025-02-17",
+        "time_id": "COMP1511-1234-T1-UndergraduateMonCLB 7 (K-E19-G05)09:00 - 11:001-5,7-10",
+        "class_id": "COMP1511-1234-T1-Undergraduate",
+        "week": 1,
+        "date": "2025-02-17",
+        "starts_at": "2025-02-17T09:00:00+11:00",
+        "ends_at": "2025-02-17T11:00:00+11:00",
+        "location": "CLB 7 (K-E19-G05)",
+    });
+    data.occurrences_vec = Some(vec![occurrence.clone()]);
+    send_batch_data_with_config(&data, &mock.uploading_config(API_KEY))
+        .await
+        .unwrap();
+
+    let batches = mock.batches();
+    let tables = batches[0]
+        .iter()
+        .map(|batch| batch.metadata.table_name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(tables, ["courses", "classes", "times", "occurrences"]);
+    let occurrences = &batches[0][3];
+    assert_eq!(
+        occurrences.metadata.sql_up,
+        fs::read_to_string("sql/Occurrences/up.sql").unwrap()
+    );
+    assert_eq!(
+        occurrences.payload,
+        [occurrence.as_object().unwrap().clone()]
+    );
+}
+
+// Failed inserts are logged and returned as the outcome rather than as an
+// error, so these check the outcome and that nothing was inserted.
+
+#[tokio::test]
+async fn rows_must_match_the_declared_columns() {
+    let mock = MockHasuragres::start(API_KEY).await;
+    let mut data = data();
+    data.times_vec[0].as_object_mut().unwrap().remove("weeks");
+    let outcome = send_batch_data_with_config(&data, &mock.uploading_config(API_KEY))
+        .await
+        .unwrap();
+    assert_eq!(outcome, UploadOutcome::Rejected);
+    assert!(mock.batches().is_empty());
+}
+
+#[tokio::test]
+async fn wrong_api_key_is_an_http_error() {
+    let mock = MockHasuragres::start(API_KEY).await;
+    let outcome = send_batch_data_with_config(&data(), &mock.uploading_config("wrong_key"))
+        .await
+        .unwrap();
+    assert_eq!(outcome, UploadOutcome::HttpError);
+    assert!(mock.batches().is_empty());
+}
+
+#[tokio::test]
+async fn failed_table_is_rejected() {
+    let mock = MockHasuragres::start(API_KEY).await;
+    mock.script(Scripted::FailTable {
+        table: "classes".to_string(),
+        error: "duplicate key value violates unique constraint".to_string(),
+    });
+    let outcome = send_batch_data_with_config(&data(), &mock.uploading_config(API_KEY))
+        .await
+        .unwrap();
+    assert_eq!(outcome, UploadOutcome::Rejected);
+    assert!(mock.batches().is_empty());
+}
+
+#[tokio::test]
+async fn invalid_json_response_is_an_invalid_response() {
+    let mock = MockHasuragres::start(API_KEY).await;
+    mock.script(Scripted::Raw {
+        status: "200 OK".to_string(),
+        body: "<html>Bad Gateway</html>".to_string(),
+    });
+    let outcome = send_batch_data_with_config(&data(), &mock.uploading_config(API_KEY))
+        .await
+        .unwrap();
+    assert_eq!(outcome, UploadOutcome::InvalidResponse);
+}
+
+#[tokio::test]
+async fn unreachable_server_is_unreachable() {
+    let mock = MockHasuragres::start(API_KEY).await;
+    let config = mock.uploading_config(API_KEY);
+    drop(mock);
+    let outcome = send_batch_data_with_config(&data(), &config).await.unwrap();
+    assert_eq!(outcome, UploadOutcome::Unreachable);
+}
@@ -0,0 +1,365 @@
+//! End-to-end scraper tests against a local mock of the timetable website
+//! (see `support::mock_timetable`), covering the request client, rate limiter
+//! year resolution and the streaming scrape API without touching UNSW servers.
+
+mod support;
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::Datelike;
+use futures::StreamExt;
+use spooderman::{
+    RateLimitPolicy, SchoolArea, ScrapeCancelled, ScrapeEvent, ScrapeFilter, ScrapeProgress,
+    ScrapingContext, SkippedPage, SubjectArea, Year, YearToScrape, scrape_all_courses, scrape_year,
+};
+use support::mock_timetable::{Fault, MockTimetable};
+use tokio_util::sync::CancellationToken;
+
+const YEAR: Year = 2025;
+
+/// The course pages in the fixture tree.
+const COURSE_PAGES: [&str; 5] = [
+    "ACCT1501.html",
+    "COMP1511.html",
+    "COMP3311.html",
+    "COMP9021.html",
+    "MATH1131.html",
+];
+
+fn ctx(mock: &MockTimetable, scrape_filter: ScrapeFilter) -> Arc<ScrapingContext> {
+    let rate_limit_policy = RateLimitPolicy {
+        // Keep retries after injected faults quick.
+        pause_after_rate_change: Duration::from_millis(100),
+        ..RateLimitPolicy::default()
+    };
+    let ctx = ScrapingContext::builder()
+        .timetable_api_url(mock.timetable_api_url())
+        .scrape_filter(scrape_filter)
+        .rate_limit_policy(rate_limit_policy)
+        .build()
+        .unwrap();
+    Arc::new(ctx)
+}
+
+async fn scrape_course_ids(mock: &MockTimetable, scrape_filter: ScrapeFilter) -> Vec<String> {
+    let ctx = ctx(mock, scrape_filter);
+    let (_, courses) = scrape_all_courses(YearToScrape::Year(YEAR), ctx, CancellationToken::new())
+        .await
+        .unwrap();
+    courses.into_iter().map(|course| course.course_id).collect()
+}
+
+fn all_course_ids() -> Vec<String> {
+    [
+        "ACCT1501Undergraduate",
+        "COMP1511Undergraduate",
+        "COMP3311Postgraduate",
+        "COMP3311Undergraduate",
+        "COMP9021Postgraduate",
+        "MATH1131Undergraduate",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn current_year() -> Year {
+    chrono::Utc::now().year()
+}
+
+#[tokio::test]
+async fn full_crawl() {
+    let mock = MockTimetable::start([YEAR]).await;
+    assert_eq!(
+        scrape_course_ids(&mock, ScrapeFilter::default()).await,
+        all_course_ids()
+    );
+    for page in ["", "ACCTKENS.html", "COMPKENS.html", "MATHKENS.html"] {
+        assert_eq!(mock.request_count(&format!("/{YEAR}/{page}")), 1);
+    }
+    // COMP3311 is offered to both careers, so its page is fetched for each.
+    for page in COURSE_PAGES {
+        let expected = if page == "COMP3311.html" { 2 } else { 1 };
+        assert_eq!(mock.request_count(&format!("/{YEAR}/{page}")), expected);
+    }
+}
+
+#[tokio::test]
+#[allow(deprecated)]
+async fn school_area_scrape_groups_courses_by_subject_area() {
+    let mock = MockTimetable::start([YEAR]).await;
+    let ctx = ctx(&mock, ScrapeFilter::default());
+    let school_area = SchoolArea::scrape(mock.page_url(YEAR, ""), &ctx)
+        .await
+        .unwrap();
+    let mut subject_codes = school_area
+        .pages
+        .iter()
+        .map(|page| (page.subject_code.as_str(), page.subject_area.courses.len()))
+        .collect::<Vec<_>>();
+    subject_codes.sort();
+    assert_eq!(subject_codes, [("ACCT", 1), ("COMP", 4), ("MATH", 1)]);
+    let mut course_ids = school_area
+        .get_all_courses()
+        .map(|course| course.course_id)
+        .collect::<Vec<_>>();
+    course_ids.sort();
+    assert_eq!(course_ids, all_course_ids());
+
+    let subject_area = SubjectArea::scrape(mock.page_url(YEAR, "MATHKENS.html"), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(subject_area.courses[0].course_id, "MATH1131Undergraduate");
+}
+
+#[tokio::test]
+async fn crawl_with_latency() {
+    let mock = MockTimetable::start([YEAR]).await;
+    mock.set_latency(Duration::from_millis(200));
+    assert_eq!(
+        scrape_course_ids(&mock, ScrapeFilter::default()).await,
+        all_course_ids()
+    );
+}
+
+#[tokio::test]
+async fn filtered_crawl_only_fetches_needed_pages() {
+    let mock = MockTimetable::start([YEAR]).await;
+    let filter = ScrapeFilter::new(&[], &["COMP1511".to_string()], &[], &[], &[]);
+    assert_eq!(
+        scrape_course_ids(&mock, filter).await,
+        ["COMP1511Undergraduate"]
+    );
+    assert_eq!(
+        mock.requests(),
+        [
+            format!("/{YEAR}/"),
+            format!("/{YEAR}/COMPKENS.html"),
+            format!("/{YEAR}/COMP1511.html"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn retries_after_truncated_body() {
+    let mock = MockTimetable::start([YEAR]).await;
+    mock.inject(&format!("/{YEAR}/MATH1131.html"), Fault::TruncatedBody);
+    assert_eq!(
+        scrape_course_ids(&mock, ScrapeFilter::default()).await,
+        all_course_ids()
+    );
+    assert_eq!(mock.request_count(&format!("/{YEAR}/MATH1131.html")), 2);
+}
+
+#[tokio::test]
+async fn request_stats_count_requests_retries_and_rate_changes() {
+    let mock = MockTimetable::start([YEAR]).await;
+    mock.inject(&format!("/{YEAR}/COMPKENS.html"), Fault::TruncatedBody);
+    let ctx = ctx(&mock, ScrapeFilter::default());
+    scrape_all_courses(
+        YearToScrape::Year(YEAR),
+        Arc::clone(&ctx),
+        CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+
+    let stats = ctx.stats.snapshot();
+    // The truncated body was retried, so only the other requests returned a page.
+    assert_eq!(stats.requests, mock.requests().len() as u64 - 1);
+    assert_eq!(stats.retries, 1);
+    assert_eq!(stats.rate_changes, 1);
+    assert_eq!(stats.request_rate, Some(66));
+    assert!(stats.bytes_downloaded > 0);
+}
+
+#[tokio::test]
+async fn latest_year_with_data_prefers_future_years() {
+    let year = current_year();
+    let mock = MockTimetable::start([year - 1, year, year + 1]).await;
+    let ctx = ctx(&mock, ScrapeFilter::default());
+    let resolved = YearToScrape::LatestYearWithDataAvailable
+        .resolve_year(&ctx)
+        .await
+        .unwrap();
+    assert_eq!(resolved, year + 1);
+}
+
+#[tokio::test]
+async fn latest_year_with_data_falls_back_to_past_years() {
+    let year = current_year();
+    let mock = MockTimetable::start([year - 5, year - 2]).await;
+    let ctx = ctx(&mock, ScrapeFilter::default());
+    let resolved = YearToScrape::LatestYearWithDataAvailable
+        .resolve_year(&ctx)
+        .await
+        .unwrap();
+    assert_eq!(resolved, year - 2);
+}
+
+#[tokio::test]
+async fn latest_year_with_data_fails_without_any_data() {
+    let mock = MockTimetable::start([]).await;
+    let ctx = ctx(&mock, ScrapeFilter::default());
+    assert!(
+        YearToScrape::LatestYearWithDataAvailable
+            .resolve_year(&ctx)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn scrape_year_streams_courses_with_progress() {
+    let mock = MockTimetable::start([YEAR]).await;
+    let ctx = ctx(&mock, ScrapeFilter::default());
+    let events = scrape_year(YearToScrape::Year(YEAR), ctx, CancellationToken::new())
+        .map(Result::unwrap)
+        .collect::<Vec<_>>()
+        .await;
+
+    assert!(matches!(events[0], ScrapeEvent::YearResolved(YEAR)));
+    let mut subject_codes = Vec::new();
+    let mut course_ids = Vec::new();
+    let mut last_progress = ScrapeProgress::default();
+    for event in &events[1..] {
+        match event {
+            ScrapeEvent::YearResolved(_) => panic!("year resolved twice"),
+            ScrapeEvent::Progress(progress) => {
+                assert!(progress.courses_scraped >= last_progress.courses_scraped);
+                assert!(progress.courses_scraped <= progress.courses_found);
+                last_progress = *progress;
+            }
+            ScrapeEvent::SubjectArea(page) => {
+                assert!(course_ids.is_empty(), "subject areas come first");
+                subject_codes.push(page.subject_code.clone());
+            }
+            ScrapeEvent::Course(course) => course_ids.push(course.course_id.clone()),
+            ScrapeEvent::Skipped(page) => panic!("skipped {page:?} without cancelling"),
+        }
+    }
+    subject_codes.sort();
+    assert_eq!(subject_codes, ["ACCT", "COMP", "MATH"]);
+    course_ids.sort();
+    assert_eq!(course_ids, all_course_ids());
+    assert_eq!(
+        last_progress,
+        ScrapeProgress {
+            subject_areas_found: 3,
+            subject_areas_scraped: 3,
+            courses_found: 6,
+            courses_scraped: 6,
+        }
+    );
+}
+
+#[tokio::test]
+async fn scrape_all_courses_resolves_year_and_sorts() {
+    let year = current_year();
+    let mock = MockTimetable::start([year]).await;
+    let ctx = ctx(&mock, ScrapeFilter::default());
+    let (resolved, courses) = scrape_all_courses(
+        YearToScrape::LatestYearWithDataAvailable,
+        ctx,
+        CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(resolved, year);
+    let course_ids = courses
+        .into_iter()
+        .map(|course| course.course_id)
+        .collect::<Vec<_>>();
+    assert_eq!(course_ids, all_course_ids());
+}
+
+#[tokio::test]
+async fn cancelling_scrape_year_stops_fetching() {
+    let mock = MockTimetable::start([YEAR]).await;
+    mock.set_latency(Duration::from_millis(300));
+    let ctx = ctx(&mock, ScrapeFilter::default());
+    let cancel_token = CancellationToken::new();
+    let mut events = Box::pin(scrape_year(
+        YearToScrape::Year(YEAR),
+        ctx,
+        cancel_token.clone(),
+    ));
+
+    assert!(matches!(
+        events.next().await,
+        Some(Ok(ScrapeEvent::YearResolved(YEAR)))
+    ));
+    cancel_token.cancel();
+    let error = loop {
+        match events.next().await {
+            Some(Ok(_)) => continue,
+            Some(Err(error)) => break error,
+            None => panic!("stream ended without an error"),
+        }
+    };
+    assert!(error.is::<ScrapeCancelled>());
+    assert!(events.next().await.is_none());
+
+    // At most the year page had been requested, and nothing more is.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let requests = mock.requests();
+    assert!(
+        requests.iter().all(|path| *path == format!("/{YEAR}/")),
+        "{:?}",
+        requests
+    );
+}
+
+#[tokio::test]
+async fn cancelling_mid_crawl_reports_skipped_pages() {
+    let mock = MockTimetable::start([YEAR]).await;
+    // Every course page is slow, so they are all in flight when cancelling.
+    // COMP3311 is fetched once per career.
+    for page in COURSE_PAGES.into_iter().chain(["COMP3311.html"]) {
+        mock.inject(
+            &format!("/{YEAR}/{page}"),
+            Fault::Latency(Duration::from_secs(30)),
+        );
+    }
+    let ctx = ctx(&mock, ScrapeFilter::default());
+    let cancel_token = CancellationToken::new();
+    let mut events = Box::pin(scrape_year(
+        YearToScrape::Year(YEAR),
+        ctx,
+        cancel_token.clone(),
+    ));
+
+    let mut skipped = Vec::new();
+    let mut last_progress = ScrapeProgress::default();
+    let error = loop {
+        match events.next().await {
+            Some(Ok(ScrapeEvent::Progress(progress))) => {
+                last_progress = progress;
+                if progress.subject_areas_scraped == progress.subject_areas_found {
+                    cancel_token.cancel();
+                }
+            }
+            Some(Ok(ScrapeEvent::Skipped(page))) => skipped.push(page),
+            Some(Ok(ScrapeEvent::Course(course))) => panic!("scraped {}", course.course_id),
+            Some(Ok(ScrapeEvent::YearResolved(_) | ScrapeEvent::SubjectArea(_))) => {}
+            Some(Err(error)) => break error,
+            None => panic!("stream ended without an error"),
+        }
+    };
+    assert!(error.is::<ScrapeCancelled>());
+    assert_eq!(last_progress.courses_found, 6);
+    assert_eq!(last_progress.courses_scraped, 0);
+
+    let mut skipped_courses = skipped
+        .iter()
+        .map(|page| match page {
+            SkippedPage::Course {
+                course_code,
+                career,
+                ..
+            } => format!("{course_code}{career}"),
+            page => panic!("unexpected skipped page {page:?}"),
+        })
+        .collect::<Vec<_>>();
+    skipped_courses.sort();
+    assert_eq!(skipped_courses, all_course_ids());
+}
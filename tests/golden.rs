@@ -0,0 +1,145 @@
+//! Golden-file tests for the HTML parsers.
+//!
+//! Synthetic timetable pages in `tests/fixtures/timetable`, hand-written in the
+//! layout of timetable.unsw.edu.au, are parsed by the real school area, subject
+//! area and course parsers, and the results are compared against the JSON in
+//! `tests/golden`. After an intended parser change, regenerate
+//! the goldens with `./update-goldens.sh` and review the diff.
+
+use std::{fs, path::PathBuf, sync::Arc};
+
+use serde::Serialize;
+use serde_json::Value;
+use spooderman::{
+    PartialCourse, ScrapeFilter, ScrapingConfig, ScrapingContext, SnapshotFetcher, YearToScrape,
+    parse_partial_courses, parse_partial_pages, scrape_all_courses,
+};
+use tokio_util::sync::CancellationToken;
+
+const TIMETABLE_API_URL: &str = "https://timetable.unsw.edu.au/year/";
+const YEAR: &str = "2025";
+
+fn ctx() -> ScrapingContext {
+    ScrapingContext::with_config(
+        ScrapingConfig::from_timetable_api_url(TIMETABLE_API_URL),
+        ScrapeFilter::default(),
+    )
+    .unwrap()
+}
+
+fn page_url(page: &str) -> String {
+    format!("{}{}", TIMETABLE_API_URL.replace("year", YEAR), page)
+}
+
+/// Reads the synthetic page for `url`, where the year page itself is `index.html`.
+fn fixture(url: &str) -> String {
+    let page = url.rsplit('/').next().filter(|page| !page.is_empty());
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/timetable")
+        .join(YEAR)
+        .join(page.unwrap_or("index.html"));
+    fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("failed to read fixture {}: {}", path.display(), e))
+}
+
+fn assert_golden(name: &str, value: &impl Serialize) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.json", name));
+    let actual = serde_json::to_string_pretty(value).unwrap() + "\n";
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "failed to read golden {} ({}), run ./update-goldens.sh to create it",
+            path.display(),
+            e
+        )
+    });
+    assert!(
+        expected == actual,
+        "{} doesn't match the parser output, run ./update-goldens.sh if this is intended\n--- expected\n{}\n--- actual\n{}",
+        path.display(),
+        expected,
+        actual
+    );
+}
+
+fn parse_subject_area(ctx: &ScrapingContext, url: &str) -> Vec<PartialCourse> {
+    let mut partial_courses = Vec::new();
+    parse_partial_courses(&fixture(url), url, ctx, |partial_course| {
+        partial_courses.push(partial_course);
+        Ok(())
+    })
+    .unwrap();
+    partial_courses
+}
+
+#[test]
+fn school_area_page() {
+    let ctx = ctx();
+    let url = page_url("");
+    let mut partial_pages = Vec::new();
+    parse_partial_pages(&fixture(&url), &url, &ctx, |partial_page| {
+        partial_pages.push(partial_page);
+        Ok(())
+    })
+    .unwrap();
+    assert_golden("school_area", &partial_pages);
+}
+
+#[test]
+fn subject_area_page() {
+    let ctx = ctx();
+    let partial_courses = parse_subject_area(&ctx, &page_url("COMPKENS.html"));
+    assert_golden("subject_area_COMPKENS", &partial_courses);
+}
+
+#[test]
+fn course_pages() {
+    let ctx = ctx();
+    let courses = parse_subject_area(&ctx, &page_url("COMPKENS.html"))
+        .into_iter()
+        .map(|partial_course| {
+            let html = fixture(&partial_course.url);
+            partial_course
+                .complete_from_html(&html, &ctx.selector_profile)
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_golden("courses_COMPKENS", &courses);
+}
+
+#[tokio::test]
+async fn crawl_from_snapshot_matches_goldens() {
+    let filter = ScrapeFilter::new(&["COMP".to_string()], &[], &[], &[], &[]);
+    let fetcher = SnapshotFetcher::new(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/timetable"),
+    );
+    let ctx = Arc::new(
+        ScrapingContext::with_fetcher(
+            ScrapingConfig::from_timetable_api_url(TIMETABLE_API_URL),
+            filter,
+            Arc::new(fetcher),
+        )
+        .unwrap(),
+    );
+    let year = YearToScrape::Year(YEAR.parse().unwrap());
+    let (_, courses) = scrape_all_courses(year, ctx, CancellationToken::new())
+        .await
+        .unwrap();
+    let courses = courses
+        .into_iter()
+        .map(|course| serde_json::to_value(course).unwrap())
+        .collect::<Vec<_>>();
+
+    // Unlike the crawl, which sorts by course id, the golden is in page order.
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/courses_COMPKENS.json");
+    let mut expected: Vec<Value> =
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    let course_id = |course: &Value| course["course_id"].as_str().unwrap().to_string();
+    expected.sort_by_key(course_id);
+    assert_eq!(courses, expected);
+}
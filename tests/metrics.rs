@@ -0,0 +1,111 @@
+//! Tests the Prometheus metrics endpoint, scraping and uploading against the
+//! local mocks. Metrics are process-wide, so these tests live in their own
+//! binary and only check metrics they changed.
+
+mod support;
+
+use std::{sync::Arc, time::Duration};
+
+use spooderman::{
+    RateLimitPolicy, ReadFromMemory, ScrapingContext, YearToScrape, scrape_all_courses,
+    send_batch_data_with_config, serve_metrics,
+};
+use support::{
+    mock_hasuragres::{MockHasuragres, Scripted},
+    mock_timetable::{Fault, MockTimetable},
+};
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+const API_KEY: &str = "test_key";
+
+async fn fetch_metrics() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve_metrics(listener));
+    let response = reqwest::get(format!("http://{addr}/metrics"))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain")
+    );
+    response.text().await.unwrap()
+}
+
+/// The value of the sample `name`, e.g. `requests_total{outcome="ok"}`.
+fn sample(metrics: &str, name: &str) -> f64 {
+    let name = format!("spooderman_{name} ");
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(&name))
+        .unwrap_or_else(|| panic!("{name} missing from:\n{metrics}"))
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn serves_request_rate_limiter_and_upload_metrics() {
+    let timetable = MockTimetable::start([2025]).await;
+    timetable.inject("/2025/COMPKENS.html", Fault::TruncatedBody);
+    let ctx = ScrapingContext::builder()
+        .timetable_api_url(timetable.timetable_api_url())
+        .rate_limit_policy(RateLimitPolicy {
+            pause_after_rate_change: Duration::from_millis(100),
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+    let ctx = Arc::new(ctx);
+    scrape_all_courses(
+        YearToScrape::Year(2025),
+        Arc::clone(&ctx),
+        CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+
+    let hasuragres = MockHasuragres::start(API_KEY).await;
+    hasuragres.script(Scripted::Raw {
+        status: "503 Service Unavailable".to_string(),
+        body: "down for maintenance".to_string(),
+    });
+    let data = ReadFromMemory {
+        courses_vec: Vec::new(),
+        classes_vec: Vec::new(),
+        times_vec: Vec::new(),
+        occurrences_vec: None,
+    };
+    let uploading_config = hasuragres.uploading_config(API_KEY);
+    // Failed uploads are only logged and counted.
+    send_batch_data_with_config(&data, &uploading_config)
+        .await
+        .unwrap();
+    send_batch_data_with_config(&data, &uploading_config)
+        .await
+        .unwrap();
+
+    let metrics = fetch_metrics().await;
+    let ok_requests = sample(&metrics, "requests_total{outcome=\"ok\"}");
+    assert_eq!(ok_requests, ctx.stats.snapshot().requests as f64);
+    assert_eq!(
+        sample(&metrics, "requests_total{outcome=\"body_failed\"}"),
+        1.0
+    );
+    assert_eq!(
+        sample(&metrics, "request_duration_seconds_count{outcome=\"ok\"}"),
+        ok_requests
+    );
+    assert_eq!(sample(&metrics, "rate_limiter_generation"), 1.0);
+    assert_eq!(sample(&metrics, "request_rate"), 66.0);
+    assert_eq!(sample(&metrics, "rate_limiter_pauses_total"), 1.0);
+    assert_eq!(sample(&metrics, "rate_limiter_pause_seconds_total"), 0.1);
+    assert_eq!(
+        sample(&metrics, "batch_inserts_total{outcome=\"http_error\"}"),
+        1.0
+    );
+    assert_eq!(sample(&metrics, "batch_inserts_total{outcome=\"ok\"}"), 1.0);
+}
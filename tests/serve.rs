@@ -0,0 +1,145 @@
+//! Tests the local REST and GraphQL server over the golden COMPKENS courses.
+
+use serde_json::{Value, json};
+use spooderman::{Course, serve_timetable};
+use tokio::net::TcpListener;
+
+async fn serve() -> String {
+    let courses: Vec<Course> =
+        serde_json::from_str(include_str!("golden/courses_COMPKENS.json")).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve_timetable(listener, courses, std::future::pending()));
+    format!("http://{addr}")
+}
+
+async fn get(url: &str) -> (u16, Value) {
+    let response = reqwest::get(url).await.unwrap();
+    (response.status().as_u16(), response.json().await.unwrap())
+}
+
+async fn graphql(base_url: &str, query: &str, variables: Value) -> Value {
+    reqwest::Client::new()
+        .post(format!("{base_url}/graphql"))
+        .json(&json!({ "query": query, "variables": variables }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+fn column<'a>(rows: &'a Value, name: &str) -> Vec<&'a str> {
+    rows.as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row[name].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn serves_courses_classes_and_times() {
+    let base_url = serve().await;
+
+    let (status, courses) = get(&format!("{base_url}/courses/COMP3311")).await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        column(&courses, "course_id"),
+        ["COMP3311Undergraduate", "COMP3311Postgraduate"]
+    );
+    // As in the `TEXT` column of Hasuragres, and the same as over GraphQL.
+    assert_eq!(courses[0]["terms"], r#"["T1"]"#);
+
+    let (status, classes) = get(&format!("{base_url}/courses/COMP9021/classes?term=T3")).await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        column(&classes, "class_id"),
+        ["COMP9021Postgraduate-7001-T3-2025"]
+    );
+
+    let (status, times) = get(&format!(
+        "{base_url}/classes/COMP1511Undergraduate-1001-T1-2025/times"
+    ))
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(column(&times, "day"), ["Mon", "Wed"]);
+    assert_eq!(
+        times[0]["id"],
+        "COMP1511Undergraduate-1001-T1-2025MonAinsworth G03 (K-J17-G03)14:00 - 16:001-5,7-10"
+    );
+}
+
+#[tokio::test]
+async fn unknown_courses_and_classes_are_not_found() {
+    let base_url = serve().await;
+    let (status, error) = get(&format!("{base_url}/courses/COMP0000")).await;
+    assert_eq!(status, 404);
+    assert_eq!(error, json!({ "error": "no course COMP0000" }));
+    let (status, _) = get(&format!("{base_url}/classes/COMP0000-1-T1-2025/times")).await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn answers_hasura_style_graphql_queries() {
+    let base_url = serve().await;
+    let response = graphql(
+        &base_url,
+        r#"query getCourseInfo($courseCode: String!, $term: String!) {
+            courses(where: {course_code: {_eq: $courseCode}}) {
+                course_code
+                course_name
+                terms
+                classes(where: {term: {_eq: $term}, activity: {_neq: "Lecture"}}) {
+                    class_id
+                    activity
+                    times { day time location }
+                }
+            }
+        }"#,
+        json!({ "courseCode": "COMP1511", "term": "T1" }),
+    )
+    .await;
+    assert_eq!(response.get("errors"), None, "{response}");
+    let courses = &response["data"]["courses"];
+    assert_eq!(courses.as_array().unwrap().len(), 1);
+    assert_eq!(courses[0]["course_code"], "COMP1511");
+    assert_eq!(courses[0]["terms"], r#"["T1","T2","T3"]"#);
+    let classes = &courses[0]["classes"];
+    assert_eq!(
+        column(classes, "class_id"),
+        [
+            "COMP1511Undergraduate-1002-T1-2025",
+            "COMP1511Undergraduate-1003-T1-2025"
+        ]
+    );
+    assert_eq!(classes[0]["times"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn graphql_filters_and_paginates_tables() {
+    let base_url = serve().await;
+    let response = graphql(
+        &base_url,
+        r#"{
+            courses(where: {uoc: {_gte: 6}, career: {_in: ["Postgraduate"]}}, limit: 1, offset: 1) {
+                course_id
+            }
+            classes_by_pk(class_id: "COMP3311Postgraduate-5001-T1-2025") { section term }
+            times(where: {class_id: {_eq: "COMP1511Undergraduate-1001-T1-2025"}, day: {_eq: "Wed"}}) {
+                time
+            }
+        }"#,
+        json!({}),
+    )
+    .await;
+    assert_eq!(response.get("errors"), None, "{response}");
+    assert_eq!(
+        response["data"],
+        json!({
+            "courses": [{ "course_id": "COMP9021Postgraduate" }],
+            "classes_by_pk": { "section": "A", "term": "T1" },
+            "times": [{ "time": "10:00 - 12:00" }],
+        })
+    );
+}
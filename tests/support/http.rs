@@ -0,0 +1,81 @@
+//! Just enough HTTP/1.1 for the mock servers: one request per connection, and
+//! the connection is closed after the response.
+
+use std::collections::HashMap;
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Header names are lowercased.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+}
+
+/// Returns `None` if the client hung up or sent something that isn't HTTP.
+pub async fn read_request(stream: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut request_line = String::new();
+    stream.read_line(&mut request_line).await.ok()?;
+    let mut request_line = request_line.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        if stream.read_line(&mut header).await.ok()? == 0 {
+            return None;
+        }
+        if header == "\r\n" {
+            break;
+        }
+        let (name, value) = header.split_once(':')?;
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+
+    let content_length = headers
+        .get("content-length")
+        .map_or(Some(0), |length| length.parse().ok())?;
+    let mut body = vec![0; content_length];
+    stream.read_exact(&mut body).await.ok()?;
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+/// Writes a response whose `Content-Length` is that of `body`, but only sends
+/// the first `sent_len` bytes of it (to simulate a dropped connection).
+pub async fn write_response(
+    stream: &mut BufReader<TcpStream>,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+    sent_len: usize,
+) {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    let stream = stream.get_mut();
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(&body[..sent_len]).await;
+    let _ = stream.shutdown().await;
+}
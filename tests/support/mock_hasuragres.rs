@@ -0,0 +1,204 @@
+//! An in-process stand-in for Hasuragres' `/batch_insert` endpoint.
+//!
+//! Checks the API key and that the request body has the shape Hasuragres
+//! expects (including that every payload row has exactly the declared columns),
+//! records accepted batches, and can be scripted to reject the next requests.
+
+use std::{
+    collections::{BTreeSet, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use spooderman::UploadingConfig;
+use tokio::{
+    io::BufReader,
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use super::http::{Request, read_request, write_response};
+
+/// Hasuragres' contract for the metadata of one table's batch.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Metadata {
+    pub table_name: String,
+    pub columns: Vec<String>,
+    pub sql_up: String,
+    pub sql_down: String,
+    pub write_mode: Option<String>,
+    pub sql_before: Option<String>,
+    pub sql_after: Option<String>,
+    pub dryrun: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchInsert {
+    pub metadata: Metadata,
+    pub payload: Vec<Map<String, Value>>,
+}
+
+/// A scripted response to the next request with a valid API key.
+#[derive(Debug, Clone)]
+pub enum Scripted {
+    /// Fail the insert into `table` with a 400, as Hasuragres does when e.g.
+    /// `sql_up` or a row is rejected by Postgres.
+    FailTable { table: String, error: String },
+    /// Respond with a status and raw body.
+    Raw { status: String, body: String },
+}
+
+#[derive(Default)]
+struct State {
+    api_key: String,
+    scripted: VecDeque<Scripted>,
+    /// Every well-formed, authorised request, in order.
+    batches: Vec<Vec<BatchInsert>>,
+    request_count: usize,
+}
+
+pub struct MockHasuragres {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    server: JoinHandle<()>,
+}
+
+impl MockHasuragres {
+    pub async fn start(api_key: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State {
+            api_key: api_key.to_string(),
+            ..State::default()
+        }));
+        let server = tokio::spawn({
+            let state = Arc::clone(&state);
+            async move {
+                loop {
+                    let Ok((stream, _)) = listener.accept().await else {
+                        return;
+                    };
+                    tokio::spawn(handle_connection(stream, Arc::clone(&state)));
+                }
+            }
+        });
+        Self {
+            addr,
+            state,
+            server,
+        }
+    }
+
+    /// An uploading config pointing at this server.
+    pub fn uploading_config(&self, api_key: &str) -> UploadingConfig {
+        UploadingConfig {
+            hasuragres_url: format!("http://{}", self.addr),
+            hasuragres_api_key: api_key.to_string(),
+        }
+    }
+
+    /// Uses `response` for the next authorised request, instead of inserting.
+    pub fn script(&self, response: Scripted) {
+        self.state.lock().unwrap().scripted.push_back(response);
+    }
+
+    /// The batches of every well-formed, authorised request, in order.
+    pub fn batches(&self) -> Vec<Vec<BatchInsert>> {
+        self.state.lock().unwrap().batches.clone()
+    }
+
+    pub fn request_count(&self) -> usize {
+        self.state.lock().unwrap().request_count
+    }
+}
+
+impl Drop for MockHasuragres {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+fn error(status: &str, message: impl Into<String>) -> (String, String) {
+    (
+        status.to_string(),
+        json!({ "error": message.into() }).to_string(),
+    )
+}
+
+/// Returns the error message if `batch` doesn't follow the contract.
+fn validate(batch: &BatchInsert) -> Result<(), String> {
+    let metadata = &batch.metadata;
+    if metadata.columns.is_empty() {
+        return Err(format!("{}: no columns", metadata.table_name));
+    }
+    if metadata.sql_up.trim().is_empty() || metadata.sql_down.trim().is_empty() {
+        return Err(format!("{}: empty sql_up or sql_down", metadata.table_name));
+    }
+    if let Some(write_mode) = &metadata.write_mode
+        && write_mode != "overwrite"
+        && write_mode != "append"
+    {
+        return Err(format!(
+            "{}: unknown write_mode '{}'",
+            metadata.table_name, write_mode
+        ));
+    }
+    let columns = metadata.columns.iter().collect::<BTreeSet<_>>();
+    for (i, row) in batch.payload.iter().enumerate() {
+        if row.keys().collect::<BTreeSet<_>>() != columns {
+            return Err(format!(
+                "{}: row {} has columns {:?}, expected {:?}",
+                metadata.table_name,
+                i,
+                row.keys().collect::<Vec<_>>(),
+                metadata.columns
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn respond(request: &Request, state: &Mutex<State>) -> (String, String) {
+    let mut state = state.lock().unwrap();
+    state.request_count += 1;
+
+    if request.method != "POST" || request.path != "/batch_insert" {
+        return error("404 Not Found", "not found");
+    }
+    if request.header("X-API-Key") != Some(state.api_key.as_str()) {
+        return error("401 Unauthorized", "Invalid API key");
+    }
+    let batches = match serde_json::from_slice::<Vec<BatchInsert>>(&request.body) {
+        Ok(batches) => batches,
+        Err(e) => return error("422 Unprocessable Entity", e.to_string()),
+    };
+    if let Err(message) = batches.iter().try_for_each(validate) {
+        return error("400 Bad Request", message);
+    }
+
+    match state.scripted.pop_front() {
+        Some(Scripted::FailTable {
+            table,
+            error: message,
+        }) => error("400 Bad Request", format!("{}: {}", table, message)),
+        Some(Scripted::Raw { status, body }) => (status, body),
+        None => {
+            state.batches.push(batches);
+            ("200 OK".to_string(), json!({}).to_string())
+        }
+    }
+}
+
+async fn handle_connection(stream: TcpStream, state: Arc<Mutex<State>>) {
+    let mut stream = BufReader::new(stream);
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+    let (status, body) = respond(&request, &state);
+    let body = body.as_bytes();
+    write_response(&mut stream, &status, "application/json", body, body.len()).await;
+}
@@ -0,0 +1,182 @@
+//! An in-process stand-in for timetable.unsw.edu.au.
+//!
+//! Serves the synthetic pages in `tests/fixtures/timetable` in the same layout as
+//! `TIMETABLE_API_URL`, so that the real scrapers can crawl it end to end. Faults
+//! (slow responses, 429s, truncated bodies) can be injected per page, and every
+//! request is recorded so tests can check what was fetched.
+
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    fs,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use spooderman::Year;
+use tokio::{
+    io::BufReader,
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use super::http::{read_request, write_response};
+
+/// The fixture year whose pages are served for every year the mock has data for.
+const FIXTURE_YEAR: &str = "2025";
+
+/// A fault applied to one response.
+#[derive(Debug, Clone, Copy)]
+pub enum Fault {
+    /// Respond only after the given delay.
+    Latency(Duration),
+    /// Respond with `429 Too Many Requests`.
+    TooManyRequests,
+    /// Send the headers for the full page, but close the connection halfway
+    /// through the body.
+    TruncatedBody,
+}
+
+#[derive(Default)]
+struct State {
+    years: BTreeSet<Year>,
+    latency: Duration,
+    /// Faults to apply to the next requests for a path, in order.
+    faults: HashMap<String, VecDeque<Fault>>,
+    requests: Vec<String>,
+}
+
+pub struct MockTimetable {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    server: JoinHandle<()>,
+}
+
+impl MockTimetable {
+    /// Starts a server with data for `years`. Any other year 404s, like UNSW
+    /// does for years that haven't been published yet.
+    pub async fn start(years: impl IntoIterator<Item = Year>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State {
+            years: years.into_iter().collect(),
+            ..State::default()
+        }));
+        let server = tokio::spawn({
+            let state = Arc::clone(&state);
+            async move {
+                loop {
+                    let Ok((stream, _)) = listener.accept().await else {
+                        return;
+                    };
+                    tokio::spawn(handle_connection(stream, Arc::clone(&state)));
+                }
+            }
+        });
+        Self {
+            addr,
+            state,
+            server,
+        }
+    }
+
+    /// The `TIMETABLE_API_URL` template pointing at this server.
+    pub fn timetable_api_url(&self) -> String {
+        format!("http://{}/year/", self.addr)
+    }
+
+    /// The URL of `page` (e.g. `COMPKENS.html`, or `""` for the year page).
+    pub fn page_url(&self, year: Year, page: &str) -> String {
+        format!("http://{}/{}/{}", self.addr, year, page)
+    }
+
+    /// Delays every response by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Applies `fault` to the next request for `path` (e.g. `/2025/COMP1511.html`).
+    /// Faults injected for the same path are applied to consecutive requests.
+    pub fn inject(&self, path: &str, fault: Fault) {
+        self.state
+            .lock()
+            .unwrap()
+            .faults
+            .entry(path.to_string())
+            .or_default()
+            .push_back(fault);
+    }
+
+    /// The paths of all requests received so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn request_count(&self, path: &str) -> usize {
+        self.requests().iter().filter(|p| *p == path).count()
+    }
+}
+
+impl Drop for MockTimetable {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Returns the fixture page for `path`, or `None` if UNSW would 404.
+fn page_for(path: &str, years: &BTreeSet<Year>) -> Option<String> {
+    let mut segments = path.trim_start_matches('/').splitn(2, '/');
+    let year = segments.next()?.parse::<Year>().ok()?;
+    if !years.contains(&year) {
+        return None;
+    }
+    let page = match segments.next() {
+        None | Some("") => "index.html",
+        Some(page) if !page.contains('/') && !page.starts_with('.') => page,
+        Some(_) => return None,
+    };
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/timetable")
+        .join(FIXTURE_YEAR)
+        .join(page);
+    fs::read_to_string(path).ok()
+}
+
+async fn handle_connection(stream: TcpStream, state: Arc<Mutex<State>>) {
+    let mut stream = BufReader::new(stream);
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+    let path = request.path.as_str();
+
+    let (latency, fault, page) = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(path.to_string());
+        let fault = state
+            .faults
+            .get_mut(path)
+            .and_then(|faults| faults.pop_front());
+        (state.latency, fault, page_for(path, &state.years))
+    };
+
+    tokio::time::sleep(latency).await;
+    if let Some(Fault::Latency(latency)) = fault {
+        tokio::time::sleep(latency).await;
+    }
+
+    let (status, body) = match (fault, page) {
+        (Some(Fault::TooManyRequests), _) => ("429 Too Many Requests", "Too Many Requests".into()),
+        (_, Some(page)) => ("200 OK", page),
+        (_, None) => (
+            "404 Not Found",
+            "<html><body>Not Found</body></html>".into(),
+        ),
+    };
+    let body = body.as_bytes();
+    let sent_len = match fault {
+        Some(Fault::TruncatedBody) => body.len() / 2,
+        _ => body.len(),
+    };
+    write_response(&mut stream, status, "text/html", body, sent_len).await;
+}
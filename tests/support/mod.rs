@@ -0,0 +1,6 @@
+// Each integration test crate only uses part of the support code.
+#![allow(dead_code)]
+
+pub mod http;
+pub mod mock_hasuragres;
+pub mod mock_timetable;
@@ -0,0 +1,109 @@
+//! Checks that log events carry the fields of the spans they were logged in,
+//! by scraping the local timetable mock with a JSON subscriber. Installs a
+//! global subscriber, so this lives in its own test binary.
+
+mod support;
+
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde_json::Value;
+use spooderman::{
+    RateLimitPolicy, ScrapeFilter, ScrapingContext, YearToScrape, scrape_all_courses,
+};
+use support::mock_timetable::{Fault, MockTimetable};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The fields of the span called `name` that `event` was logged in.
+fn span<'a>(event: &'a Value, name: &str) -> &'a Value {
+    event["spans"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|span| span["name"] == name)
+        .unwrap_or_else(|| panic!("no {name} span in {event}"))
+}
+
+#[tokio::test]
+async fn log_events_carry_span_fields() {
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    tracing_subscriber::fmt()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_writer(move || writer.clone())
+        .init();
+
+    let mock = MockTimetable::start([2025]).await;
+    mock.inject("/2025/COMP1511.html", Fault::TruncatedBody);
+    let ctx = ScrapingContext::builder()
+        .timetable_api_url(mock.timetable_api_url())
+        .scrape_filter(ScrapeFilter::new(
+            &[],
+            &["COMP1511".to_string()],
+            &[],
+            &[],
+            &[],
+        ))
+        .rate_limit_policy(RateLimitPolicy {
+            pause_after_rate_change: Duration::from_millis(100),
+            ..RateLimitPolicy::default()
+        })
+        .build()
+        .unwrap();
+    scrape_all_courses(
+        YearToScrape::Year(2025),
+        Arc::new(ctx),
+        CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+
+    let logs = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let events = logs
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let retried = events
+        .iter()
+        .find(|event| {
+            event["fields"]["message"]
+                .as_str()
+                .is_some_and(|message| message.contains("fetching body for COMP1511 failed"))
+        })
+        .expect("the truncated body was logged");
+
+    assert_eq!(span(retried, "year")["year"], 2025);
+    assert_eq!(span(retried, "subject_area")["subject_code"], "COMP");
+    let course = span(retried, "course");
+    assert_eq!(course["course_code"], "COMP1511");
+    assert_eq!(course["career"], "Undergraduate");
+    let request = span(retried, "request");
+    assert_eq!(request["attempt"], 1);
+    assert_eq!(request["request_rate"], "100 req/s");
+    assert_eq!(request["url"], mock.page_url(2025, "COMP1511.html"));
+
+    let finished = events
+        .iter()
+        .find(|event| event["fields"]["message"] == "Finished scraping course COMP1511")
+        .expect("the course was scraped");
+    assert_eq!(span(finished, "course")["course_code"], "COMP1511");
+}
@@ -0,0 +1,5 @@
+#!/bin/bash
+
+# Regenerates the golden files in tests/golden from the synthetic pages in
+# tests/fixtures, review the diff before committing.
+UPDATE_GOLDENS=1 cargo test --test golden